@@ -0,0 +1,53 @@
+//! Operator helpers behind the `greentic-session` admin binary.
+//!
+//! Everything here goes through the [`SessionStore`] trait, so the same inspection steps work
+//! against any backend without knowing its key layout. Index checks and repairs are
+//! [`SessionStore::verify_and_repair`].
+
+use crate::error::{SessionResult, invalid_argument, not_found};
+use crate::listing::SessionListing;
+use crate::store::SessionStore;
+use crate::wait::WaitSpec;
+use greentic_types::SessionKey;
+use std::time::Duration;
+
+/// Fetches a session together with its live waits.
+pub fn show_session<S: SessionStore + ?Sized>(
+    store: &S,
+    key: &SessionKey,
+) -> SessionResult<Option<SessionListing>> {
+    let Some(data) = store.get_session(key)? else {
+        return Ok(None);
+    };
+    let waits = store.list_session_waits(key)?;
+    Ok(Some(SessionListing {
+        key: key.clone(),
+        data,
+        waits,
+    }))
+}
+
+/// Resets the lifetime of every wait on a session to `ttl` and returns the number of waits.
+///
+/// Sessions without waits never expire, so extending one is rejected.
+pub fn extend_session<S: SessionStore + ?Sized>(
+    store: &S,
+    key: &SessionKey,
+    ttl: Duration,
+) -> SessionResult<usize> {
+    let listing = show_session(store, key)?.ok_or_else(|| not_found(key))?;
+    let Some(user_id) = listing.waits.first().map(|wait| wait.user_id.clone()) else {
+        return Err(invalid_argument(format!(
+            "session {} has no waits to extend",
+            key.as_str()
+        )));
+    };
+    let waits: Vec<WaitSpec> = listing
+        .waits
+        .into_iter()
+        .map(|wait| WaitSpec::new(wait.name, wait.scope).with_ttl(ttl))
+        .collect();
+    let ctx = listing.data.tenant_ctx.clone();
+    store.register_waits(&ctx, &user_id, key, listing.data, &waits)?;
+    Ok(waits.len())
+}
@@ -0,0 +1,245 @@
+//! Portable JSONL archives of sessions and their waits.
+//!
+//! An archive starts with a header line followed by one line per session. Payloads are written as
+//! plain [`SessionData`] JSON so archives can move between backends, codecs and format versions.
+//! Wait lifetimes are stored as absolute deadlines, so an import restores the time that was left
+//! when the archive was written, minus the time spent in transit.
+
+use crate::ReplyScope;
+use crate::error::{SessionResult, invalid_argument, io_error, serde_error};
+use crate::history::now_millis;
+use crate::listing::{SessionFilter, SessionListing};
+use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec};
+use greentic_types::{SessionData, SessionKey, UserId};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+/// Value of the `format` header field identifying session archives.
+pub const ARCHIVE_FORMAT: &str = "greentic-session-archive";
+/// Archive layout version written by [`export_sessions`].
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const PAGE_SIZE: usize = 256;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ArchiveLine {
+    Header {
+        format: String,
+        version: u32,
+        exported_at_ms: u64,
+    },
+    Session(Box<ArchivedSession>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedSession {
+    key: SessionKey,
+    data: SessionData,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    waits: Vec<ArchivedWait>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedWait {
+    name: String,
+    user_id: UserId,
+    scope: ReplyScope,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+impl ArchivedWait {
+    fn new(wait: SessionWait, now_ms: u64) -> Self {
+        Self {
+            name: wait.name,
+            user_id: wait.user_id,
+            scope: wait.scope,
+            expires_at_ms: wait.expires_in.map(|remaining| {
+                let remaining = u64::try_from(remaining.as_millis()).unwrap_or(u64::MAX);
+                now_ms.saturating_add(remaining)
+            }),
+        }
+    }
+}
+
+/// Outcome of [`export_sessions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Sessions written.
+    pub sessions: usize,
+    /// Waits written across those sessions.
+    pub waits: usize,
+}
+
+/// Outcome of [`import_sessions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Sessions recreated.
+    pub sessions: usize,
+    /// Waits re-registered, including their user and scope indices.
+    pub waits: usize,
+    /// Sessions left untouched because the key already exists in the target store.
+    pub skipped_existing: usize,
+    /// Sessions dropped because every wait expired before the import.
+    pub skipped_expired: usize,
+}
+
+/// Streams the sessions matching `filter`, or every session of every tenant when `None`, to
+/// `writer` as a JSONL archive.
+pub fn export_sessions<S: SessionStore + ?Sized>(
+    store: &S,
+    filter: Option<&SessionFilter>,
+    mut writer: impl Write,
+) -> SessionResult<ExportReport> {
+    let exported_at_ms = now_millis();
+    write_line(
+        &mut writer,
+        &ArchiveLine::Header {
+            format: ARCHIVE_FORMAT.into(),
+            version: ARCHIVE_VERSION,
+            exported_at_ms,
+        },
+    )?;
+    let filters = match filter {
+        Some(filter) => vec![filter.clone()],
+        None => store
+            .list_tenants()?
+            .into_iter()
+            .map(|(env, tenant)| SessionFilter::tenant(env, tenant))
+            .collect(),
+    };
+    let mut report = ExportReport::default();
+    for filter in filters {
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = store.list_sessions(&filter, cursor.as_deref(), PAGE_SIZE)?;
+            for listing in page.sessions {
+                let waits: Vec<ArchivedWait> = listing
+                    .waits
+                    .into_iter()
+                    .map(|wait| ArchivedWait::new(wait, exported_at_ms))
+                    .collect();
+                report.sessions += 1;
+                report.waits += waits.len();
+                write_line(
+                    &mut writer,
+                    &ArchiveLine::Session(Box::new(ArchivedSession {
+                        key: listing.key,
+                        data: listing.data,
+                        waits,
+                    })),
+                )?;
+            }
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+    }
+    writer.flush().map_err(io_error)?;
+    Ok(report)
+}
+
+/// Recreates the sessions of a JSONL archive in `store`, re-registering their waits with the
+/// lifetime they had left.
+///
+/// Sessions whose key already exists are skipped, so an interrupted import can be re-run.
+pub fn import_sessions<S: SessionStore + ?Sized>(
+    store: &S,
+    reader: impl BufRead,
+) -> SessionResult<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut header_seen = false;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed: ArchiveLine = serde_json::from_str(&line).map_err(|err| {
+            invalid_argument(format!("archive line {} is malformed: {err}", index + 1))
+        })?;
+        match parsed {
+            ArchiveLine::Header {
+                format, version, ..
+            } => {
+                if format != ARCHIVE_FORMAT || version > ARCHIVE_VERSION {
+                    return Err(invalid_argument(format!(
+                        "unsupported archive {format} version {version}"
+                    )));
+                }
+                header_seen = true;
+            }
+            ArchiveLine::Session(_) if !header_seen => {
+                return Err(invalid_argument("archive does not start with a header"));
+            }
+            ArchiveLine::Session(session) => import_session(store, *session, &mut report)?,
+        }
+    }
+    Ok(report)
+}
+
+/// Copies a listed session and its waits into `store`, like a single archive line.
+pub(crate) fn restore_listing<S: SessionStore + ?Sized>(
+    store: &S,
+    listing: SessionListing,
+    report: &mut ImportReport,
+) -> SessionResult<()> {
+    let now_ms = now_millis();
+    let waits = listing
+        .waits
+        .into_iter()
+        .map(|wait| ArchivedWait::new(wait, now_ms))
+        .collect();
+    let session = ArchivedSession {
+        key: listing.key,
+        data: listing.data,
+        waits,
+    };
+    import_session(store, session, report)
+}
+
+fn import_session<S: SessionStore + ?Sized>(
+    store: &S,
+    session: ArchivedSession,
+    report: &mut ImportReport,
+) -> SessionResult<()> {
+    if store.get_session(&session.key)?.is_some() {
+        report.skipped_existing += 1;
+        return Ok(());
+    }
+    let ctx = session.data.tenant_ctx.clone();
+    let Some(user_id) = session.waits.first().map(|wait| wait.user_id.clone()) else {
+        store.insert_session(&ctx, &session.key, session.data)?;
+        report.sessions += 1;
+        return Ok(());
+    };
+    let now_ms = now_millis();
+    let waits: Vec<WaitSpec> = session
+        .waits
+        .into_iter()
+        .filter(|wait| wait.expires_at_ms.is_none_or(|deadline| deadline > now_ms))
+        .map(|wait| {
+            let spec = WaitSpec::new(wait.name, wait.scope);
+            match wait.expires_at_ms {
+                Some(deadline) => spec.with_ttl(Duration::from_millis(deadline - now_ms)),
+                None => spec,
+            }
+        })
+        .collect();
+    if waits.is_empty() {
+        report.skipped_expired += 1;
+        return Ok(());
+    }
+    store.register_waits(&ctx, &user_id, &session.key, session.data, &waits)?;
+    report.sessions += 1;
+    report.waits += waits.len();
+    Ok(())
+}
+
+fn write_line(writer: &mut impl Write, line: &ArchiveLine) -> SessionResult<()> {
+    serde_json::to_writer(&mut *writer, line).map_err(serde_error)?;
+    writer.write_all(b"\n").map_err(io_error)
+}
@@ -0,0 +1,844 @@
+//! Tamper-evident audit trail of session mutations.
+//!
+//! [`AuditedSessionStore`] appends an [`AuditRecord`] to an [`AuditSink`] after every successful
+//! mutation. Records are hash-chained: each one stores the hash of its predecessor and a SHA-256
+//! digest over its own content, so [`verify_chain`] detects edited, reordered or removed entries.
+
+use crate::ReplyScope;
+use crate::dedup::EventDedup;
+use crate::error::{GreenticError, SessionResult, audit_chain_broken, io_error, serde_error};
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::InboxMessage;
+use crate::listing::{SessionFilter, SessionPage};
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::IndexRepairReport;
+use crate::stats::SessionStats;
+use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec};
+use greentic_types::{
+    EnvId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "redis")]
+pub use crate::backends::redis_audit::RedisStreamAuditSink;
+
+/// `prev_hash` of the first record of a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Actor recorded when neither the call nor the store names one.
+const SYSTEM_ACTOR: &str = "system";
+
+/// Attempts made to link a record to a tail other writers keep moving.
+const APPEND_ATTEMPTS: usize = 8;
+
+/// Bytes read per step when [`JsonlAuditSink`] looks for the last line of its file.
+const TAIL_CHUNK: u64 = 4096;
+
+/// Kind of mutation captured by an [`AuditRecord`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    /// A session was created, directly or by registering waits under a new key.
+    Created,
+    /// The payload of a session was replaced.
+    Updated,
+    /// Waits were registered on an existing session.
+    Suspended,
+    /// Waits were cleared so the session can resume.
+    Resumed,
+    /// A session was deleted.
+    Deleted,
+    /// A session was reclaimed after its lifetime elapsed.
+    Expired,
+    /// Every session of a tenant or user was purged.
+    Purged,
+    /// A session payload was restored from its history.
+    RolledBack,
+}
+
+/// One entry of the audit chain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the chain, starting at zero.
+    pub sequence: u64,
+    /// Wall-clock time of the mutation, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Kind of mutation.
+    pub operation: AuditOperation,
+    /// Session affected; `None` for tenant or user purges.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<SessionKey>,
+    /// Environment of the affected session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<EnvId>,
+    /// Tenant of the affected session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<TenantId>,
+    /// Team of the affected session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<TeamId>,
+    /// User the affected session is bound to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserId>,
+    /// Identity that performed the mutation.
+    pub actor: String,
+    /// Cursor before the mutation, when the mutation itself leaves it unchanged.
+    ///
+    /// Replacing writes do not read the session first, so their previous cursor is the
+    /// `new_cursor` of the session's preceding record in the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_cursor: Option<SessionCursor>,
+    /// Cursor after the mutation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_cursor: Option<SessionCursor>,
+    /// Operation specific detail, such as wait names or purge counts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Hash of the preceding record, or [`GENESIS_HASH`].
+    pub prev_hash: String,
+    /// SHA-256 digest over every other field of this record.
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// Recomputes the digest stored in [`AuditRecord::hash`].
+    pub fn compute_hash(&self) -> SessionResult<String> {
+        let mut unsigned = self.clone();
+        unsigned.hash = String::new();
+        let encoded = serde_json::to_vec(&unsigned).map_err(serde_error)?;
+        Ok(hex::encode(Sha256::digest(&encoded)))
+    }
+}
+
+/// Checks that `records` form an intact chain starting at the genesis record.
+///
+/// Fails with [`crate::ErrorCode::Conflict`] naming the first sequence number that does not link
+/// to its predecessor or whose content no longer matches its hash.
+pub fn verify_chain(records: &[AuditRecord]) -> SessionResult<()> {
+    let mut expected_prev = GENESIS_HASH.to_string();
+    for (position, record) in records.iter().enumerate() {
+        if record.sequence != position as u64 {
+            return Err(audit_chain_broken(
+                position as u64,
+                format!("found sequence {}", record.sequence),
+            ));
+        }
+        if record.prev_hash != expected_prev {
+            return Err(audit_chain_broken(
+                record.sequence,
+                "previous hash does not match the preceding record",
+            ));
+        }
+        if record.compute_hash()? != record.hash {
+            return Err(audit_chain_broken(
+                record.sequence,
+                "record content does not match its hash",
+            ));
+        }
+        expected_prev = record.hash.clone();
+    }
+    Ok(())
+}
+
+/// Append-only destination of audit records.
+pub trait AuditSink: Send + Sync + 'static {
+    /// Appends a record after the current tail.
+    fn append(&self, record: &AuditRecord) -> SessionResult<()>;
+
+    /// Appends `record` only if it links to the current tail: its `prev_hash` is the hash of the
+    /// last record, or [`GENESIS_HASH`] for an empty sink. Returns `false`, appending nothing,
+    /// when another writer moved the tail first.
+    ///
+    /// The check and the append must be one atomic step for stores on several hosts to share the
+    /// sink. The default appends unchecked, which only keeps the chain intact with a single
+    /// writing store.
+    fn append_linked(&self, record: &AuditRecord) -> SessionResult<bool> {
+        self.append(record)?;
+        Ok(true)
+    }
+
+    /// Returns the most recently appended record, used to continue an existing chain.
+    fn last_record(&self) -> SessionResult<Option<AuditRecord>>;
+
+    /// Returns every record in append order.
+    fn read_all(&self) -> SessionResult<Vec<AuditRecord>>;
+}
+
+/// Sink keeping records in memory, for tests.
+#[derive(Default)]
+pub struct InMemoryAuditSink {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl InMemoryAuditSink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AuditSink for InMemoryAuditSink {
+    fn append(&self, record: &AuditRecord) -> SessionResult<()> {
+        self.records.lock().push(record.clone());
+        Ok(())
+    }
+
+    fn append_linked(&self, record: &AuditRecord) -> SessionResult<bool> {
+        let mut records = self.records.lock();
+        let tail = records
+            .last()
+            .map_or(GENESIS_HASH, |last| last.hash.as_str());
+        if tail != record.prev_hash {
+            return Ok(false);
+        }
+        records.push(record.clone());
+        Ok(true)
+    }
+
+    fn last_record(&self) -> SessionResult<Option<AuditRecord>> {
+        Ok(self.records.lock().last().cloned())
+    }
+
+    fn read_all(&self) -> SessionResult<Vec<AuditRecord>> {
+        Ok(self.records.lock().clone())
+    }
+}
+
+/// Sink appending one JSON record per line to a file.
+///
+/// [`AuditSink::append_linked`] checks the file's last line under an exclusive file lock, so
+/// stores in several processes on one host can share the file.
+pub struct JsonlAuditSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlAuditSink {
+    /// Opens `path` for appending, creating it when missing.
+    pub fn open(path: impl AsRef<Path>) -> SessionResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(io_error)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn write_line(file: &mut File, record: &AuditRecord) -> SessionResult<()> {
+        let mut line = serde_json::to_vec(record).map_err(serde_error)?;
+        line.push(b'\n');
+        file.write_all(&line).map_err(io_error)?;
+        file.flush().map_err(io_error)
+    }
+
+    /// Reads the last non-blank line of the file, scanning backwards from its end.
+    fn last_line(&self) -> SessionResult<Option<String>> {
+        let mut file = File::open(&self.path).map_err(io_error)?;
+        let mut end = file.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let mut tail = Vec::new();
+        while end > 0 {
+            let start = end.saturating_sub(TAIL_CHUNK);
+            let mut chunk = vec![0; usize::try_from(end - start).unwrap_or_default()];
+            file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+            file.read_exact(&mut chunk).map_err(io_error)?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+            end = start;
+            let trimmed = tail.trim_ascii_end();
+            if let Some(newline) = trimmed.iter().rposition(|byte| *byte == b'\n') {
+                return Ok(Some(
+                    String::from_utf8_lossy(&trimmed[newline + 1..]).into_owned(),
+                ));
+            }
+        }
+        let trimmed = tail.trim_ascii_end();
+        Ok((!trimmed.is_empty()).then(|| String::from_utf8_lossy(trimmed).into_owned()))
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn append(&self, record: &AuditRecord) -> SessionResult<()> {
+        Self::write_line(&mut self.file.lock(), record)
+    }
+
+    fn append_linked(&self, record: &AuditRecord) -> SessionResult<bool> {
+        let mut file = self.file.lock();
+        File::lock(&file).map_err(io_error)?;
+        let appended = self.last_record().and_then(|last| {
+            let tail = last
+                .as_ref()
+                .map_or(GENESIS_HASH, |last| last.hash.as_str());
+            if tail != record.prev_hash {
+                return Ok(false);
+            }
+            Self::write_line(&mut file, record).map(|()| true)
+        });
+        File::unlock(&file).map_err(io_error)?;
+        appended
+    }
+
+    fn last_record(&self) -> SessionResult<Option<AuditRecord>> {
+        self.last_line()?
+            .map(|line| serde_json::from_str(&line).map_err(serde_error))
+            .transpose()
+    }
+
+    fn read_all(&self) -> SessionResult<Vec<AuditRecord>> {
+        let file = File::open(&self.path).map_err(io_error)?;
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(io_error)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line).map_err(serde_error)?);
+        }
+        Ok(records)
+    }
+}
+
+/// Audit record that could not be appended after its mutation was applied.
+#[derive(Debug)]
+pub struct AuditFailure<'a> {
+    /// Kind of mutation that was applied without a record.
+    pub operation: AuditOperation,
+    /// Session affected, if any.
+    pub session_key: Option<&'a SessionKey>,
+    /// Error returned by the sink.
+    pub error: &'a GreenticError,
+}
+
+type FailureHook = Arc<dyn Fn(&AuditFailure<'_>) + Send + Sync>;
+
+struct ChainHead {
+    next_sequence: u64,
+    last_hash: String,
+}
+
+impl ChainHead {
+    /// Continues after the last record of `sink`.
+    fn resume(sink: &dyn AuditSink) -> SessionResult<Self> {
+        Ok(match sink.last_record()? {
+            Some(last) => Self {
+                next_sequence: last.sequence + 1,
+                last_hash: last.hash,
+            },
+            None => Self {
+                next_sequence: 0,
+                last_hash: GENESIS_HASH.to_string(),
+            },
+        })
+    }
+}
+
+/// [`SessionStore`] wrapper appending a hash-chained [`AuditRecord`] for every successful
+/// mutation.
+///
+/// The actor is taken from the tenant context of the call: the impersonating actor when present,
+/// otherwise the user. Calls without a user are attributed to the store's default actor. Records
+/// are appended after the wrapped store succeeded and are built from the call and its result, not
+/// from separate reads. A sink failure does not turn the applied mutation into an error: the
+/// mutation's result is returned, the failure is counted in [`Self::failed_appends`] and passed to
+/// the hook set with [`Self::on_failure`].
+///
+/// Records are appended with [`AuditSink::append_linked`]. When another store sharing the sink
+/// appended first, the store reloads the tail and links the record again, so stores on several
+/// hosts keep one chain as long as the sink checks the link atomically. The chain head is only
+/// locked around each append, not while the tail is reloaded.
+pub struct AuditedSessionStore<S> {
+    inner: S,
+    sink: Arc<dyn AuditSink>,
+    default_actor: String,
+    head: Mutex<ChainHead>,
+    failed_appends: AtomicU64,
+    failure_hook: Option<FailureHook>,
+}
+
+impl<S: SessionStore> AuditedSessionStore<S> {
+    /// Wraps `inner`, continuing the chain already present in `sink`.
+    pub fn new(inner: S, sink: Arc<dyn AuditSink>) -> SessionResult<Self> {
+        let head = ChainHead::resume(sink.as_ref())?;
+        Ok(Self {
+            inner,
+            sink,
+            default_actor: SYSTEM_ACTOR.to_string(),
+            head: Mutex::new(head),
+            failed_appends: AtomicU64::new(0),
+            failure_hook: None,
+        })
+    }
+
+    /// Registers a hook invoked when a record cannot be appended after its mutation applied.
+    pub fn on_failure(mut self, hook: impl Fn(&AuditFailure<'_>) + Send + Sync + 'static) -> Self {
+        self.failure_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Number of records lost to sink failures since the store was created.
+    pub fn failed_appends(&self) -> u64 {
+        self.failed_appends.load(Ordering::Relaxed)
+    }
+
+    /// Sets the actor recorded for calls that carry no user, such as purges.
+    pub fn with_default_actor(mut self, actor: impl Into<String>) -> Self {
+        self.default_actor = actor.into();
+        self
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the sink records are appended to.
+    pub fn sink(&self) -> &Arc<dyn AuditSink> {
+        &self.sink
+    }
+
+    fn actor(&self, ctx: Option<&TenantCtx>) -> String {
+        ctx.and_then(|ctx| {
+            ctx.impersonation
+                .as_ref()
+                .map(|impersonation| impersonation.actor_id.as_str().to_string())
+                .or_else(|| {
+                    ctx.user_id
+                        .as_ref()
+                        .or(ctx.user.as_ref())
+                        .map(|user| user.as_str().to_string())
+                })
+        })
+        .unwrap_or_else(|| self.default_actor.clone())
+    }
+
+    /// Appends the record for an applied mutation, reporting a sink failure to the hook.
+    fn record(&self, entry: Entry<'_>) {
+        let (operation, key) = (entry.operation, entry.key);
+        if let Err(error) = self.append(entry) {
+            self.failed_appends.fetch_add(1, Ordering::Relaxed);
+            if let Some(hook) = &self.failure_hook {
+                hook(&AuditFailure {
+                    operation,
+                    session_key: key,
+                    error: &error,
+                });
+            }
+        }
+    }
+
+    fn append(&self, entry: Entry<'_>) -> SessionResult<()> {
+        let ctx = entry.ctx;
+        let mut record = AuditRecord {
+            sequence: 0,
+            timestamp_ms: now_millis(),
+            operation: entry.operation,
+            session_key: entry.key.cloned(),
+            env: ctx.map(|ctx| ctx.env.clone()),
+            tenant: ctx.map(|ctx| ctx.tenant_id.clone()),
+            team: ctx.and_then(|ctx| ctx.team_id.clone().or_else(|| ctx.team.clone())),
+            user: ctx.and_then(|ctx| ctx.user_id.clone().or_else(|| ctx.user.clone())),
+            actor: self.actor(ctx.filter(|_| entry.attributed)),
+            previous_cursor: entry.previous.map(|data| data.cursor.clone()),
+            new_cursor: entry.current.map(|data| data.cursor.clone()),
+            detail: entry.detail,
+            prev_hash: String::new(),
+            hash: String::new(),
+        };
+        for _ in 0..APPEND_ATTEMPTS {
+            {
+                let mut head = self.head.lock();
+                record.sequence = head.next_sequence;
+                record.prev_hash = head.last_hash.clone();
+                record.hash = record.compute_hash()?;
+                if self.sink.append_linked(&record)? {
+                    head.next_sequence = record.sequence + 1;
+                    head.last_hash = record.hash;
+                    return Ok(());
+                }
+            }
+            let tail = ChainHead::resume(self.sink.as_ref())?;
+            let mut head = self.head.lock();
+            // A local append may already have moved past the reloaded tail.
+            if tail.next_sequence > head.next_sequence {
+                *head = tail;
+            }
+        }
+        Err(audit_chain_broken(
+            record.sequence,
+            "other writers kept appending to the sink",
+        ))
+    }
+}
+
+/// Inputs of one audit record.
+struct Entry<'a> {
+    operation: AuditOperation,
+    key: Option<&'a SessionKey>,
+    ctx: Option<&'a TenantCtx>,
+    previous: Option<&'a SessionData>,
+    current: Option<&'a SessionData>,
+    detail: Option<String>,
+    attributed: bool,
+}
+
+impl<'a> Entry<'a> {
+    fn new(operation: AuditOperation, key: Option<&'a SessionKey>) -> Self {
+        Self {
+            operation,
+            key,
+            ctx: None,
+            previous: None,
+            current: None,
+            detail: None,
+            attributed: true,
+        }
+    }
+
+    fn ctx(mut self, ctx: Option<&'a TenantCtx>) -> Self {
+        self.ctx = ctx;
+        self
+    }
+
+    fn previous(mut self, previous: Option<&'a SessionData>) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    fn current(mut self, current: Option<&'a SessionData>) -> Self {
+        self.current = current;
+        self
+    }
+
+    /// Attributes the record to the store's default actor instead of the context user.
+    fn unattributed(mut self) -> Self {
+        self.attributed = false;
+        self
+    }
+
+    fn detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+fn purge_detail(report: &PurgeReport) -> String {
+    format!(
+        "sessions={}, scope_pointers={}, user_wait_sets={}",
+        report.sessions, report.scope_pointers, report.user_wait_sets
+    )
+}
+
+impl<S: SessionStore> SessionStore for AuditedSessionStore<S> {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        let key = self.inner.create_session(ctx, data.clone())?;
+        self.record(
+            Entry::new(AuditOperation::Created, Some(&key))
+                .ctx(Some(ctx))
+                .current(Some(&data)),
+        );
+        Ok(key)
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.inner.insert_session(ctx, key, data.clone())?;
+        self.record(
+            Entry::new(AuditOperation::Created, Some(key))
+                .ctx(Some(ctx))
+                .current(Some(&data)),
+        );
+        Ok(())
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        self.inner.get_session(key)
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.inner.update_session(key, data.clone())?;
+        self.record(
+            Entry::new(AuditOperation::Updated, Some(key))
+                .ctx(Some(&data.tenant_ctx))
+                .current(Some(&data)),
+        );
+        Ok(())
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        // Only the tenant context is taken from this read; the fence keeps it from changing.
+        let stored = self.inner.get_session(key)?;
+        self.inner.remove_session(key)?;
+        self.record(
+            Entry::new(AuditOperation::Deleted, Some(key))
+                .ctx(stored.as_ref().map(|data| &data.tenant_ctx)),
+        );
+        Ok(())
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        self.inner.migrate_all()
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        let data = self.inner.patch_session(key, patch)?;
+        self.record(
+            Entry::new(AuditOperation::Updated, Some(key))
+                .ctx(Some(&data.tenant_ctx))
+                .current(Some(&data))
+                .detail(format!("patch={}", patch.describe())),
+        );
+        Ok(data)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        self.inner.list_session_versions(key)
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        self.inner.get_session_version(key, version)
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        self.inner.rollback_session(key, version)?;
+        // Archived versions never change, so the restored payload can be read back afterwards.
+        let restored = self.inner.get_session_version(key, version).ok().flatten();
+        self.record(
+            Entry::new(AuditOperation::RolledBack, Some(key))
+                .ctx(restored.as_ref().map(|data| &data.tenant_ctx))
+                .current(restored.as_ref())
+                .detail(format!("version={version}")),
+        );
+        Ok(())
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        let created = self
+            .inner
+            .register_waits(ctx, user_id, session_key, data.clone(), waits)?;
+        let operation = if created {
+            AuditOperation::Created
+        } else {
+            AuditOperation::Suspended
+        };
+        let names: Vec<&str> = waits.iter().map(|wait| wait.name.as_str()).collect();
+        self.record(
+            Entry::new(operation, Some(session_key))
+                .ctx(Some(ctx))
+                .current(Some(&data))
+                .detail(format!("waits={}", names.join(","))),
+        );
+        Ok(created)
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        self.inner.list_session_waits(key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        let cleared = self.inner.clear_session_wait(key, name)?;
+        if cleared {
+            let current = self.inner.get_session(key)?;
+            self.record(
+                Entry::new(AuditOperation::Resumed, Some(key))
+                    .ctx(current.as_ref().map(|data| &data.tenant_ctx))
+                    .previous(current.as_ref())
+                    .current(current.as_ref())
+                    .detail(format!("waits={name}")),
+            );
+        }
+        Ok(cleared)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        let cleared = self.inner.clear_session_waits(key)?;
+        if !cleared.is_empty() {
+            let current = self.inner.get_session(key)?;
+            self.record(
+                Entry::new(AuditOperation::Resumed, Some(key))
+                    .ctx(current.as_ref().map(|data| &data.tenant_ctx))
+                    .previous(current.as_ref())
+                    .current(current.as_ref())
+                    .detail(format!("waits={}", cleared.join(","))),
+            );
+        }
+        Ok(cleared)
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.inner.find_wait_by_scope(ctx, user_id, scope)
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        self.inner.list_waits_for_user(ctx, user_id)
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        let removed = self.inner.clear_wait(ctx, user_id, scope)?;
+        if let Some(key) = &removed {
+            self.record(
+                Entry::new(AuditOperation::Deleted, Some(key))
+                    .ctx(Some(ctx))
+                    .detail(format!("scope={}", scope.scope_hash())),
+            );
+        }
+        Ok(removed)
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        self.inner.list_sessions(filter, cursor, limit)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        self.inner.list_tenants()
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let report = self.inner.purge_tenant(env, tenant)?;
+        let scope = TenantCtx::new(env.clone(), tenant.clone());
+        self.record(
+            Entry::new(AuditOperation::Purged, None)
+                .ctx(Some(&scope))
+                .unattributed()
+                .detail(purge_detail(&report)),
+        );
+        Ok(report)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let report = self.inner.purge_user(env, tenant, user)?;
+        let mut scope = TenantCtx::new(env.clone(), tenant.clone());
+        scope.user_id = Some(user.clone());
+        // The purged user is the subject of the record, not its actor.
+        self.record(
+            Entry::new(AuditOperation::Purged, None)
+                .ctx(Some(&scope))
+                .unattributed()
+                .detail(purge_detail(&report)),
+        );
+        Ok(report)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        let expired = self.inner.purge_expired()?;
+        for key in &expired {
+            self.record(Entry::new(AuditOperation::Expired, Some(key)));
+        }
+        Ok(expired)
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        self.inner.verify_and_repair(repair)
+    }
+
+    fn health(&self) -> StoreHealth {
+        self.inner.health()
+    }
+
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        self.inner.stats(filter)
+    }
+
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        self.inner.record_event_once(ctx, event_id, ttl)
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        self.inner.bind_event_session(ctx, event_id, key)
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        self.inner.inbox_push(key, payload, ttl)
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_peek(key)
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_pop(key)
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        self.inner.inbox_len(key)
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        self.inner.find_by_user(ctx, user)
+    }
+}
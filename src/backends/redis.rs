@@ -1,12 +1,340 @@
 use crate::ReplyScope;
-use crate::error::{SessionResult, invalid_argument, not_found, redis_error, serde_error};
+use crate::dedup::{EventDedup, validate_event_id, validate_event_ttl};
+use crate::error::{
+    SessionResult, concurrent_modification, fence_rejected, invalid_argument, not_found,
+    redis_error, serde_error, session_exists, unsupported_format, version_not_found,
+};
+use crate::fence::{normalize_team, normalize_user};
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::{InboxMessage, validate_inbox_ttl};
+use crate::listing::{SessionFilter, SessionListing, SessionPage, validate_page_limit};
+use crate::metrics::record_stale_index_entries;
+use crate::options::SessionStoreOptions;
+use crate::patch::{ContextPatch, SessionPatch};
+use crate::purge::PurgeReport;
+use crate::repair::{
+    DanglingScope, IndexIssue, IndexIssueKind, IndexRepairReport, OrphanedUserWait,
+};
+use crate::stats::{SessionStats, TTL_BOUNDS, TtlBuckets};
 use crate::store::SessionStore;
-use greentic_types::{SessionData, SessionKey, TenantCtx, UserId};
-use redis::{Client, Commands, Connection};
-use std::time::Duration;
+use crate::wait::{SessionWait, WaitSpec, session_ttl, validate_waits};
+use greentic_types::{EnvId, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId};
+use redis::{
+    Client, Commands, Connection, Pipeline, RedisError, Script, ScriptInvocation, ServerErrorKind,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 const DEFAULT_NAMESPACE: &str = "greentic:session";
+const PURGE_BATCH: usize = 256;
+
+/// Lua helper shared by the scripts that archive a payload, so versions are numbered in one
+/// place.
+///
+/// `archive` pushes `payload` onto the `history` list as the version after the newest one,
+/// recorded at `recorded_at`, trims the list to `depth` and expires it after `ttl` milliseconds,
+/// or persists it when `ttl` is not positive.
+macro_rules! archive_lua {
+    () => {
+        r"
+local function archive(history, payload, depth, recorded_at, ttl)
+  local version = 1
+  local newest = redis.call('LINDEX', history, 0)
+  if newest then
+    version = tonumber(string.match(newest, '^(%d+):')) + 1
+  end
+  redis.call('LPUSH', history, string.format('%d:%s:', version, recorded_at) .. payload)
+  redis.call('LTRIM', history, 0, depth - 1)
+  if ttl > 0 then
+    redis.call('PEXPIRE', history, ttl)
+  else
+    redis.call('PERSIST', history)
+  end
+end
+"
+    };
+}
+
+/// Lua helper shared by the scripts that replace a payload, so its size reaches the stats
+/// counters in the same step as the write.
+///
+/// `record_payload` moves the contribution of session `member` in the `contributions` hash, and
+/// the `payload_bytes` total with it, to `bytes`. Sessions without a contribution are left alone,
+/// as [`STATS_SCRIPT`] does for partial updates.
+macro_rules! stats_lua {
+    () => {
+        r"
+local function record_payload(totals, contributions, member, bytes)
+  local previous = redis.call('HGET', contributions, member)
+  if not previous then
+    return
+  end
+  local sep = string.find(previous, ':', 1, true)
+  redis.call('HINCRBY', totals, 'payload_bytes', bytes - tonumber(string.sub(previous, 1, sep - 1)))
+  redis.call('HSET', contributions, member, bytes .. string.sub(previous, sep))
+end
+"
+    };
+}
+
+/// Replaces a session payload only if it still hashes to `ARGV[1]`.
+///
+/// When `ARGV[3]`, the history depth, is not `0`, the replaced payload is archived as the newest
+/// version, recorded at `ARGV[4]`, and the list shares the entry's lifetime. `KEYS[3]` and
+/// `KEYS[4]` are the tenant's stats totals and contributions, updated for session `ARGV[5]`, and
+/// the remaining keys the listing indices it is added to, so sessions written before they were
+/// indexed are picked up by their next write. Returns `1` on success, `0` when the payload
+/// changed and `-1` when the session is gone.
+const COMPARE_AND_SET_SCRIPT: &str = concat!(
+    archive_lua!(),
+    stats_lua!(),
+    r"
+local current = redis.call('GET', KEYS[1])
+if not current then
+  return -1
+end
+if redis.sha1hex(current) ~= ARGV[1] then
+  return 0
+end
+redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+local depth = tonumber(ARGV[3])
+if depth > 0 then
+  archive(KEYS[2], current, depth, ARGV[4], redis.call('PTTL', KEYS[1]))
+end
+record_payload(KEYS[3], KEYS[4], ARGV[5], string.len(ARGV[2]))
+for i = 5, #KEYS do
+  redis.call('ZADD', KEYS[i], 0, ARGV[5])
+end
+return 1
+"
+);
+
+/// Applies an RFC 7396 merge patch and/or a new cursor to a JSON-codec payload in place.
+///
+/// `KEYS` are the entry and the history list. `ARGV[1]` is the merge patch and `ARGV[2]` the
+/// cursor, as JSON or empty to leave that part alone; `ARGV[3]` is the format version written by
+/// the store, `ARGV[4]` the size up to which the payload passes every limit, `-1` for none,
+/// `ARGV[5]` and `ARGV[6]` the history depth and recording time, `ARGV[7]` the session key and
+/// `ARGV[8]` the namespace. The session's tenant is only known once its payload is decoded, so
+/// its stats counters and listing indices are named from the namespace inside the script rather
+/// than passed as `KEYS`. Returns `{'written', payload}`, `{'missing'}`,
+/// or `{'fallback'}` when the payload must be patched client-side: it is not JSON in the current
+/// format version, it holds `[]` or a number of 15 or more digits, which cjson would turn into
+/// `{}` or round, or the patched payload outgrows the ceiling and needs the exact checks.
+const MERGE_PATCH_SCRIPT: &str = concat!(
+    archive_lua!(),
+    stats_lua!(),
+    r"
+local function exact(text)
+  return not string.find(text, '%[%s*%]') and not string.find(text, '%d' .. string.rep('%d', 14))
+end
+local function is_object(value)
+  return type(value) == 'table' and (next(value) == nil or type(next(value)) == 'string')
+end
+local function merge(target, patch)
+  if not is_object(patch) then
+    return patch
+  end
+  if not is_object(target) then
+    target = {}
+  end
+  for name, value in pairs(patch) do
+    if value == cjson.null then
+      target[name] = nil
+    else
+      target[name] = merge(target[name], value)
+    end
+  end
+  return target
+end
+local stored = redis.call('GET', KEYS[1])
+if not stored then
+  return {'missing'}
+end
+if string.sub(stored, 1, 1) ~= 'J' or not (exact(stored) and exact(ARGV[1]) and exact(ARGV[2])) then
+  return {'fallback'}
+end
+local decoded, envelope = pcall(cjson.decode, string.sub(stored, 2))
+if not decoded or type(envelope) ~= 'table' or type(envelope.data) ~= 'table'
+    or envelope.format_version ~= tonumber(ARGV[3]) then
+  return {'fallback'}
+end
+local data = envelope.data
+local ctx = data.tenant_ctx
+if type(ctx) ~= 'table' or type(ctx.env) ~= 'string' or type(ctx.tenant_id) ~= 'string' then
+  return {'fallback'}
+end
+if ARGV[1] ~= '' then
+  if type(data.context_json) ~= 'string' or not string.find(data.context_json, '%S') then
+    return {'fallback'}
+  end
+  local parsed, context = pcall(cjson.decode, data.context_json)
+  if not parsed then
+    return {'fallback'}
+  end
+  data.context_json = cjson.encode(merge(context, cjson.decode(ARGV[1])))
+end
+if ARGV[2] ~= '' then
+  data.cursor = cjson.decode(ARGV[2])
+end
+local payload = 'J' .. cjson.encode(envelope)
+local ceiling = tonumber(ARGV[4])
+if ceiling >= 0 and string.len(payload) > ceiling then
+  return {'fallback'}
+end
+redis.call('SET', KEYS[1], payload, 'KEEPTTL')
+local depth = tonumber(ARGV[5])
+if depth > 0 then
+  archive(KEYS[2], stored, depth, ARGV[6], redis.call('PTTL', KEYS[1]))
+end
+local tenant = ctx.env .. ':' .. ctx.tenant_id
+local totals = ARGV[8] .. ':stats:' .. tenant
+record_payload(totals, totals .. ':sessions', ARGV[7], string.len(payload))
+redis.call('ZADD', ARGV[8] .. ':index:tenant:' .. tenant, 0, ARGV[7])
+local team = ctx.team_id
+if type(team) ~= 'string' then
+  team = ctx.team
+end
+if type(team) == 'string' then
+  redis.call('ZADD', ARGV[8] .. ':index:team:' .. tenant .. ':' .. team, 0, ARGV[7])
+end
+return {'written', payload}
+"
+);
+
+/// Archives `ARGV[1]` onto the history list `KEYS[1]`, for writes that replace a payload inside a
+/// transaction. `ARGV` continues with the depth, the recording time and the list's lifetime in
+/// milliseconds, `0` keeping it persistent.
+///
+/// Transactions send it with `EVAL` rather than by hash: a script cache flushed mid-transaction
+/// would otherwise drop the archived version while the rest of the transaction applies.
+const ARCHIVE_SCRIPT: &str = concat!(
+    archive_lua!(),
+    r"
+archive(KEYS[1], ARGV[1], tonumber(ARGV[2]), ARGV[3], tonumber(ARGV[4]))
+return 1
+"
+);
+
+/// Deletes `KEYS[1]` only while it still holds `ARGV[1]`, removing it from the sorted set
+/// `KEYS[2]` as well when one is given.
+const COMPARE_AND_DELETE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  if KEYS[2] then
+    redis.call('ZREM', KEYS[2], KEYS[1])
+  end
+  return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Applies a change to one session's contribution to the per-tenant stats counters.
+///
+/// `KEYS` are the totals hash, the per-session contribution hash and the expiry sorted set;
+/// `ARGV[1]` is the session key. `ARGV[2]` is the payload size or `del` to drop the session,
+/// `ARGV[3]` the wait count and `ARGV[4]` the expiry in epoch milliseconds or `+inf`; an empty
+/// argument keeps the recorded value. Sessions are only picked up by a change carrying all three.
+/// A backfill passes the session's entry as `KEYS[4]` and `fill` as `ARGV[5]`, and only records
+/// sessions that still exist and have no contribution yet.
+const STATS_SCRIPT: &str = r"
+local previous = redis.call('HGET', KEYS[2], ARGV[1])
+local bytes, waits = 0, 0
+if previous then
+  local sep = string.find(previous, ':', 1, true)
+  bytes = tonumber(string.sub(previous, 1, sep - 1))
+  waits = tonumber(string.sub(previous, sep + 1))
+end
+if ARGV[5] == 'fill' and (previous or redis.call('EXISTS', KEYS[4]) == 0) then
+  return 0
+end
+if ARGV[2] == 'del' then
+  if previous then
+    redis.call('HDEL', KEYS[2], ARGV[1])
+    redis.call('ZREM', KEYS[3], ARGV[1])
+    redis.call('HINCRBY', KEYS[1], 'sessions', -1)
+    redis.call('HINCRBY', KEYS[1], 'payload_bytes', -bytes)
+    redis.call('HINCRBY', KEYS[1], 'waits', -waits)
+  end
+  return 0
+end
+if not previous and (ARGV[2] == '' or ARGV[3] == '' or ARGV[4] == '') then
+  return 0
+end
+local next_bytes, next_waits = bytes, waits
+if ARGV[2] ~= '' then next_bytes = tonumber(ARGV[2]) end
+if ARGV[3] ~= '' then next_waits = tonumber(ARGV[3]) end
+if not previous then
+  redis.call('HINCRBY', KEYS[1], 'sessions', 1)
+end
+redis.call('HINCRBY', KEYS[1], 'payload_bytes', next_bytes - bytes)
+redis.call('HINCRBY', KEYS[1], 'waits', next_waits - waits)
+redis.call('HSET', KEYS[2], ARGV[1], next_bytes .. ':' .. next_waits)
+if ARGV[4] ~= '' then
+  redis.call('ZADD', KEYS[3], ARGV[4], ARGV[1])
+end
+return 1
+";
+
+/// Operates on a session's inbox after checking the session's entry in the same step, so an inbox
+/// never outlives its session.
+///
+/// `KEYS` are the entry and the inbox; `ARGV[1]` is `push`, `peek`, `pop` or `len`. A push passes
+/// the message as `ARGV[2]` and the TTL in milliseconds as `ARGV[3]`, capped at the entry's
+/// remaining lifetime, and returns the new length or `-1` without an entry. Without an entry the
+/// other operations delete what is left of the inbox and report it empty.
+const INBOX_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+  redis.call('DEL', KEYS[2])
+  if ARGV[1] == 'push' then return -1 end
+  if ARGV[1] == 'len' then return 0 end
+  return false
+end
+if ARGV[1] == 'push' then
+  local len = redis.call('RPUSH', KEYS[2], ARGV[2])
+  local ttl = tonumber(ARGV[3])
+  local remaining = redis.call('PTTL', KEYS[1])
+  if remaining > 0 and remaining < ttl then ttl = remaining end
+  redis.call('PEXPIRE', KEYS[2], ttl)
+  return len
+end
+if ARGV[1] == 'peek' then return redis.call('LINDEX', KEYS[2], 0) end
+if ARGV[1] == 'pop' then return redis.call('LPOP', KEYS[2]) end
+return redis.call('LLEN', KEYS[2])
+";
+
+/// Attempts made by compare-and-set writes before reporting a concurrent modification.
+const CAS_ATTEMPTS: usize = 8;
+
+/// Value of the `backend` metrics label.
+const BACKEND: &str = "redis";
+/// `PING` round trip above which [`RedisSessionStore::health`] reports the store as degraded.
+const SLOW_PING: Duration = Duration::from_millis(250);
+
+/// Change to a session's contribution to the per-tenant stats counters; `None` keeps the
+/// recorded value.
+#[derive(Default)]
+struct StatsUpdate {
+    payload_bytes: Option<usize>,
+    waits: Option<usize>,
+    /// Expiry in epoch milliseconds, `Some(None)` for a session that does not expire.
+    expires_at_ms: Option<Option<u64>>,
+}
+
+impl StatsUpdate {
+    /// Contribution of a session just stored without waits or expiry.
+    fn created(payload: &[u8]) -> Self {
+        Self {
+            payload_bytes: Some(payload.len()),
+            waits: Some(0),
+            expires_at_ms: Some(None),
+        }
+    }
+}
 
 /// Redis-backed session store that mirrors the in-memory semantics.
 ///
@@ -15,6 +343,15 @@ const DEFAULT_NAMESPACE: &str = "greentic:session";
 pub struct RedisSessionStore {
     client: Client,
     namespace: String,
+    options: SessionStoreOptions,
+    stats_script: Script,
+    inbox_script: Script,
+    cas_script: Script,
+    merge_script: Script,
+    delete_script: Script,
+    /// Whether [`STATS_SCRIPT`] was loaded into the server's script cache, so pipelines can
+    /// call it by hash.
+    stats_script_loaded: AtomicBool,
 }
 
 impl RedisSessionStore {
@@ -40,57 +377,534 @@ impl RedisSessionStore {
         Self {
             client,
             namespace: namespace.into(),
+            options: SessionStoreOptions::default(),
+            stats_script: Script::new(STATS_SCRIPT),
+            inbox_script: Script::new(INBOX_SCRIPT),
+            cas_script: Script::new(COMPARE_AND_SET_SCRIPT),
+            merge_script: Script::new(MERGE_PATCH_SCRIPT),
+            delete_script: Script::new(COMPARE_AND_DELETE_SCRIPT),
+            stats_script_loaded: AtomicBool::new(false),
         }
     }
 
+    /// Applies behavioural options such as quotas to the store.
+    pub fn with_options(mut self, options: SessionStoreOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     fn conn(&self) -> SessionResult<Connection> {
         self.client.get_connection().map_err(redis_error)
     }
 
-    fn normalize_team(ctx: &TenantCtx) -> Option<&greentic_types::TeamId> {
-        ctx.team_id.as_ref().or(ctx.team.as_ref())
+    fn session_entry_key(&self, key: &SessionKey) -> String {
+        format!("{}:session:{}", self.namespace, key.as_str())
     }
 
-    fn normalize_user(ctx: &TenantCtx) -> Option<&UserId> {
-        ctx.user_id.as_ref().or(ctx.user.as_ref())
+    fn session_waits_key(&self, key: &SessionKey) -> String {
+        format!("{}:waits:session:{}", self.namespace, key.as_str())
     }
 
-    fn ctx_mismatch(
-        expected: &TenantCtx,
-        provided: &TenantCtx,
-        reason: &str,
-    ) -> crate::error::GreenticError {
-        let expected_team = Self::normalize_team(expected)
-            .map(|t| t.as_str())
-            .unwrap_or("-");
-        let provided_team = Self::normalize_team(provided)
-            .map(|t| t.as_str())
-            .unwrap_or("-");
-        let expected_user_presence = if Self::normalize_user(expected).is_some() {
-            "present"
-        } else {
-            "missing"
+    fn session_history_key(&self, key: &SessionKey) -> String {
+        format!("{}:history:{}", self.namespace, key.as_str())
+    }
+
+    fn session_inbox_key(&self, key: &SessionKey) -> String {
+        format!("{}:inbox:{}", self.namespace, key.as_str())
+    }
+
+    fn tenant_index_key(&self, env: &EnvId, tenant: &TenantId) -> String {
+        format!(
+            "{}:index:tenant:{}:{}",
+            self.namespace,
+            env.as_str(),
+            tenant.as_str()
+        )
+    }
+
+    fn team_index_key(&self, env: &EnvId, tenant: &TenantId, team: &TeamId) -> String {
+        format!(
+            "{}:index:team:{}:{}:{}",
+            self.namespace,
+            env.as_str(),
+            tenant.as_str(),
+            team.as_str()
+        )
+    }
+
+    /// Queues insertion of the session into the tenant (and team) listing indices.
+    ///
+    /// Indices are sorted sets with a constant score so pages can be read with `ZRANGEBYLEX`.
+    fn queue_index_add(&self, pipe: &mut Pipeline, key: &SessionKey, ctx: &TenantCtx) {
+        pipe.zadd(
+            self.tenant_index_key(&ctx.env, &ctx.tenant_id),
+            key.as_str(),
+            0,
+        )
+        .ignore();
+        if let Some(team) = normalize_team(ctx) {
+            pipe.zadd(
+                self.team_index_key(&ctx.env, &ctx.tenant_id, team),
+                key.as_str(),
+                0,
+            )
+            .ignore();
+        }
+    }
+
+    fn queue_index_remove(&self, pipe: &mut Pipeline, key: &SessionKey, ctx: &TenantCtx) {
+        pipe.zrem(
+            self.tenant_index_key(&ctx.env, &ctx.tenant_id),
+            key.as_str(),
+        )
+        .ignore();
+        if let Some(team) = normalize_team(ctx) {
+            pipe.zrem(
+                self.team_index_key(&ctx.env, &ctx.tenant_id, team),
+                key.as_str(),
+            )
+            .ignore();
+        }
+    }
+
+    /// Keys of the tenant's stats counters, in [`STATS_SCRIPT`] order: totals, per-session
+    /// contributions and expiries.
+    fn stats_keys(&self, env: &EnvId, tenant: &TenantId) -> [String; 3] {
+        let totals = format!(
+            "{}:stats:{}:{}",
+            self.namespace,
+            env.as_str(),
+            tenant.as_str()
+        );
+        let contributions = format!("{totals}:sessions");
+        let expiries = format!("{totals}:expiry");
+        [totals, contributions, expiries]
+    }
+
+    /// Key of the sorted set tracking the tenant's scope pointers, scored by expiry in epoch
+    /// milliseconds or `+inf`, so stats count live pointers without scanning for them.
+    fn pointer_stats_key(&self, env: &EnvId, tenant: &TenantId) -> String {
+        let [totals, _, _] = self.stats_keys(env, tenant);
+        format!("{totals}:pointers")
+    }
+
+    /// [`Self::pointer_stats_key`] of the tenant owning the scope pointer `scope_key`.
+    fn pointer_stats_key_of(&self, scope_key: &str) -> Option<String> {
+        let fields = scope_key
+            .strip_prefix(&format!("{}:waits:scope:", self.namespace))
+            .and_then(parse_index_fields::<5>)?;
+        Some(format!(
+            "{}:stats:{}:{}:pointers",
+            self.namespace, fields[0], fields[1]
+        ))
+    }
+
+    /// Queues tracking of the scope pointer `scope_key`, next to the write that sets it.
+    fn queue_pointer_add(&self, pipe: &mut Pipeline, scope_key: &str, expires_at_ms: Option<u64>) {
+        if let Some(pointers) = self.pointer_stats_key_of(scope_key) {
+            let score = expires_at_ms.map_or_else(|| "+inf".to_string(), |ms| ms.to_string());
+            pipe.zadd(pointers, scope_key, score).ignore();
+        }
+    }
+
+    /// Queues untracking of the scope pointer `scope_key`, next to the write that deletes it.
+    fn queue_pointer_remove(&self, pipe: &mut Pipeline, scope_key: &str) {
+        if let Some(pointers) = self.pointer_stats_key_of(scope_key) {
+            pipe.zrem(pointers, scope_key).ignore();
+        }
+    }
+
+    /// Queues `update` of the stats counters `key` contributes to.
+    fn queue_stats(
+        &self,
+        pipe: &mut Pipeline,
+        key: &SessionKey,
+        ctx: &TenantCtx,
+        update: StatsUpdate,
+    ) {
+        let arg = |value: Option<String>| value.unwrap_or_default();
+        let expires_at_ms = update.expires_at_ms.map(|expiry| {
+            expiry
+                .map(|ms| ms.to_string())
+                .unwrap_or_else(|| "+inf".to_string())
+        });
+        pipe.invoke_script(
+            self.stats_script
+                .key(&self.stats_keys(&ctx.env, &ctx.tenant_id))
+                .arg(key.as_str())
+                .arg(arg(update.payload_bytes.map(|bytes| bytes.to_string())))
+                .arg(arg(update.waits.map(|waits| waits.to_string())))
+                .arg(arg(expires_at_ms)),
+        )
+        .ignore();
+    }
+
+    /// Queues removal of `key` from the stats counters.
+    fn queue_stats_drop(
+        &self,
+        pipe: &mut Pipeline,
+        key: &SessionKey,
+        env: &EnvId,
+        tenant: &TenantId,
+    ) {
+        pipe.invoke_script(
+            self.stats_script
+                .key(&self.stats_keys(env, tenant))
+                .arg(key.as_str())
+                .arg("del")
+                .arg("")
+                .arg(""),
+        )
+        .ignore();
+    }
+
+    /// Queues recording `key`, found by a backfill, unless a write recorded it first.
+    fn queue_stats_fill(
+        &self,
+        pipe: &mut Pipeline,
+        key: &SessionKey,
+        filter: &SessionFilter,
+        payload_bytes: usize,
+        waits: usize,
+        expires_at_ms: Option<u64>,
+    ) {
+        let expires_at_ms = expires_at_ms
+            .map(|ms| ms.to_string())
+            .unwrap_or_else(|| "+inf".to_string());
+        pipe.invoke_script(
+            self.stats_script
+                .key(&self.stats_keys(&filter.env, &filter.tenant))
+                .key(self.session_entry_key(key))
+                .arg(key.as_str())
+                .arg(payload_bytes)
+                .arg(waits)
+                .arg(expires_at_ms)
+                .arg("fill"),
+        )
+        .ignore();
+    }
+
+    /// Runs `pipe`, whose stats updates call [`STATS_SCRIPT`] by hash, loading the script first
+    /// when this store has not done so yet. Returns `false` when a `WATCH` aborted the pipeline.
+    ///
+    /// A script cache flushed since the load fails only the stats updates, so the rest of the
+    /// pipeline still applies, the next commit reloads the script and the missed updates leave
+    /// the counters approximate.
+    fn commit(&self, conn: &mut Connection, pipe: &Pipeline) -> SessionResult<bool> {
+        if !self.stats_script_loaded.load(Ordering::Relaxed) {
+            self.stats_script.load(conn).map_err(redis_error)?;
+            self.stats_script_loaded.store(true, Ordering::Relaxed);
+        }
+        match pipe.query::<Option<()>>(conn) {
+            Ok(committed) => Ok(committed.is_some()),
+            Err(err) if Self::only_missing_script(&err) => {
+                self.stats_script_loaded.store(false, Ordering::Relaxed);
+                Ok(true)
+            }
+            Err(err) => Err(redis_error(err)),
+        }
+    }
+
+    /// Prepares an [`INBOX_SCRIPT`] call running `op` on the inbox of `key`.
+    fn inbox_call(&self, key: &SessionKey, op: &str) -> ScriptInvocation<'_> {
+        let mut invocation = self.inbox_script.prepare_invoke();
+        invocation
+            .key(self.session_entry_key(key))
+            .key(self.session_inbox_key(key))
+            .arg(op);
+        invocation
+    }
+
+    /// Whether every command `err` reports failed because a script was missing from the cache.
+    fn only_missing_script(err: &RedisError) -> bool {
+        err.clone().into_server_errors().is_some_and(|errors| {
+            errors
+                .iter()
+                .all(|(_, error)| error.kind() == Some(ServerErrorKind::NoScript))
+        })
+    }
+
+    /// Drops sessions whose recorded expiry passed and whose entry is gone from the tenant's
+    /// stats counters; Redis expires entries without notifying the counters.
+    fn prune_expired_stats(
+        &self,
+        conn: &mut Connection,
+        env: &EnvId,
+        tenant: &TenantId,
+    ) -> SessionResult<()> {
+        let [_, _, expiries] = self.stats_keys(env, tenant);
+        let due: Vec<String> = conn
+            .zrangebyscore(&expiries, "-inf", Self::now_millis())
+            .map_err(redis_error)?;
+        for chunk in due.chunks(PURGE_BATCH) {
+            let mut pipe = redis::pipe();
+            for member in chunk {
+                pipe.exists(self.session_entry_key(&SessionKey::new(member.as_str())));
+            }
+            let exists: Vec<bool> = pipe.query(conn).map_err(redis_error)?;
+            let mut pipe = redis::pipe();
+            let mut stale = 0;
+            for (member, _) in chunk.iter().zip(exists).filter(|(_, exists)| !exists) {
+                self.queue_stats_drop(&mut pipe, &SessionKey::new(member.as_str()), env, tenant);
+                stale += 1;
+            }
+            if stale > 0 {
+                self.commit(conn, &pipe)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the tenant's stats counters.
+    ///
+    /// Sessions and scope pointers are counted from their expiry sorted sets, so entries whose
+    /// TTL lapsed drop out right away; payload bytes and waits keep them until `purge_expired`.
+    fn counted_stats(
+        &self,
+        conn: &mut Connection,
+        env: &EnvId,
+        tenant: &TenantId,
+    ) -> SessionResult<SessionStats> {
+        let [totals, _, expiries] = self.stats_keys(env, tenant);
+        let counters: HashMap<String, i64> = conn.hgetall(&totals).map_err(redis_error)?;
+        let count = |field: &str| {
+            let value = counters.get(field).copied().unwrap_or_default();
+            u64::try_from(value).unwrap_or_default()
         };
-        let provided_user_presence = if Self::normalize_user(provided).is_some() {
-            "present"
-        } else {
-            "missing"
+        let now_ms = Self::now_millis();
+        let bounds = TTL_BOUNDS.map(|bound| {
+            now_ms.saturating_add(u64::try_from(bound.as_millis()).unwrap_or(u64::MAX))
+        });
+        let mut pipe = redis::pipe();
+        pipe.zcount(&expiries, format!("({now_ms}"), format!("({}", bounds[0]))
+            .zcount(&expiries, bounds[0], format!("({}", bounds[1]))
+            .zcount(&expiries, bounds[1], format!("({}", bounds[2]))
+            .zcount(&expiries, bounds[2], "(+inf")
+            .zcount(&expiries, "+inf", "+inf")
+            .zcount(
+                self.pointer_stats_key(env, tenant),
+                format!("({now_ms}"),
+                "+inf",
+            );
+        let (under_minute, under_hour, under_day, day_or_more, no_expiry, scope_pointers) =
+            pipe.query(conn).map_err(redis_error)?;
+        let ttl = TtlBuckets {
+            under_minute,
+            under_hour,
+            under_day,
+            day_or_more,
+            no_expiry,
         };
-        invalid_argument(format!(
-            "tenant context mismatch ({reason}): expected env={}, tenant={}, team={}, user={}, got env={}, tenant={}, team={}, user={}",
-            expected.env.as_str(),
-            expected.tenant_id.as_str(),
-            expected_team,
-            expected_user_presence,
-            provided.env.as_str(),
-            provided.tenant_id.as_str(),
-            provided_team,
-            provided_user_presence
-        ))
+        Ok(SessionStats {
+            sessions: under_minute + under_hour + under_day + day_or_more + no_expiry,
+            waits: count("waits") as usize,
+            scope_pointers,
+            payload_bytes: count("payload_bytes"),
+            ttl,
+        })
     }
 
-    fn session_entry_key(&self, key: &SessionKey) -> String {
-        format!("{}:session:{}", self.namespace, key.as_str())
+    /// Computes stats by listing the matching sessions, for filters the counters cannot answer.
+    fn scanned_stats(
+        &self,
+        conn: &mut Connection,
+        filter: &SessionFilter,
+    ) -> SessionResult<SessionStats> {
+        let mut stats = SessionStats::default();
+        let mut cursor = None;
+        loop {
+            let page = self.list_page(conn, filter, cursor.as_deref(), PURGE_BATCH)?;
+            if page.sessions.is_empty() {
+                break;
+            }
+            let mut pipe = redis::pipe();
+            for listing in &page.sessions {
+                let entry_key = self.session_entry_key(&listing.key);
+                pipe.strlen(&entry_key).pttl(&entry_key);
+            }
+            let replies: Vec<i64> = pipe.query(conn).map_err(redis_error)?;
+            for (listing, reply) in page.sessions.iter().zip(replies.chunks(2)) {
+                let (payload_bytes, ttl_ms) = (reply[0], reply[1]);
+                // `-2`: the session expired after it was listed.
+                if ttl_ms == -2 {
+                    continue;
+                }
+                stats.record(
+                    usize::try_from(payload_bytes).unwrap_or_default(),
+                    listing.waits.len(),
+                    u64::try_from(ttl_ms).ok().map(Duration::from_millis),
+                );
+            }
+            stats.scope_pointers += self.count_routed_pointers(conn, &page.sessions)?;
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Counts the scope pointers of the listed sessions' waits that still route to them.
+    fn count_routed_pointers(
+        &self,
+        conn: &mut Connection,
+        listings: &[SessionListing],
+    ) -> SessionResult<usize> {
+        let mut scope_keys = Vec::new();
+        let mut sessions = Vec::new();
+        for listing in listings {
+            for wait in &listing.waits {
+                scope_keys.push(self.scope_wait_key(
+                    &listing.data.tenant_ctx,
+                    &wait.user_id,
+                    &wait.scope,
+                ));
+                sessions.push(listing.key.as_str());
+            }
+        }
+        if scope_keys.is_empty() {
+            return Ok(0);
+        }
+        let holders: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(scope_keys)
+            .query(conn)
+            .map_err(redis_error)?;
+        Ok(holders
+            .iter()
+            .zip(sessions)
+            .filter(|(holder, session)| holder.as_deref() == Some(*session))
+            .count())
+    }
+
+    /// Records the tenant's sessions and scope pointers written before the stats counters
+    /// existed. Writes racing the backfill win: it only fills in sessions without a
+    /// contribution, and re-recording a pointer only refreshes its expiry.
+    fn backfill_stats(
+        &self,
+        conn: &mut Connection,
+        env: &EnvId,
+        tenant: &TenantId,
+    ) -> SessionResult<()> {
+        let filter = SessionFilter::tenant(env.clone(), tenant.clone());
+        let now_ms = Self::now_millis();
+        let mut cursor = None;
+        loop {
+            let page = self.list_page(conn, &filter, cursor.as_deref(), PURGE_BATCH)?;
+            if page.sessions.is_empty() {
+                break;
+            }
+            let mut pipe = redis::pipe();
+            for listing in &page.sessions {
+                let entry_key = self.session_entry_key(&listing.key);
+                pipe.strlen(&entry_key).pttl(&entry_key);
+            }
+            let replies: Vec<i64> = pipe.query(conn).map_err(redis_error)?;
+            let mut fill = redis::pipe();
+            for (listing, reply) in page.sessions.iter().zip(replies.chunks(2)) {
+                let (payload_bytes, ttl_ms) = (reply[0], reply[1]);
+                if ttl_ms == -2 {
+                    continue;
+                }
+                self.queue_stats_fill(
+                    &mut fill,
+                    &listing.key,
+                    &filter,
+                    usize::try_from(payload_bytes).unwrap_or_default(),
+                    listing.waits.len(),
+                    u64::try_from(ttl_ms)
+                        .ok()
+                        .map(|ttl_ms| now_ms.saturating_add(ttl_ms)),
+                );
+            }
+            self.commit(conn, &fill)?;
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        let pointers = Self::scan_keys(
+            conn,
+            &format!(
+                "{}:waits:scope:{}:*",
+                Self::scan_escape(&self.namespace),
+                Self::tenant_scan_prefix(env, tenant)
+            ),
+        )?;
+        for chunk in pointers.chunks(PURGE_BATCH) {
+            let mut pipe = redis::pipe();
+            for pointer in chunk {
+                pipe.pttl(pointer);
+            }
+            let ttls: Vec<i64> = pipe.query(conn).map_err(redis_error)?;
+            let mut fill = redis::pipe();
+            for (pointer, ttl_ms) in chunk.iter().zip(ttls) {
+                // `-2`: the pointer expired or was released after the scan.
+                if ttl_ms == -2 {
+                    continue;
+                }
+                let expires_at_ms = u64::try_from(ttl_ms)
+                    .ok()
+                    .map(|ttl_ms| now_ms.saturating_add(ttl_ms));
+                self.queue_pointer_add(&mut fill, pointer, expires_at_ms);
+            }
+            fill.query::<()>(conn).map_err(redis_error)?;
+        }
+        Ok(())
+    }
+
+    /// Lists one page of [`SessionStore::list_sessions`] over an open connection.
+    fn list_page(
+        &self,
+        conn: &mut Connection,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        let index_key = match &filter.team {
+            Some(team) => self.team_index_key(&filter.env, &filter.tenant, team),
+            None => self.tenant_index_key(&filter.env, &filter.tenant),
+        };
+        let mut start = cursor
+            .map(|cursor| format!("({cursor}"))
+            .unwrap_or_else(|| "-".to_string());
+        let mut page = SessionPage::default();
+        loop {
+            let members: Vec<String> = redis::cmd("ZRANGEBYLEX")
+                .arg(&index_key)
+                .arg(&start)
+                .arg("+")
+                .arg("LIMIT")
+                .arg(0)
+                .arg(limit)
+                .query(conn)
+                .map_err(redis_error)?;
+            let exhausted = members.len() < limit;
+            let sessions = self.load_sessions(conn, &members)?;
+            let keys: Vec<SessionKey> = members.iter().map(SessionKey::new).collect();
+            let waits = self.load_page_waits(conn, &keys, &sessions)?;
+            for (((member, key), data), waits) in members.iter().zip(keys).zip(sessions).zip(waits)
+            {
+                if page.sessions.len() == limit {
+                    page.next_cursor = page
+                        .sessions
+                        .last()
+                        .map(|listing| listing.key.as_str().to_string());
+                    return Ok(page);
+                }
+                start = format!("({member}");
+                // Expired sessions leave their index entries behind until `purge_expired` or
+                // `verify_and_repair` prunes them; listing only skips them.
+                let Some(data) = data else {
+                    continue;
+                };
+                if filter.matches(&data, !waits.is_empty()) {
+                    page.sessions.push(SessionListing { key, data, waits });
+                }
+            }
+            if exhausted {
+                return Ok(page);
+            }
+        }
     }
 
     fn user_waits_key(&self, ctx: &TenantCtx, user: &UserId) -> String {
@@ -128,295 +942,1470 @@ impl RedisSessionStore {
         )
     }
 
-    fn ensure_alignment(ctx: &TenantCtx, data: &SessionData) -> SessionResult<()> {
-        let stored = &data.tenant_ctx;
-        if ctx.env != stored.env || ctx.tenant_id != stored.tenant_id {
-            return Err(Self::ctx_mismatch(stored, ctx, "env/tenant must match"));
-        }
-        if Self::normalize_team(ctx) != Self::normalize_team(stored) {
-            return Err(Self::ctx_mismatch(stored, ctx, "team must match"));
-        }
-        if let Some(stored_user) = Self::normalize_user(stored) {
-            let Some(provided_user) = Self::normalize_user(ctx) else {
-                return Err(Self::ctx_mismatch(
-                    stored,
-                    ctx,
-                    "user required by session but missing in caller context",
-                ));
-            };
-            if stored_user != provided_user {
-                return Err(Self::ctx_mismatch(
-                    stored,
-                    ctx,
-                    "user must match stored session",
-                ));
-            }
-        }
-        Ok(())
-    }
-
-    fn ensure_ctx_preserved(existing: &TenantCtx, candidate: &TenantCtx) -> SessionResult<()> {
-        if existing.env != candidate.env || existing.tenant_id != candidate.tenant_id {
-            return Err(Self::ctx_mismatch(
-                existing,
-                candidate,
-                "env/tenant cannot change for an existing session",
-            ));
-        }
-        if Self::normalize_team(existing) != Self::normalize_team(candidate) {
-            return Err(Self::ctx_mismatch(
-                existing,
-                candidate,
-                "team cannot change for an existing session",
-            ));
-        }
-        match (
-            Self::normalize_user(existing),
-            Self::normalize_user(candidate),
-        ) {
-            (Some(a), Some(b)) if a == b => {}
-            (Some(_), Some(_)) | (Some(_), None) => {
-                return Err(Self::ctx_mismatch(
-                    existing,
-                    candidate,
-                    "user cannot change for an existing session",
-                ));
-            }
-            (None, Some(_)) => {
-                return Err(Self::ctx_mismatch(
-                    existing,
-                    candidate,
-                    "user cannot be introduced when none was stored",
-                ));
-            }
-            (None, None) => {}
-        }
-        Ok(())
+    fn event_key(&self, ctx: &TenantCtx, event_id: &str) -> String {
+        let team = normalize_team(ctx).map(|v| v.as_str()).unwrap_or("-");
+        format!(
+            "{}:event:{}:{}:{}:{}",
+            self.namespace,
+            ctx.env.as_str(),
+            ctx.tenant_id.as_str(),
+            team,
+            event_id
+        )
     }
 
-    fn ensure_user_matches(
-        ctx: &TenantCtx,
-        user: &UserId,
-        data: &SessionData,
-    ) -> SessionResult<()> {
-        if let Some(ctx_user) = Self::normalize_user(ctx) {
-            if ctx_user != user {
-                return Err(invalid_argument(
-                    "user must match tenant context when registering a wait",
-                ));
-            }
-        }
-        if let Some(stored_user) = Self::normalize_user(&data.tenant_ctx) {
-            if stored_user != user {
-                return Err(invalid_argument(
-                    "user must match session data when registering a wait",
-                ));
-            }
-        } else {
-            return Err(invalid_argument(
-                "user required by wait but missing in session data",
-            ));
-        }
-        Ok(())
+    fn serialize(&self, data: &SessionData) -> SessionResult<Vec<u8>> {
+        self.options.encode_payload(data)
     }
 
-    fn serialize(data: &SessionData) -> SessionResult<String> {
-        serde_json::to_string(data).map_err(serde_error)
+    fn deserialize(&self, payload: &[u8]) -> SessionResult<SessionData> {
+        self.options.decode_payload(payload)
     }
 
-    fn deserialize(payload: String) -> SessionResult<SessionData> {
-        serde_json::from_str(&payload).map_err(serde_error)
+    /// Reads the session at `key` over an open connection.
+    fn load_session(
+        &self,
+        conn: &mut Connection,
+        key: &SessionKey,
+    ) -> SessionResult<Option<SessionData>> {
+        let payload: Option<Vec<u8>> =
+            conn.get(self.session_entry_key(key)).map_err(redis_error)?;
+        payload
+            .map(|payload| self.deserialize(&payload))
+            .transpose()
     }
 
-    fn apply_ttl(conn: &mut Connection, key: &str, ttl: Option<Duration>) -> SessionResult<()> {
-        if let Some(ttl) = ttl {
-            let ttl_ms = ttl.as_millis().max(1);
-            let ttl_ms = if ttl_ms > i64::MAX as u128 {
-                i64::MAX
-            } else {
-                ttl_ms as i64
-            };
-            conn.pexpire::<_, ()>(key, ttl_ms).map_err(redis_error)?;
+    /// Reads the sessions at `keys` with one `MGET`, in the order of `keys`.
+    fn load_sessions(
+        &self,
+        conn: &mut Connection,
+        keys: &[String],
+    ) -> SessionResult<Vec<Option<SessionData>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
         }
-        Ok(())
-    }
-}
-
-impl SessionStore for RedisSessionStore {
-    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
-        Self::ensure_alignment(ctx, &data)?;
-        let key = SessionKey::new(Uuid::new_v4().to_string());
-        let payload = Self::serialize(&data)?;
-        let mut conn = self.conn()?;
-        conn.set::<_, _, ()>(self.session_entry_key(&key), payload)
+        let entry_keys: Vec<String> = keys
+            .iter()
+            .map(|key| self.session_entry_key(&SessionKey::new(key.as_str())))
+            .collect();
+        let payloads: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(entry_keys)
+            .query(conn)
             .map_err(redis_error)?;
-        Ok(key)
+        payloads
+            .into_iter()
+            .map(|payload| {
+                payload
+                    .map(|payload| self.deserialize(&payload))
+                    .transpose()
+            })
+            .collect()
     }
 
-    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
-        let mut conn = self.conn()?;
-        let payload: Option<String> = conn.get(self.session_entry_key(key)).map_err(redis_error)?;
-        payload.map(Self::deserialize).transpose()
+    fn ttl_millis(ttl: Duration) -> i64 {
+        i64::try_from(ttl.as_millis().max(1)).unwrap_or(i64::MAX)
     }
 
-    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
-        let mut conn = self.conn()?;
-        let entry_key = self.session_entry_key(key);
-        let existing: Option<String> = conn.get(&entry_key).map_err(redis_error)?;
-        let Some(existing_payload) = existing else {
-            return Err(not_found(key));
-        };
-        let previous = Self::deserialize(existing_payload)?;
-        Self::ensure_ctx_preserved(&previous.tenant_ctx, &data.tenant_ctx)?;
-        let payload = Self::serialize(&data)?;
-        conn.set::<_, _, ()>(&entry_key, payload)
-            .map_err(redis_error)
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+            .unwrap_or_default()
     }
 
-    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
-        let mut conn = self.conn()?;
-        let entry_key = self.session_entry_key(key);
-        let existing: Option<String> = conn.get(&entry_key).map_err(redis_error)?;
-        let Some(payload) = existing else {
-            return Err(not_found(key));
+    /// Loads the archived snapshots of a session that the history policy still retains.
+    fn load_history(
+        &self,
+        conn: &mut Connection,
+        key: &SessionKey,
+    ) -> SessionResult<Vec<HistoryRecord>> {
+        let Some(policy) = self.options.history() else {
+            return Ok(Vec::new());
         };
-        let data = Self::deserialize(payload)?;
-        let _: () = conn.del(entry_key).map_err(redis_error)?;
-        if let Some(user) = Self::normalize_user(&data.tenant_ctx) {
-            let user_waits_key = self.user_waits_key(&data.tenant_ctx, user);
-            let _: () = conn
-                .srem::<_, _, ()>(user_waits_key, key.as_str())
-                .map_err(redis_error)?;
+        let raw: Vec<Vec<u8>> = conn
+            .lrange(self.session_history_key(key), 0, -1)
+            .map_err(redis_error)?;
+        let now_ms = Self::now_millis();
+        let mut records = Vec::with_capacity(raw.len().min(policy.depth));
+        for entry in raw.into_iter().take(policy.depth) {
+            let record = HistoryRecord::decode(entry)?;
+            if !policy.retains(record.recorded_at_ms, now_ms) {
+                break;
+            }
+            records.push(record);
         }
-        Ok(())
+        Ok(records)
     }
 
-    fn register_wait(
+    /// Queues archiving `payload` as the newest snapshot of a session through
+    /// [`ARCHIVE_SCRIPT`], which numbers the version when the transaction runs.
+    ///
+    /// The list is trimmed to the policy depth; `ttl_ms` is applied to it, with `None` leaving it
+    /// persistent.
+    fn queue_archive(
         &self,
-        ctx: &TenantCtx,
-        user_id: &UserId,
-        scope: &ReplyScope,
-        session_key: &SessionKey,
-        data: SessionData,
-        ttl: Option<Duration>,
-    ) -> SessionResult<()> {
-        Self::ensure_alignment(ctx, &data)?;
-        Self::ensure_user_matches(ctx, user_id, &data)?;
-        let mut conn = self.conn()?;
-        let entry_key = self.session_entry_key(session_key);
-        let payload = Self::serialize(&data)?;
-        conn.set::<_, _, ()>(&entry_key, payload)
-            .map_err(redis_error)?;
-        Self::apply_ttl(&mut conn, &entry_key, ttl)?;
+        pipe: &mut Pipeline,
+        key: &SessionKey,
+        payload: Vec<u8>,
+        ttl_ms: Option<i64>,
+    ) {
+        let Some(policy) = self.options.history() else {
+            return;
+        };
+        pipe.cmd("EVAL")
+            .arg(ARCHIVE_SCRIPT)
+            .arg(1)
+            .arg(self.session_history_key(key))
+            .arg(payload)
+            .arg(policy.depth)
+            .arg(Self::now_millis())
+            .arg(ttl_ms.unwrap_or(0))
+            .ignore();
+    }
 
-        let user_waits_key = self.user_waits_key(ctx, user_id);
-        conn.sadd::<_, _, ()>(&user_waits_key, session_key.as_str())
-            .map_err(redis_error)?;
+    /// Returns the tenant context a session is stored under, failing when it does not exist.
+    fn stored_ctx(&self, conn: &mut Connection, key: &SessionKey) -> SessionResult<TenantCtx> {
+        let payload: Option<Vec<u8>> =
+            conn.get(self.session_entry_key(key)).map_err(redis_error)?;
+        let payload = payload.ok_or_else(|| not_found(key))?;
+        Ok(self.deserialize(&payload)?.tenant_ctx)
+    }
 
-        let scope_key = self.scope_wait_key(ctx, user_id, scope);
-        let previous: Option<String> = conn.get(&scope_key).map_err(redis_error)?;
-        conn.set::<_, _, ()>(&scope_key, session_key.as_str())
+    /// Replaces the session payload if it still is `previous`, keeping its lifetime and, when
+    /// `archive` is set and history is enabled, archiving `previous` as the newest version. The
+    /// new payload size reaches the tenant's stats counters in the same script.
+    fn compare_and_set(
+        &self,
+        conn: &mut Connection,
+        key: &SessionKey,
+        ctx: &TenantCtx,
+        previous: &[u8],
+        payload: &[u8],
+        archive: bool,
+    ) -> SessionResult<CasOutcome> {
+        let depth = match self.options.history() {
+            Some(policy) if archive => policy.depth,
+            _ => 0,
+        };
+        let [totals, contributions, _] = self.stats_keys(&ctx.env, &ctx.tenant_id);
+        let mut invocation = self.cas_script.prepare_invoke();
+        invocation
+            .key(self.session_entry_key(key))
+            .key(self.session_history_key(key))
+            .key(totals)
+            .key(contributions)
+            .key(self.tenant_index_key(&ctx.env, &ctx.tenant_id));
+        if let Some(team) = normalize_team(ctx) {
+            invocation.key(self.team_index_key(&ctx.env, &ctx.tenant_id, team));
+        }
+        let outcome: i64 = invocation
+            .arg(sha1_smol::Sha1::from(previous).digest().to_string())
+            .arg(payload)
+            .arg(depth)
+            .arg(Self::now_millis())
+            .arg(key.as_str())
+            .invoke(conn)
             .map_err(redis_error)?;
-        Self::apply_ttl(&mut conn, &scope_key, ttl)?;
-        if let Some(previous) = previous {
-            if previous != session_key.as_str() {
-                let _: () = conn
-                    .srem::<_, _, ()>(&user_waits_key, previous)
-                    .map_err(redis_error)?;
-            }
+        match outcome {
+            1 => Ok(CasOutcome::Written),
+            -1 => Ok(CasOutcome::Missing),
+            _ => Ok(CasOutcome::Changed),
         }
-        Ok(())
     }
 
-    fn find_wait_by_scope(
+    /// Applies a merge or cursor patch to the stored payload inside [`MERGE_PATCH_SCRIPT`], so
+    /// the payload does not travel to the client and back before the write.
+    ///
+    /// Returns `None` when the patch must be applied client-side instead: the store writes
+    /// another codec, the patch is an RFC 6902 one, or the script turned the payload down.
+    fn patch_in_place(
         &self,
-        ctx: &TenantCtx,
-        user_id: &UserId,
-        scope: &ReplyScope,
-    ) -> SessionResult<Option<SessionKey>> {
-        let mut conn = self.conn()?;
-        let scope_key = self.scope_wait_key(ctx, user_id, scope);
-        let stored: Option<String> = conn.get(&scope_key).map_err(redis_error)?;
-        let Some(raw_key) = stored else {
+        conn: &mut Connection,
+        key: &SessionKey,
+        patch: &SessionPatch,
+    ) -> SessionResult<Option<SessionData>> {
+        if self.options.codec.tag() != b'J' {
             return Ok(None);
+        }
+        let merge = match &patch.context {
+            Some(ContextPatch::Merge(merge)) => merge.to_string(),
+            Some(ContextPatch::Json(_)) => return Ok(None),
+            None => String::new(),
         };
-        let session_key = SessionKey::new(raw_key);
-        match self.get_session(&session_key)? {
-            Some(data) => {
-                let stored_ctx = &data.tenant_ctx;
-                if stored_ctx.env == ctx.env
-                    && stored_ctx.tenant_id == ctx.tenant_id
-                    && Self::normalize_team(stored_ctx) == Self::normalize_team(ctx)
-                {
-                    if let Some(stored_user) = Self::normalize_user(stored_ctx)
-                        && stored_user != user_id
-                    {
-                        let _: () = conn.del(&scope_key).map_err(redis_error)?;
-                        let user_waits_key = self.user_waits_key(ctx, user_id);
-                        let _: () = conn
-                            .srem::<_, _, ()>(&user_waits_key, session_key.as_str())
-                            .map_err(redis_error)?;
-                        return Ok(None);
-                    }
-                    Ok(Some(session_key))
-                } else {
-                    let _: () = conn.del(&scope_key).map_err(redis_error)?;
-                    let user_waits_key = self.user_waits_key(ctx, user_id);
-                    let _: () = conn
-                        .srem::<_, _, ()>(&user_waits_key, session_key.as_str())
-                        .map_err(redis_error)?;
-                    Ok(None)
-                }
-            }
-            None => {
-                let _: () = conn.del(&scope_key).map_err(redis_error)?;
-                let user_waits_key = self.user_waits_key(ctx, user_id);
-                let _: () = conn
-                    .srem::<_, _, ()>(&user_waits_key, session_key.as_str())
-                    .map_err(redis_error)?;
-                Ok(None)
+        let cursor = match &patch.cursor {
+            Some(cursor) => serde_json::to_string(cursor).map_err(serde_error)?,
+            None => String::new(),
+        };
+        let depth = self.options.history().map_or(0, |policy| policy.depth);
+        let ceiling = self
+            .options
+            .payload_ceiling()
+            .map_or(-1, |bytes| i64::try_from(bytes).unwrap_or(i64::MAX));
+        let reply: Vec<Vec<u8>> = self
+            .merge_script
+            .key(self.session_entry_key(key))
+            .key(self.session_history_key(key))
+            .arg(merge)
+            .arg(cursor)
+            .arg(self.options.migrations.target_version())
+            .arg(ceiling)
+            .arg(depth)
+            .arg(Self::now_millis())
+            .arg(key.as_str())
+            .arg(&self.namespace)
+            .invoke(conn)
+            .map_err(redis_error)?;
+        match reply.first().map(Vec::as_slice) {
+            Some(b"written") => {
+                let payload = reply.get(1).map(Vec::as_slice).unwrap_or_default();
+                let patched = self.deserialize(payload)?;
+                // Within the ceiling the checks pass; this only fires the near-limit hook.
+                self.enforce_payload_limits(key, &patched.tenant_ctx, payload)?;
+                Ok(Some(patched))
             }
+            Some(b"missing") => Err(not_found(key)),
+            _ => Ok(None),
         }
     }
 
-    fn list_waits_for_user(
+    fn load_wait_records(
         &self,
-        ctx: &TenantCtx,
-        user_id: &UserId,
-    ) -> SessionResult<Vec<SessionKey>> {
-        let mut conn = self.conn()?;
-        let user_waits_key = self.user_waits_key(ctx, user_id);
-        let stored: Vec<String> = conn.smembers(&user_waits_key).map_err(redis_error)?;
+        conn: &mut Connection,
+        key: &SessionKey,
+    ) -> SessionResult<HashMap<String, WaitRecord>> {
+        let raw: HashMap<String, String> = conn
+            .hgetall(self.session_waits_key(key))
+            .map_err(redis_error)?;
+        raw.into_iter()
+            .map(|(name, payload)| {
+                let record = serde_json::from_str(&payload).map_err(serde_error)?;
+                Ok((name, record))
+            })
+            .collect()
+    }
+
+    /// Queues deletion of a scope pointer, provided it still routes to `key`.
+    fn release_scope(
+        &self,
+        conn: &mut Connection,
+        pipe: &mut Pipeline,
+        scope_key: &str,
+        key: &SessionKey,
+    ) -> SessionResult<()> {
+        let holder: Option<String> = conn.get(scope_key).map_err(redis_error)?;
+        if holder.as_deref() == Some(key.as_str()) {
+            pipe.del(scope_key).ignore();
+            self.queue_pointer_remove(pipe, scope_key);
+        }
+        Ok(())
+    }
+
+    /// Queues removal of the waits `previous` registered on `scope_key`, which another session is
+    /// taking over.
+    fn queue_scope_takeover(
+        &self,
+        conn: &mut Connection,
+        pipe: &mut Pipeline,
+        previous: &SessionKey,
+        ctx: &TenantCtx,
+        scope_key: &str,
+        user_waits_key: &str,
+    ) -> SessionResult<()> {
+        let records = self.load_wait_records(conn, previous)?;
+        let (released, kept): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .partition(|(_, record)| record.scope_key == scope_key);
+        for (name, _) in &released {
+            pipe.hdel(self.session_waits_key(previous), name).ignore();
+        }
+        if kept.is_empty() {
+            pipe.srem(user_waits_key, previous.as_str()).ignore();
+        }
+        self.queue_stats(
+            pipe,
+            previous,
+            ctx,
+            StatsUpdate {
+                waits: Some(kept.len()),
+                ..StatsUpdate::default()
+            },
+        );
+        Ok(())
+    }
+
+    /// Queues removal of every wait registered on `key` together with its routing indices.
+    fn queue_wait_release(
+        &self,
+        conn: &mut Connection,
+        pipe: &mut Pipeline,
+        key: &SessionKey,
+        records: &HashMap<String, WaitRecord>,
+    ) -> SessionResult<()> {
+        for record in records.values() {
+            self.release_scope(conn, pipe, &record.scope_key, key)?;
+            pipe.srem(&record.user_waits_key, key.as_str()).ignore();
+        }
+        pipe.del(self.session_waits_key(key)).ignore();
+        Ok(())
+    }
+
+    /// Deletes a session, its waits and every routing index pointing at it.
+    ///
+    /// Returns `false` when the session did not exist. The entry is watched, so of two concurrent
+    /// purges only one reports the session as deleted.
+    fn purge_session(&self, conn: &mut Connection, key: &SessionKey) -> SessionResult<bool> {
+        let entry_key = self.session_entry_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            redis::cmd("WATCH")
+                .arg(&entry_key)
+                .query::<()>(conn)
+                .map_err(redis_error)?;
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let records = self.load_wait_records(conn, key)?;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            self.queue_wait_release(conn, &mut pipe, key, &records)?;
+            pipe.del(&entry_key).ignore();
+            pipe.del(self.session_history_key(key)).ignore();
+            pipe.del(self.session_inbox_key(key)).ignore();
+            let Some(payload) = existing else {
+                if self.commit(conn, &pipe)? {
+                    return Ok(false);
+                }
+                continue;
+            };
+            let data = self.deserialize(&payload)?;
+            if let Some(user) = normalize_user(&data.tenant_ctx) {
+                pipe.srem(self.user_waits_key(&data.tenant_ctx, user), key.as_str())
+                    .ignore();
+            }
+            self.queue_index_remove(&mut pipe, key, &data.tenant_ctx);
+            self.queue_stats_drop(
+                &mut pipe,
+                key,
+                &data.tenant_ctx.env,
+                &data.tenant_ctx.tenant_id,
+            );
+            if self.commit(conn, &pipe)? {
+                return Ok(true);
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn enforce_payload_limits(
+        &self,
+        key: &SessionKey,
+        ctx: &TenantCtx,
+        payload: &[u8],
+    ) -> SessionResult<()> {
+        self.options.check_payload(key, ctx, payload.len())
+    }
+
+    /// Checks the tenant session quota against the listing index cardinality, and `WATCH`es the
+    /// index so the caller's transaction fails instead of exceeding the quota when a concurrent
+    /// write adds a session.
+    ///
+    /// The index may still count sessions that expired through their TTL, so those members are
+    /// pruned before a rejection is reported.
+    fn enforce_session_quota(&self, conn: &mut Connection, ctx: &TenantCtx) -> SessionResult<()> {
+        let quotas = &self.options.quotas;
+        let Some(limit) = quotas.limits_for(&ctx.tenant_id).max_sessions_per_tenant else {
+            return Ok(());
+        };
+        let index_key = self.tenant_index_key(&ctx.env, &ctx.tenant_id);
+        let current: usize = conn.zcard(&index_key).map_err(redis_error)?;
+        if current >= limit {
+            self.prune_missing_members(conn, &index_key, "listing")?;
+        }
+        let current = Self::watch_cardinality(conn, &index_key, "ZCARD")?;
+        quotas.ensure_session_capacity(&ctx.tenant_id, current)
+    }
+
+    /// Checks the user wait quota against the live waits of the sessions in the user's wait set,
+    /// leaving out `key`, whose waits the registration replaces.
+    ///
+    /// The set and the counted waits hashes are `WATCH`ed like [`Self::enforce_session_quota`], so
+    /// a concurrent registration for the same user aborts the caller's transaction. Members whose
+    /// session is gone count for nothing; sessions registered before per-session wait records
+    /// existed count as one wait.
+    fn enforce_wait_quota(
+        &self,
+        conn: &mut Connection,
+        ctx: &TenantCtx,
+        user: &UserId,
+        key: &SessionKey,
+        adding: usize,
+    ) -> SessionResult<()> {
+        let quotas = &self.options.quotas;
+        if quotas
+            .limits_for(&ctx.tenant_id)
+            .max_waits_per_user
+            .is_none()
+        {
+            return Ok(());
+        }
+        let user_waits_key = self.user_waits_key(ctx, user);
+        redis::cmd("WATCH")
+            .arg(&user_waits_key)
+            .query::<()>(conn)
+            .map_err(redis_error)?;
+        let members: Vec<String> = conn.smembers(&user_waits_key).map_err(redis_error)?;
+        let others: Vec<SessionKey> = members
+            .into_iter()
+            .filter(|member| member.as_str() != key.as_str())
+            .map(SessionKey::new)
+            .collect();
+        let now_ms = Self::now_millis();
+        let mut current = 0;
+        for chunk in others.chunks(PURGE_BATCH) {
+            let mut watch = redis::cmd("WATCH");
+            let mut pipe = redis::pipe();
+            for other in chunk {
+                watch.arg(self.session_waits_key(other));
+                pipe.exists(self.session_entry_key(other))
+                    .hvals(self.session_waits_key(other));
+            }
+            watch.query::<()>(conn).map_err(redis_error)?;
+            let replies: Vec<(bool, Vec<String>)> = pipe.query(conn).map_err(redis_error)?;
+            for (exists, records) in replies {
+                if !exists {
+                    continue;
+                }
+                if records.is_empty() {
+                    current += 1;
+                    continue;
+                }
+                for payload in records {
+                    let record: WaitRecord = serde_json::from_str(&payload).map_err(serde_error)?;
+                    if record.user_waits_key == user_waits_key && !record.is_expired(now_ms) {
+                        current += 1;
+                    }
+                }
+            }
+        }
+        quotas.ensure_wait_capacity(&ctx.tenant_id, current, adding)
+    }
+
+    /// `WATCH`es `key` and then reads its cardinality with `command`, so a member added after
+    /// the read aborts the caller's transaction.
+    fn watch_cardinality(conn: &mut Connection, key: &str, command: &str) -> SessionResult<usize> {
+        redis::cmd("WATCH")
+            .arg(key)
+            .query::<()>(conn)
+            .map_err(redis_error)?;
+        redis::cmd(command)
+            .arg(key)
+            .query(conn)
+            .map_err(redis_error)
+    }
+
+    /// Keys holding per-session state, deleted together when a session is purged.
+    fn session_scoped_keys(&self, key: &SessionKey) -> Vec<String> {
+        vec![
+            self.session_entry_key(key),
+            self.session_waits_key(key),
+            self.session_history_key(key),
+            self.session_inbox_key(key),
+        ]
+    }
+
+    /// Deletes the per-session keys, leaving shared indices to the caller.
+    ///
+    /// Returns `1` when the session still existed and `0` otherwise, so callers can sum counts.
+    fn delete_session_keys(&self, conn: &mut Connection, key: &SessionKey) -> SessionResult<usize> {
+        let existed: bool = conn
+            .exists(self.session_entry_key(key))
+            .map_err(redis_error)?;
+        conn.del::<_, ()>(self.session_scoped_keys(key))
+            .map_err(redis_error)?;
+        Ok(usize::from(existed))
+    }
+
+    fn scan_keys(conn: &mut Connection, pattern: &str) -> SessionResult<Vec<String>> {
+        conn.scan_match::<_, String>(pattern)
+            .map_err(redis_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(redis_error)
+    }
+
+    /// Scans every session entry of the namespace for those whose context `owned` accepts, so
+    /// purges also reach sessions missing from the listing indices. Entries that no longer
+    /// decode are skipped, as they cannot be attributed to an owner.
+    fn scan_sessions(
+        &self,
+        conn: &mut Connection,
+        owned: impl Fn(&TenantCtx) -> bool,
+    ) -> SessionResult<Vec<(SessionKey, SessionData)>> {
+        let prefix = format!("{}:session:", self.namespace);
+        let entries = Self::scan_keys(
+            conn,
+            &format!("{}:session:*", Self::scan_escape(&self.namespace)),
+        )?;
+        let mut sessions = Vec::new();
+        for chunk in entries.chunks(PURGE_BATCH) {
+            let payloads: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+                .arg(chunk)
+                .query(conn)
+                .map_err(redis_error)?;
+            for (entry_key, payload) in chunk.iter().zip(payloads) {
+                let (Some(key), Some(payload)) = (entry_key.strip_prefix(&prefix), payload) else {
+                    continue;
+                };
+                if let Ok(data) = self.deserialize(&payload)
+                    && owned(&data.tenant_ctx)
+                {
+                    sessions.push((SessionKey::new(key), data));
+                }
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Deletes every key matching `pattern`, purging the sessions referenced by set members first.
+    fn purge_sets(
+        &self,
+        conn: &mut Connection,
+        pattern: &str,
+        report: &mut PurgeReport,
+        owner: Option<&UserId>,
+    ) -> SessionResult<usize> {
+        let sets = Self::scan_keys(conn, pattern)?;
+        for set in &sets {
+            let kind: String = redis::cmd("TYPE")
+                .arg(set)
+                .query(conn)
+                .map_err(redis_error)?;
+            let members: Vec<String> = match kind.as_str() {
+                "set" => conn.smembers(set).map_err(redis_error)?,
+                "zset" => conn.zrange(set, 0, -1).map_err(redis_error)?,
+                _ => Vec::new(),
+            };
+            for chunk in members.chunks(PURGE_BATCH) {
+                let owned: Vec<bool> = match owner {
+                    Some(owner) => self
+                        .load_sessions(conn, chunk)?
+                        .into_iter()
+                        .map(|data| {
+                            data.is_some_and(|data| normalize_user(&data.tenant_ctx) == Some(owner))
+                        })
+                        .collect(),
+                    None => vec![true; chunk.len()],
+                };
+                for (member, _) in chunk.iter().zip(owned).filter(|(_, owned)| *owned) {
+                    report.sessions +=
+                        self.delete_session_keys(conn, &SessionKey::new(member.as_str()))?;
+                }
+            }
+            conn.del::<_, ()>(set).map_err(redis_error)?;
+        }
+        Ok(sets.len())
+    }
+
+    /// Removes members of the index set or sorted set `index_key` whose session entry no longer
+    /// exists, returning the removed keys.
+    fn prune_missing_members(
+        &self,
+        conn: &mut Connection,
+        index_key: &str,
+        index: &'static str,
+    ) -> SessionResult<Vec<SessionKey>> {
+        let kind: String = redis::cmd("TYPE")
+            .arg(index_key)
+            .query(conn)
+            .map_err(redis_error)?;
+        let members: Vec<String> = match kind.as_str() {
+            "set" => conn.smembers(index_key).map_err(redis_error)?,
+            "zset" => conn.zrange(index_key, 0, -1).map_err(redis_error)?,
+            _ => return Ok(Vec::new()),
+        };
+        let mut missing = Vec::new();
+        for chunk in members.chunks(PURGE_BATCH) {
+            let mut pipe = redis::pipe();
+            for member in chunk {
+                pipe.exists(self.session_entry_key(&SessionKey::new(member.as_str())));
+            }
+            let exists: Vec<bool> = pipe.query(conn).map_err(redis_error)?;
+            let stale: Vec<&String> = chunk
+                .iter()
+                .zip(exists)
+                .filter(|(_, exists)| !exists)
+                .map(|(member, _)| member)
+                .collect();
+            if stale.is_empty() {
+                continue;
+            }
+            if kind == "set" {
+                conn.srem::<_, _, ()>(index_key, &stale)
+                    .map_err(redis_error)?;
+            } else {
+                conn.zrem::<_, _, ()>(index_key, &stale)
+                    .map_err(redis_error)?;
+            }
+            record_stale_index_entries(BACKEND, index, stale.len());
+            missing.extend(
+                stale
+                    .into_iter()
+                    .map(|member| SessionKey::new(member.as_str())),
+            );
+        }
+        Ok(missing)
+    }
+
+    fn scan_escape(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for ch in value.chars() {
+            if matches!(ch, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        escaped
+    }
+
+    /// Loads the unexpired waits of a session, pruning expired records along the way.
+    fn live_waits(
+        &self,
+        conn: &mut Connection,
+        key: &SessionKey,
+    ) -> SessionResult<Vec<SessionWait>> {
+        let now_ms = Self::now_millis();
+        let mut records = self.load_wait_records(conn, key)?;
+        let expired: Vec<String> = records
+            .iter()
+            .filter(|(_, record)| record.is_expired(now_ms))
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !expired.is_empty() {
+            conn.hdel::<_, _, ()>(self.session_waits_key(key), &expired)
+                .map_err(redis_error)?;
+            record_stale_index_entries(BACKEND, "session_waits", expired.len());
+            records.retain(|name, _| !expired.contains(name));
+        }
+        Ok(Self::wait_listings(records, now_ms))
+    }
+
+    /// Reads the live waits of a listing page in one pipelined round trip, skipping sessions
+    /// that are gone. Expired wait records are left for `purge_expired` to remove.
+    fn load_page_waits(
+        &self,
+        conn: &mut Connection,
+        keys: &[SessionKey],
+        sessions: &[Option<SessionData>],
+    ) -> SessionResult<Vec<Vec<SessionWait>>> {
+        let mut pipe = redis::pipe();
+        for (key, data) in keys.iter().zip(sessions) {
+            if data.is_some() {
+                pipe.hgetall(self.session_waits_key(key));
+            }
+        }
+        let replies: Vec<HashMap<String, String>> = if sessions.iter().any(Option::is_some) {
+            pipe.query(conn).map_err(redis_error)?
+        } else {
+            Vec::new()
+        };
+        let mut replies = replies.into_iter();
+        let now_ms = Self::now_millis();
+        let mut page = Vec::with_capacity(sessions.len());
+        for data in sessions {
+            let mut records = HashMap::new();
+            if data.is_some() {
+                for (name, payload) in replies.next().unwrap_or_default() {
+                    let record: WaitRecord = serde_json::from_str(&payload).map_err(serde_error)?;
+                    if !record.is_expired(now_ms) {
+                        records.insert(name, record);
+                    }
+                }
+            }
+            page.push(Self::wait_listings(records, now_ms));
+        }
+        Ok(page)
+    }
+
+    /// Converts live wait records into listings sorted by wait name.
+    fn wait_listings(records: HashMap<String, WaitRecord>, now_ms: u64) -> Vec<SessionWait> {
+        let mut waits: Vec<SessionWait> = records
+            .into_iter()
+            .map(|(name, record)| SessionWait {
+                name,
+                user_id: record.user_id,
+                scope: record.scope,
+                expires_in: record
+                    .expires_at_ms
+                    .map(|deadline| Duration::from_millis(deadline.saturating_sub(now_ms))),
+            })
+            .collect();
+        waits.sort_by(|a, b| a.name.cmp(&b.name));
+        waits
+    }
+
+    /// Returns `true` when the scope pointer `scope_key` leads to a live wait of `session`.
+    fn scope_routes(
+        &self,
+        conn: &mut Connection,
+        scope_key: &str,
+        session: &SessionKey,
+        now_ms: u64,
+    ) -> SessionResult<bool> {
+        let exists: bool = conn
+            .exists(self.session_entry_key(session))
+            .map_err(redis_error)?;
+        if !exists {
+            return Ok(false);
+        }
+        // Pointers of sessions registered before per-session wait records existed stay valid.
+        let records = self.load_wait_records(conn, session)?;
+        Ok(records.is_empty()
+            || records
+                .values()
+                .any(|record| record.scope_key == scope_key && !record.is_expired(now_ms)))
+    }
+
+    /// Points the scope of an unrouted wait back at `key`, unless another live wait took the
+    /// scope over, in which case the wait is dropped and `true` is returned.
+    fn repair_scope(
+        &self,
+        conn: &mut Connection,
+        key: &SessionKey,
+        name: &str,
+        record: &WaitRecord,
+        holder: Option<String>,
+        now_ms: u64,
+    ) -> SessionResult<bool> {
+        if let Some(holder) = holder
+            && self.scope_routes(conn, &record.scope_key, &SessionKey::new(holder), now_ms)?
+        {
+            let mut records = self.load_wait_records(conn, key)?;
+            records.remove(name);
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            pipe.hdel(self.session_waits_key(key), name).ignore();
+            if records.is_empty() {
+                pipe.srem(&record.user_waits_key, key.as_str()).ignore();
+            }
+            pipe.query::<()>(conn).map_err(redis_error)?;
+            return Ok(true);
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let set = pipe.cmd("SET").arg(&record.scope_key).arg(key.as_str());
+        if let Some(deadline) = record.expires_at_ms {
+            set.arg("PX").arg(deadline.saturating_sub(now_ms).max(1));
+        }
+        set.ignore();
+        self.queue_pointer_add(&mut pipe, &record.scope_key, record.expires_at_ms);
+        pipe.query::<()>(conn).map_err(redis_error)?;
+        Ok(false)
+    }
+
+    /// Resolves the scope pointer at `scope_key` to a session `ctx` may read.
+    ///
+    /// With `cleanup`, a pointer that does not resolve is dropped along with its user wait entry.
+    fn resolve_scope(
+        &self,
+        conn: &mut Connection,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope_key: &str,
+        cleanup: bool,
+    ) -> SessionResult<Option<SessionKey>> {
+        let stored: Option<String> = conn.get(scope_key).map_err(redis_error)?;
+        let Some(raw_key) = stored else {
+            return Ok(None);
+        };
+        let session_key = SessionKey::new(raw_key);
+        let readable = match self.load_session(conn, &session_key)? {
+            Some(data)
+                if self
+                    .options
+                    .fence
+                    .permits_read(&data.tenant_ctx, ctx, user_id) =>
+            {
+                // Sessions registered before per-session wait records existed have an empty
+                // waits hash; their scope pointers remain authoritative.
+                let records = self.load_wait_records(conn, &session_key)?;
+                records.is_empty() || records.values().any(|record| record.scope_key == scope_key)
+            }
+            _ => false,
+        };
+        if readable {
+            return Ok(Some(session_key));
+        }
+        if cleanup {
+            self.drop_stale_scope(conn, ctx, user_id, scope_key, &session_key)?;
+        }
+        Ok(None)
+    }
+
+    /// Returns the members of the user wait set at `set_key` that `ctx` may read.
+    ///
+    /// With `cleanup`, members that are gone or fenced off are removed from the set.
+    fn readable_waits(
+        &self,
+        conn: &mut Connection,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        set_key: &str,
+        cleanup: bool,
+    ) -> SessionResult<Vec<SessionKey>> {
+        let stored: Vec<String> = conn.smembers(set_key).map_err(redis_error)?;
         let mut results = Vec::new();
-        for raw_key in stored {
-            let session_key = SessionKey::new(raw_key.clone());
-            match self.get_session(&session_key)? {
-                Some(data) => {
-                    let stored_ctx = &data.tenant_ctx;
-                    if stored_ctx.env == ctx.env
-                        && stored_ctx.tenant_id == ctx.tenant_id
-                        && Self::normalize_team(stored_ctx) == Self::normalize_team(ctx)
-                    {
-                        if let Some(stored_user) = Self::normalize_user(stored_ctx)
-                            && stored_user != user_id
-                        {
-                            let _: () = conn
-                                .srem::<_, _, ()>(&user_waits_key, raw_key)
-                                .map_err(redis_error)?;
-                            continue;
-                        }
+        for chunk in stored.chunks(PURGE_BATCH) {
+            let mut stale = Vec::new();
+            for (raw_key, data) in chunk.iter().zip(self.load_sessions(conn, chunk)?) {
+                let fenced = data.is_some_and(|data| {
+                    self.options
+                        .fence
+                        .permits_read(&data.tenant_ctx, ctx, user_id)
+                });
+                if fenced {
+                    results.push(SessionKey::new(raw_key.as_str()));
+                } else if cleanup {
+                    stale.push(raw_key);
+                }
+            }
+            if !stale.is_empty() {
+                conn.srem::<_, _, ()>(set_key, &stale)
+                    .map_err(redis_error)?;
+                record_stale_index_entries(BACKEND, "user_waits", stale.len());
+            }
+        }
+        Ok(results)
+    }
+
+    /// Escaped `env:tenant` segment for matching a tenant's keys with `SCAN`.
+    fn tenant_scan_prefix(env: &EnvId, tenant: &TenantId) -> String {
+        format!(
+            "{}:{}",
+            Self::scan_escape(env.as_str()),
+            Self::scan_escape(tenant.as_str())
+        )
+    }
+
+    fn drop_stale_scope(
+        &self,
+        conn: &mut Connection,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope_key: &str,
+        session_key: &SessionKey,
+    ) -> SessionResult<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(scope_key)
+            .ignore()
+            .srem(self.user_waits_key(ctx, user_id), session_key.as_str())
+            .ignore();
+        self.queue_pointer_remove(&mut pipe, scope_key);
+        pipe.query::<()>(conn).map_err(redis_error)?;
+        record_stale_index_entries(BACKEND, "scope", 1);
+        Ok(())
+    }
+}
+
+/// Splits the `:`-separated identifier fields at the end of an index key.
+fn parse_index_fields<const N: usize>(rest: &str) -> Option<[&str; N]> {
+    let mut fields = rest.split(':');
+    let parsed = std::array::from_fn(|_| fields.next().unwrap_or_default());
+    (fields.next().is_none() && parsed.iter().all(|field| !field.is_empty())).then_some(parsed)
+}
+
+/// Reads the env, tenant, team (`-` for none) and user leading an index key's fields.
+fn index_owner(fields: &[&str]) -> Option<(EnvId, TenantId, Option<TeamId>, UserId)> {
+    let team = match fields[2] {
+        "-" => None,
+        team => Some(TeamId::try_from(team).ok()?),
+    };
+    Some((
+        EnvId::try_from(fields[0]).ok()?,
+        TenantId::try_from(fields[1]).ok()?,
+        team,
+        UserId::try_from(fields[3]).ok()?,
+    ))
+}
+
+/// Wait registration persisted in the per-session waits hash.
+#[derive(Serialize, Deserialize)]
+struct WaitRecord {
+    user_id: UserId,
+    scope: ReplyScope,
+    scope_key: String,
+    user_waits_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+/// Outcome of [`RedisSessionStore::compare_and_set`].
+enum CasOutcome {
+    /// The payload was replaced.
+    Written,
+    /// Another writer changed the payload first.
+    Changed,
+    /// The session is gone.
+    Missing,
+}
+
+/// Archived session payload stored in the per-session history list, newest first.
+///
+/// Entries are framed as `{version}:{recorded_at_ms}:` followed by the stored payload bytes, as
+/// written by the `archive` Lua helper.
+struct HistoryRecord {
+    version: u64,
+    recorded_at_ms: u64,
+    payload: Vec<u8>,
+}
+
+impl HistoryRecord {
+    fn decode(mut entry: Vec<u8>) -> SessionResult<Self> {
+        let malformed = || unsupported_format("malformed history entry");
+        let mut fields = entry.splitn(3, |byte| *byte == b':');
+        let mut number = || -> SessionResult<u64> {
+            let field = fields.next().ok_or_else(malformed)?;
+            std::str::from_utf8(field)
+                .ok()
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(malformed)
+        };
+        let version = number()?;
+        let recorded_at_ms = number()?;
+        let header = format!("{version}:{recorded_at_ms}:").len();
+        if entry.len() < header {
+            return Err(malformed());
+        }
+        let payload = entry.split_off(header);
+        Ok(Self {
+            version,
+            recorded_at_ms,
+            payload,
+        })
+    }
+}
+
+impl WaitRecord {
+    fn is_expired(&self, now_ms: u64) -> bool {
+        self.expires_at_ms
+            .is_some_and(|deadline| deadline <= now_ms)
+    }
+}
+
+impl SessionStore for RedisSessionStore {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
+        let key = SessionKey::new(Uuid::new_v4().to_string());
+        let payload = self.serialize(&data)?;
+        self.enforce_payload_limits(&key, ctx, &payload)?;
+        let mut conn = self.conn()?;
+        for _ in 0..CAS_ATTEMPTS {
+            self.enforce_session_quota(&mut conn, ctx)?;
+            let created = StatsUpdate::created(&payload);
+            let mut pipe = redis::pipe();
+            pipe.atomic()
+                .set(self.session_entry_key(&key), &payload)
+                .ignore();
+            self.queue_index_add(&mut pipe, &key, &data.tenant_ctx);
+            self.queue_stats(&mut pipe, &key, &data.tenant_ctx, created);
+            if self.commit(&mut conn, &pipe)? {
+                return Ok(key);
+            }
+        }
+        Err(concurrent_modification(&key))
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
+        let payload = self.serialize(&data)?;
+        self.enforce_payload_limits(key, ctx, &payload)?;
+        let mut conn = self.conn()?;
+        let entry_key = self.session_entry_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            // Watching the entry stands in for `SET NX`, whose outcome a transaction only reports
+            // after the index and stats updates were applied.
+            redis::cmd("WATCH")
+                .arg(&entry_key)
+                .query::<()>(&mut conn)
+                .map_err(redis_error)?;
+            let exists: bool = conn.exists(&entry_key).map_err(redis_error)?;
+            if exists {
+                return Err(session_exists(key));
+            }
+            self.enforce_session_quota(&mut conn, ctx)?;
+            let created = StatsUpdate::created(&payload);
+            let mut pipe = redis::pipe();
+            pipe.atomic().set(&entry_key, &payload).ignore();
+            self.queue_index_add(&mut pipe, key, &data.tenant_ctx);
+            self.queue_stats(&mut pipe, key, &data.tenant_ctx, created);
+            if self.commit(&mut conn, &pipe)? {
+                return Ok(());
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        let mut conn = self.conn()?;
+        self.load_session(&mut conn, key)
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        let payload = self.serialize(&data)?;
+        self.enforce_payload_limits(key, &data.tenant_ctx, &payload)?;
+        let mut conn = self.conn()?;
+        let entry_key = self.session_entry_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let Some(existing_payload) = existing else {
+                return Err(not_found(key));
+            };
+            let previous = self.deserialize(&existing_payload)?;
+            self.options
+                .fence
+                .check_update(&previous.tenant_ctx, &data.tenant_ctx)
+                .map_err(fence_rejected)?;
+            match self.compare_and_set(
+                &mut conn,
+                key,
+                &data.tenant_ctx,
+                &existing_payload,
+                &payload,
+                true,
+            )? {
+                CasOutcome::Written => return Ok(()),
+                CasOutcome::Missing => return Err(not_found(key)),
+                CasOutcome::Changed => continue,
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        let mut conn = self.conn()?;
+        if self.purge_session(&mut conn, key)? {
+            Ok(())
+        } else {
+            Err(not_found(key))
+        }
+    }
+
+    /// Scans every session entry and rewrites those stored in an older format version or with a
+    /// codec other than the configured one.
+    ///
+    /// Rewrites go through the compare-and-set script, so sessions changed concurrently are left
+    /// to the writer, which already stores the current format. Archived history snapshots are
+    /// still upgraded on read.
+    ///
+    /// Every scanned session is also added to its listing indices, and afterwards every
+    /// tenant's stats counters are backfilled, so run it once after upgrading from a release
+    /// without them: listings, quotas, purges by index and stats only see indexed sessions.
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        let mut conn = self.conn()?;
+        let namespace = Self::scan_escape(&self.namespace);
+        let prefix = format!("{}:session:", self.namespace);
+        let target = self.options.migrations.target_version();
+        let mut report = MigrationReport::default();
+        for entry_key in Self::scan_keys(&mut conn, &format!("{namespace}:session:*"))? {
+            let Some(key) = entry_key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let Some(existing_payload) = existing else {
+                continue;
+            };
+            report.scanned += 1;
+            let (version, data) = self.options.decode_versioned_payload(&existing_payload)?;
+            let mut pipe = redis::pipe();
+            self.queue_index_add(&mut pipe, &SessionKey::new(key), &data.tenant_ctx);
+            pipe.query::<()>(&mut conn).map_err(redis_error)?;
+            let same_codec = existing_payload.first() == Some(&self.options.codec.tag());
+            if version == target && same_codec {
+                continue;
+            }
+            let payload = self.serialize(&data)?;
+            let outcome = self.compare_and_set(
+                &mut conn,
+                &SessionKey::new(key),
+                &data.tenant_ctx,
+                &existing_payload,
+                &payload,
+                false,
+            )?;
+            if matches!(outcome, CasOutcome::Written) {
+                report.migrated += 1;
+            }
+        }
+        let index_prefix = format!("{}:index:tenant:", self.namespace);
+        for index_key in Self::scan_keys(&mut conn, &format!("{namespace}:index:tenant:*"))? {
+            let Some([env, tenant]) = index_key
+                .strip_prefix(&index_prefix)
+                .and_then(parse_index_fields::<2>)
+            else {
+                continue;
+            };
+            if let (Ok(env), Ok(tenant)) = (EnvId::try_from(env), TenantId::try_from(tenant)) {
+                self.backfill_stats(&mut conn, &env, &tenant)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reads, patches and writes back the payload, committing through a compare-and-set script
+    /// that is retried when another writer changed the session in between.
+    ///
+    /// Merge and cursor patches on JSON-codec payloads are applied in one Lua script instead, see
+    /// [`MERGE_PATCH_SCRIPT`]. Patches the script cannot apply exactly fall back to the
+    /// client-side path, where the whole payload travels both ways.
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        let mut conn = self.conn()?;
+        if let Some(patched) = self.patch_in_place(&mut conn, key, patch)? {
+            return Ok(patched);
+        }
+        let entry_key = self.session_entry_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let Some(existing_payload) = existing else {
+                return Err(not_found(key));
+            };
+            let patched = patch.apply(&self.deserialize(&existing_payload)?)?;
+            let payload = self.serialize(&patched)?;
+            self.enforce_payload_limits(key, &patched.tenant_ctx, &payload)?;
+            match self.compare_and_set(
+                &mut conn,
+                key,
+                &patched.tenant_ctx,
+                &existing_payload,
+                &payload,
+                true,
+            )? {
+                CasOutcome::Written => return Ok(patched),
+                CasOutcome::Missing => return Err(not_found(key)),
+                CasOutcome::Changed => continue,
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        let mut conn = self.conn()?;
+        let exists: bool = conn
+            .exists(self.session_entry_key(key))
+            .map_err(redis_error)?;
+        if !exists {
+            return Err(not_found(key));
+        }
+        self.load_history(&mut conn, key)?
+            .into_iter()
+            .map(|record| {
+                let data = self.deserialize(&record.payload)?;
+                Ok(SessionVersion {
+                    version: record.version,
+                    recorded_at_ms: record.recorded_at_ms,
+                    cursor: data.cursor,
+                })
+            })
+            .collect()
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        let mut conn = self.conn()?;
+        let exists: bool = conn
+            .exists(self.session_entry_key(key))
+            .map_err(redis_error)?;
+        if !exists {
+            return Err(not_found(key));
+        }
+        self.load_history(&mut conn, key)?
+            .into_iter()
+            .find(|record| record.version == version)
+            .map(|record| self.deserialize(&record.payload))
+            .transpose()
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        let mut conn = self.conn()?;
+        let entry_key = self.session_entry_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let Some(existing_payload) = existing else {
+                return Err(not_found(key));
+            };
+            let Some(record) = self
+                .load_history(&mut conn, key)?
+                .into_iter()
+                .find(|record| record.version == version)
+            else {
+                return Err(version_not_found(key, version));
+            };
+            // The archived bytes may predate the current format or codec; restore the decoded
+            // snapshot as the store writes today.
+            let snapshot = self.deserialize(&record.payload)?;
+            let payload = self.serialize(&snapshot)?;
+            self.enforce_payload_limits(key, &snapshot.tenant_ctx, &payload)?;
+            match self.compare_and_set(
+                &mut conn,
+                key,
+                &snapshot.tenant_ctx,
+                &existing_payload,
+                &payload,
+                true,
+            )? {
+                CasOutcome::Written => return Ok(()),
+                CasOutcome::Missing => return Err(not_found(key)),
+                CasOutcome::Changed => continue,
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
+        self.options
+            .fence
+            .check_wait_user(ctx, user_id, &data)
+            .map_err(fence_rejected)?;
+        validate_waits(waits)?;
+        let payload = self.serialize(&data)?;
+        self.enforce_payload_limits(session_key, ctx, &payload)?;
+        let mut conn = self.conn()?;
+        let entry_key = self.session_entry_key(session_key);
+        let waits_key = self.session_waits_key(session_key);
+        for _ in 0..CAS_ATTEMPTS {
+            // The entry decides whether the session is reported as created and which payload is
+            // archived; it may not move before the transaction commits.
+            redis::cmd("WATCH")
+                .arg(&entry_key)
+                .query::<()>(&mut conn)
+                .map_err(redis_error)?;
+            let existing: Option<Vec<u8>> = conn.get(&entry_key).map_err(redis_error)?;
+            let created = existing.is_none();
+            if let Some(existing) = &existing {
+                let previous = self.deserialize(existing)?;
+                self.options
+                    .fence
+                    .check_update(&previous.tenant_ctx, &data.tenant_ctx)
+                    .map_err(fence_rejected)?;
+            } else {
+                self.enforce_session_quota(&mut conn, ctx)?;
+            }
+            self.enforce_wait_quota(&mut conn, ctx, user_id, session_key, waits.len())?;
+            let user_waits_key = self.user_waits_key(ctx, user_id);
+            let previous_waits = self.load_wait_records(&mut conn, session_key)?;
+            let now_ms = Self::now_millis();
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            self.queue_wait_release(&mut conn, &mut pipe, session_key, &previous_waits)?;
+            for wait in waits {
+                let scope_key = self.scope_wait_key(ctx, user_id, &wait.scope);
+                let holder: Option<String> = conn.get(&scope_key).map_err(redis_error)?;
+                if let Some(holder) = holder
+                    && holder != session_key.as_str()
+                {
+                    self.queue_scope_takeover(
+                        &mut conn,
+                        &mut pipe,
+                        &SessionKey::new(holder),
+                        ctx,
+                        &scope_key,
+                        &user_waits_key,
+                    )?;
+                }
+                pipe.set(&scope_key, session_key.as_str()).ignore();
+                if let Some(ttl) = wait.ttl {
+                    pipe.pexpire(&scope_key, Self::ttl_millis(ttl)).ignore();
+                }
+                let expires_at_ms = wait.ttl.map(|ttl| {
+                    now_ms.saturating_add(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX))
+                });
+                self.queue_pointer_add(&mut pipe, &scope_key, expires_at_ms);
+                let record = WaitRecord {
+                    user_id: user_id.clone(),
+                    scope: wait.scope.clone(),
+                    scope_key,
+                    user_waits_key: user_waits_key.clone(),
+                    expires_at_ms,
+                };
+                let record = serde_json::to_string(&record).map_err(serde_error)?;
+                pipe.hset(&waits_key, &wait.name, record).ignore();
+            }
+            pipe.set(&entry_key, &payload).ignore();
+            pipe.sadd(&user_waits_key, session_key.as_str()).ignore();
+            self.queue_index_add(&mut pipe, session_key, ctx);
+            let session_ttl_ms = session_ttl(waits).map(Self::ttl_millis);
+            self.queue_stats(
+                &mut pipe,
+                session_key,
+                ctx,
+                StatsUpdate {
+                    payload_bytes: Some(payload.len()),
+                    waits: Some(waits.len()),
+                    expires_at_ms: Some(session_ttl_ms.map(|ttl_ms| now_ms + ttl_ms as u64)),
+                },
+            );
+            if let Some(ttl_ms) = session_ttl_ms {
+                pipe.pexpire(&entry_key, ttl_ms).ignore();
+                pipe.pexpire(&waits_key, ttl_ms).ignore();
+            }
+            if let Some(existing) = existing {
+                self.queue_archive(&mut pipe, session_key, existing, session_ttl_ms);
+            }
+            if self.commit(&mut conn, &pipe)? {
+                return Ok(created);
+            }
+        }
+        Err(concurrent_modification(session_key))
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        let mut conn = self.conn()?;
+        let exists: bool = conn
+            .exists(self.session_entry_key(key))
+            .map_err(redis_error)?;
+        if !exists {
+            return Err(not_found(key));
+        }
+        self.live_waits(&mut conn, key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        let mut conn = self.conn()?;
+        let ctx = self.stored_ctx(&mut conn, key)?;
+        let mut records = self.load_wait_records(&mut conn, key)?;
+        let Some(record) = records.remove(name) else {
+            return Ok(false);
+        };
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        self.release_scope(&mut conn, &mut pipe, &record.scope_key, key)?;
+        pipe.hdel(self.session_waits_key(key), name).ignore();
+        if records.is_empty() {
+            pipe.srem(&record.user_waits_key, key.as_str()).ignore();
+        }
+        self.queue_stats(
+            &mut pipe,
+            key,
+            &ctx,
+            StatsUpdate {
+                waits: Some(records.len()),
+                ..StatsUpdate::default()
+            },
+        );
+        self.commit(&mut conn, &pipe)?;
+        Ok(true)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        let mut conn = self.conn()?;
+        let ctx = self.stored_ctx(&mut conn, key)?;
+        let waits_key = self.session_waits_key(key);
+        for _ in 0..CAS_ATTEMPTS {
+            // The reported names must be the waits this call removed, not a concurrent writer's.
+            redis::cmd("WATCH")
+                .arg(&waits_key)
+                .query::<()>(&mut conn)
+                .map_err(redis_error)?;
+            let records = self.load_wait_records(&mut conn, key)?;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            self.queue_wait_release(&mut conn, &mut pipe, key, &records)?;
+            self.queue_stats(
+                &mut pipe,
+                key,
+                &ctx,
+                StatsUpdate {
+                    waits: Some(0),
+                    ..StatsUpdate::default()
+                },
+            );
+            if self.commit(&mut conn, &pipe)? {
+                let mut names: Vec<String> = records.into_keys().collect();
+                names.sort();
+                return Ok(names);
+            }
+        }
+        Err(concurrent_modification(key))
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        let mut conn = self.conn()?;
+        let scope_key = self.scope_wait_key(ctx, user_id, scope);
+        if let Some(session_key) = self.resolve_scope(&mut conn, ctx, user_id, &scope_key, true)? {
+            return Ok(Some(session_key));
+        }
+        if !self.options.fence.reads_across_teams(ctx) {
+            return Ok(None);
+        }
+        let pattern = format!(
+            "{}:waits:scope:{}:*:{}:{}",
+            Self::scan_escape(&self.namespace),
+            Self::tenant_scan_prefix(&ctx.env, &ctx.tenant_id),
+            Self::scan_escape(user_id.as_str()),
+            scope.scope_hash()
+        );
+        let mut found = Vec::new();
+        for other in Self::scan_keys(&mut conn, &pattern)? {
+            if other == scope_key {
+                continue;
+            }
+            if let Some(session_key) = self.resolve_scope(&mut conn, ctx, user_id, &other, false)? {
+                found.push(session_key);
+            }
+        }
+        Ok(found.into_iter().min_by(|a, b| a.as_str().cmp(b.as_str())))
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        let mut conn = self.conn()?;
+        let user_waits_key = self.user_waits_key(ctx, user_id);
+        let mut results = self.readable_waits(&mut conn, ctx, user_id, &user_waits_key, true)?;
+        if self.options.fence.reads_across_teams(ctx) {
+            let pattern = format!(
+                "{}:waits:user:{}:*:{}",
+                Self::scan_escape(&self.namespace),
+                Self::tenant_scan_prefix(&ctx.env, &ctx.tenant_id),
+                Self::scan_escape(user_id.as_str())
+            );
+            for other in Self::scan_keys(&mut conn, &pattern)? {
+                if other == user_waits_key {
+                    continue;
+                }
+                for session_key in self.readable_waits(&mut conn, ctx, user_id, &other, false)? {
+                    if !results.contains(&session_key) {
                         results.push(session_key);
-                    } else {
-                        let _: () = conn
-                            .srem::<_, _, ()>(&user_waits_key, raw_key)
-                            .map_err(redis_error)?;
                     }
                 }
-                None => {
-                    let _: () = conn
-                        .srem::<_, _, ()>(&user_waits_key, raw_key)
-                        .map_err(redis_error)?;
-                }
             }
         }
         Ok(results)
@@ -427,21 +2416,501 @@ impl SessionStore for RedisSessionStore {
         ctx: &TenantCtx,
         user_id: &UserId,
         scope: &ReplyScope,
-    ) -> SessionResult<()> {
+    ) -> SessionResult<Option<SessionKey>> {
         let mut conn = self.conn()?;
         let scope_key = self.scope_wait_key(ctx, user_id, scope);
         let stored: Option<String> = conn.get(&scope_key).map_err(redis_error)?;
-        if let Some(raw_key) = stored {
-            let session_key = SessionKey::new(raw_key.clone());
-            let entry_key = self.session_entry_key(&session_key);
-            let _: () = conn.del(&entry_key).map_err(redis_error)?;
-            let _: () = conn.del(&scope_key).map_err(redis_error)?;
-            let user_waits_key = self.user_waits_key(ctx, user_id);
-            let _: () = conn
-                .srem::<_, _, ()>(&user_waits_key, raw_key)
+        let Some(raw_key) = stored else {
+            return Ok(None);
+        };
+        let session_key = SessionKey::new(raw_key);
+        if !self.scope_routes(&mut conn, &scope_key, &session_key, Self::now_millis())? {
+            // An expired or re-routed wait only costs its pointer; the session keeps its other
+            // waits.
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            self.release_scope(&mut conn, &mut pipe, &scope_key, &session_key)?;
+            pipe.query::<()>(&mut conn).map_err(redis_error)?;
+            record_stale_index_entries(BACKEND, "scope", 1);
+            return Ok(None);
+        }
+        let removed = self.purge_session(&mut conn, &session_key)?;
+        self.drop_stale_scope(&mut conn, ctx, user_id, &scope_key, &session_key)?;
+        Ok(removed.then_some(session_key))
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        validate_page_limit(limit)?;
+        let mut conn = self.conn()?;
+        self.list_page(&mut conn, filter, cursor, limit)
+    }
+
+    /// Derives the tenants from the tenant listing indices, which Redis drops once empty.
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        let mut conn = self.conn()?;
+        let namespace = Self::scan_escape(&self.namespace);
+        let prefix = format!("{}:index:tenant:", self.namespace);
+        let mut tenants = Vec::new();
+        for index_key in Self::scan_keys(&mut conn, &format!("{namespace}:index:tenant:*"))? {
+            let Some((env, tenant)) = index_key
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.split_once(':'))
+            else {
+                continue;
+            };
+            if let (Ok(env), Ok(tenant)) = (EnvId::try_from(env), TenantId::try_from(tenant)) {
+                tenants.push((env, tenant));
+            }
+        }
+        tenants.sort();
+        tenants.dedup();
+        Ok(tenants)
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let mut conn = self.conn()?;
+        let mut report = PurgeReport::default();
+        let tenant_index = self.tenant_index_key(env, tenant);
+        // Drain the listing index first; processed members are removed so an interrupted purge
+        // resumes where it stopped.
+        loop {
+            let members: Vec<String> = redis::cmd("ZRANGEBYLEX")
+                .arg(&tenant_index)
+                .arg("-")
+                .arg("+")
+                .arg("LIMIT")
+                .arg(0)
+                .arg(PURGE_BATCH)
+                .query(&mut conn)
+                .map_err(redis_error)?;
+            if members.is_empty() {
+                break;
+            }
+            for member in &members {
+                report.sessions += self.delete_session_keys(&mut conn, &SessionKey::new(member))?;
+            }
+            conn.zrem::<_, _, ()>(&tenant_index, members)
                 .map_err(redis_error)?;
         }
-        Ok(())
+        // Sessions written before the indices existed, or never indexed, are found by scanning.
+        let unindexed =
+            self.scan_sessions(&mut conn, |ctx| ctx.env == *env && ctx.tenant_id == *tenant)?;
+        for (key, _) in unindexed {
+            report.sessions += self.delete_session_keys(&mut conn, &key)?;
+        }
+
+        let prefix = Self::tenant_scan_prefix(env, tenant);
+        let namespace = Self::scan_escape(&self.namespace);
+        let user_wait_sets = self.purge_sets(
+            &mut conn,
+            &format!("{namespace}:waits:user:{prefix}:*"),
+            &mut report,
+            None,
+        )?;
+        report.user_wait_sets = user_wait_sets;
+        self.purge_sets(
+            &mut conn,
+            &format!("{namespace}:index:team:{prefix}:*"),
+            &mut report,
+            None,
+        )?;
+        for pointer in Self::scan_keys(&mut conn, &format!("{namespace}:waits:scope:{prefix}:*"))? {
+            conn.del::<_, ()>(&pointer).map_err(redis_error)?;
+            report.scope_pointers += 1;
+        }
+        for event in Self::scan_keys(&mut conn, &format!("{namespace}:event:{prefix}:*"))? {
+            conn.del::<_, ()>(&event).map_err(redis_error)?;
+        }
+        let mut stats_keys = self.stats_keys(env, tenant).to_vec();
+        stats_keys.push(self.pointer_stats_key(env, tenant));
+        conn.del::<_, ()>(stats_keys).map_err(redis_error)?;
+        Ok(report)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let mut conn = self.conn()?;
+        let mut report = PurgeReport::default();
+        let tenant_index = self.tenant_index_key(env, tenant);
+        let mut start = "-".to_string();
+        loop {
+            let members: Vec<String> = redis::cmd("ZRANGEBYLEX")
+                .arg(&tenant_index)
+                .arg(&start)
+                .arg("+")
+                .arg("LIMIT")
+                .arg(0)
+                .arg(PURGE_BATCH)
+                .query(&mut conn)
+                .map_err(redis_error)?;
+            let Some(last) = members.last() else {
+                break;
+            };
+            start = format!("({last}");
+            let sessions = self.load_sessions(&mut conn, &members)?;
+            for (member, data) in members.into_iter().zip(sessions) {
+                let key = SessionKey::new(member);
+                let Some(data) = data else {
+                    continue;
+                };
+                if normalize_user(&data.tenant_ctx) != Some(user) {
+                    continue;
+                }
+                report.sessions += self.delete_session_keys(&mut conn, &key)?;
+                let mut pipe = redis::pipe();
+                self.queue_index_remove(&mut pipe, &key, &data.tenant_ctx);
+                self.queue_stats_drop(&mut pipe, &key, env, tenant);
+                self.commit(&mut conn, &pipe)?;
+            }
+        }
+        let unindexed = self.scan_sessions(&mut conn, |ctx| {
+            ctx.env == *env && ctx.tenant_id == *tenant && normalize_user(ctx) == Some(user)
+        })?;
+        for (key, data) in unindexed {
+            report.sessions += self.delete_session_keys(&mut conn, &key)?;
+            let mut pipe = redis::pipe();
+            self.queue_index_remove(&mut pipe, &key, &data.tenant_ctx);
+            self.queue_stats_drop(&mut pipe, &key, env, tenant);
+            self.commit(&mut conn, &pipe)?;
+        }
+
+        let namespace = Self::scan_escape(&self.namespace);
+        let prefix = Self::tenant_scan_prefix(env, tenant);
+        let user_segment = Self::scan_escape(user.as_str());
+        let user_wait_sets = self.purge_sets(
+            &mut conn,
+            &format!("{namespace}:waits:user:{prefix}:*:{user_segment}"),
+            &mut report,
+            Some(user),
+        )?;
+        report.user_wait_sets = user_wait_sets;
+        for pointer in Self::scan_keys(
+            &mut conn,
+            &format!("{namespace}:waits:scope:{prefix}:*:{user_segment}:*"),
+        )? {
+            let mut pipe = redis::pipe();
+            pipe.atomic().del(&pointer).ignore();
+            self.queue_pointer_remove(&mut pipe, &pointer);
+            pipe.query::<()>(&mut conn).map_err(redis_error)?;
+            report.scope_pointers += 1;
+        }
+        Ok(report)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        // Redis drops expired entries through their TTL; what remains is index membership, so the
+        // sessions reported as expired are the tenant index members without an entry.
+        let mut conn = self.conn()?;
+        let namespace = Self::scan_escape(&self.namespace);
+        let mut expired = Vec::new();
+        for index_key in Self::scan_keys(&mut conn, &format!("{namespace}:index:tenant:*"))? {
+            expired.extend(self.prune_missing_members(&mut conn, &index_key, "listing")?);
+        }
+        for (pattern, index) in [("index:team", "listing"), ("waits:user", "user_waits")] {
+            for index_key in Self::scan_keys(&mut conn, &format!("{namespace}:{pattern}:*"))? {
+                self.prune_missing_members(&mut conn, &index_key, index)?;
+            }
+        }
+        let prefix = format!("{}:stats:", self.namespace);
+        for expiries in Self::scan_keys(&mut conn, &format!("{namespace}:stats:*:expiry"))? {
+            let Some((env, tenant)) = expiries
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(":expiry"))
+                .and_then(|rest| rest.split_once(':'))
+            else {
+                continue;
+            };
+            if let (Ok(env), Ok(tenant)) = (EnvId::try_from(env), TenantId::try_from(tenant)) {
+                self.prune_expired_stats(&mut conn, &env, &tenant)?;
+            }
+        }
+        for pointers in Self::scan_keys(&mut conn, &format!("{namespace}:stats:*:pointers"))? {
+            conn.zrembyscore::<_, _, _, ()>(&pointers, "-inf", Self::now_millis())
+                .map_err(redis_error)?;
+        }
+        Ok(expired)
+    }
+
+    /// Scans scope pointers, user wait sets and sessions of the namespace in turn.
+    ///
+    /// Registration, removal and clearing are not atomic across these keys, so crashes and
+    /// takeovers can leave them out of step. Repairs delete scope pointers through a
+    /// compare-and-delete script, so pointers re-registered during the scan are kept.
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        let mut conn = self.conn()?;
+        let namespace = Self::scan_escape(&self.namespace);
+        let now_ms = Self::now_millis();
+        let mut report = IndexRepairReport {
+            repaired: repair,
+            ..IndexRepairReport::default()
+        };
+
+        let scope_prefix = format!("{}:waits:scope:", self.namespace);
+        for scope_key in Self::scan_keys(&mut conn, &format!("{namespace}:waits:scope:*"))? {
+            let holder: Option<String> = conn.get(&scope_key).map_err(redis_error)?;
+            let Some(holder) = holder else {
+                continue;
+            };
+            report.scope_pointers += 1;
+            let session = SessionKey::new(holder.as_str());
+            if self.scope_routes(&mut conn, &scope_key, &session, now_ms)? {
+                continue;
+            }
+            let Some(fields) = scope_key
+                .strip_prefix(&scope_prefix)
+                .and_then(|rest| parse_index_fields::<5>(rest))
+            else {
+                continue;
+            };
+            let Some((env, tenant, team, user)) = index_owner(&fields) else {
+                continue;
+            };
+            report.dangling_scopes.push(DanglingScope {
+                env,
+                tenant,
+                team,
+                user,
+                scope_hash: fields[4].to_string(),
+                session,
+            });
+            if repair {
+                let mut delete = self.delete_script.key(&scope_key);
+                if let Some(pointers) = self.pointer_stats_key_of(&scope_key) {
+                    delete.key(pointers);
+                }
+                delete
+                    .arg(&holder)
+                    .invoke::<i64>(&mut conn)
+                    .map_err(redis_error)?;
+                record_stale_index_entries(BACKEND, "scope", 1);
+            }
+        }
+
+        let user_prefix = format!("{}:waits:user:", self.namespace);
+        for set_key in Self::scan_keys(&mut conn, &format!("{namespace}:waits:user:*"))? {
+            let Some((env, tenant, team, user)) = set_key
+                .strip_prefix(&user_prefix)
+                .and_then(|rest| parse_index_fields::<4>(rest))
+                .and_then(|fields| index_owner(&fields))
+            else {
+                continue;
+            };
+            let members: Vec<String> = conn.smembers(&set_key).map_err(redis_error)?;
+            for member in members {
+                report.user_wait_members += 1;
+                let session = SessionKey::new(member.as_str());
+                let records = self.load_wait_records(&mut conn, &session)?;
+                let exists: bool = conn
+                    .exists(self.session_entry_key(&session))
+                    .map_err(redis_error)?;
+                // Sessions registered before per-session wait records existed keep their user
+                // set membership.
+                let waiting = exists
+                    && (records.is_empty()
+                        || records.values().any(|record| {
+                            record.user_waits_key == set_key && !record.is_expired(now_ms)
+                        }));
+                if waiting {
+                    continue;
+                }
+                if repair {
+                    conn.srem::<_, _, ()>(&set_key, &member)
+                        .map_err(redis_error)?;
+                    record_stale_index_entries(BACKEND, "user_waits", 1);
+                }
+                report.orphaned_user_waits.push(OrphanedUserWait {
+                    env: env.clone(),
+                    tenant: tenant.clone(),
+                    team: team.clone(),
+                    user: user.clone(),
+                    session,
+                });
+            }
+        }
+
+        let session_prefix = format!("{}:session:", self.namespace);
+        for entry_key in Self::scan_keys(&mut conn, &format!("{namespace}:session:*"))? {
+            let Some(key) = entry_key.strip_prefix(&session_prefix) else {
+                continue;
+            };
+            let key = SessionKey::new(key);
+            report.sessions += 1;
+            let mut records: Vec<(String, WaitRecord)> = self
+                .load_wait_records(&mut conn, &key)?
+                .into_iter()
+                .filter(|(_, record)| !record.is_expired(now_ms))
+                .collect();
+            records.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, record) in records {
+                let holder: Option<String> = conn.get(&record.scope_key).map_err(redis_error)?;
+                if holder.as_deref() != Some(key.as_str()) {
+                    report.unindexed_waits.push(IndexIssue {
+                        key: key.clone(),
+                        wait: name.clone(),
+                        kind: IndexIssueKind::ScopeNotRouted,
+                    });
+                    if repair
+                        && self.repair_scope(&mut conn, &key, &name, &record, holder, now_ms)?
+                    {
+                        // The wait lost its scope to another session and was dropped.
+                        continue;
+                    }
+                }
+                let indexed: bool = conn
+                    .sismember(&record.user_waits_key, key.as_str())
+                    .map_err(redis_error)?;
+                if !indexed {
+                    report.unindexed_waits.push(IndexIssue {
+                        key: key.clone(),
+                        wait: name,
+                        kind: IndexIssueKind::UserNotIndexed,
+                    });
+                    if repair {
+                        conn.sadd::<_, _, ()>(&record.user_waits_key, key.as_str())
+                            .map_err(redis_error)?;
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Sends `PING` on a fresh connection; round trips above 250 ms are reported as degraded.
+    fn health(&self) -> StoreHealth {
+        let started = Instant::now();
+        let ping = self.conn().and_then(|mut conn| {
+            redis::cmd("PING")
+                .query::<String>(&mut conn)
+                .map_err(redis_error)
+        });
+        let latency = started.elapsed();
+        let health = match ping {
+            Ok(_) => StoreHealth::healthy(BACKEND).with_latency(latency),
+            Err(err) => StoreHealth::unavailable(BACKEND, err.message),
+        };
+        let health = health.with_namespace(self.namespace.clone());
+        if health.is_ready() && latency > SLOW_PING {
+            return health.degrade(format!("ping took {} ms", latency.as_millis()));
+        }
+        health
+    }
+
+    /// Tenant-wide filters are answered from per-tenant counters kept up to date by every write;
+    /// narrower filters list the matching sessions.
+    ///
+    /// Reading the counters takes one `HGETALL` and one pipeline of `ZCOUNT`s and never writes.
+    /// Sessions written before the counters existed are missing until `migrate_all` backfills
+    /// them. The counters count waits as registered, including waits whose own TTL lapsed before
+    /// their session's, and keep the payload bytes and waits of expired sessions until
+    /// `purge_expired` drops them.
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        let mut conn = self.conn()?;
+        if filter.is_tenant_wide() {
+            return self.counted_stats(&mut conn, &filter.env, &filter.tenant);
+        }
+        self.scanned_stats(&mut conn, filter)
+    }
+
+    /// Appends to a list that `PEXPIRE` refreshes on every push, capped at the session's
+    /// remaining lifetime at the time of the push; every operation runs [`INBOX_SCRIPT`].
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        validate_inbox_ttl(ttl)?;
+        let message = serde_json::to_string(&InboxMessage::new(payload)).map_err(serde_error)?;
+        let mut conn = self.conn()?;
+        let len: i64 = self
+            .inbox_call(key, "push")
+            .arg(message)
+            .arg(Self::ttl_millis(ttl))
+            .invoke(&mut conn)
+            .map_err(redis_error)?;
+        usize::try_from(len).map_err(|_| not_found(key))
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let mut conn = self.conn()?;
+        let message: Option<String> = self
+            .inbox_call(key, "peek")
+            .invoke(&mut conn)
+            .map_err(redis_error)?;
+        message
+            .map(|message| serde_json::from_str(&message).map_err(serde_error))
+            .transpose()
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let mut conn = self.conn()?;
+        let message: Option<String> = self
+            .inbox_call(key, "pop")
+            .invoke(&mut conn)
+            .map_err(redis_error)?;
+        message
+            .map(|message| serde_json::from_str(&message).map_err(serde_error))
+            .transpose()
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        let mut conn = self.conn()?;
+        self.inbox_call(key, "len")
+            .invoke(&mut conn)
+            .map_err(redis_error)
+    }
+
+    /// Records the event with `SET NX PX`; the value holds the bound session key, empty until
+    /// [`SessionStore::bind_event_session`] sets it.
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        validate_event_id(event_id)?;
+        validate_event_ttl(ttl)?;
+        let mut conn = self.conn()?;
+        let event_key = self.event_key(ctx, event_id);
+        let recorded: Option<String> = redis::cmd("SET")
+            .arg(&event_key)
+            .arg("")
+            .arg("NX")
+            .arg("PX")
+            .arg(Self::ttl_millis(ttl))
+            .query(&mut conn)
+            .map_err(redis_error)?;
+        if recorded.is_some() {
+            return Ok(EventDedup::FirstSeen);
+        }
+        let session: Option<String> = conn.get(&event_key).map_err(redis_error)?;
+        Ok(EventDedup::Duplicate {
+            session: session
+                .filter(|session| !session.is_empty())
+                .map(SessionKey::new),
+        })
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        validate_event_id(event_id)?;
+        let mut conn = self.conn()?;
+        let bound: Option<String> = redis::cmd("SET")
+            .arg(self.event_key(ctx, event_id))
+            .arg(key.as_str())
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query(&mut conn)
+            .map_err(redis_error)?;
+        Ok(bound.is_some())
     }
 
     fn find_by_user(
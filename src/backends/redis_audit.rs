@@ -0,0 +1,138 @@
+use crate::audit::{AuditRecord, AuditSink, GENESIS_HASH};
+use crate::error::{SessionResult, redis_error, serde_error};
+use redis::{Client, Connection, Script};
+use std::collections::HashMap;
+
+const RECORD_FIELD: &str = "record";
+const HASH_FIELD: &str = "hash";
+const READ_BATCH: usize = 512;
+
+/// Appends a record only if it links to the stream's last entry.
+///
+/// `KEYS[1]` is the stream; `ARGV` are the encoded record, its `prev_hash`, its hash and
+/// [`GENESIS_HASH`]. Entries written before the hash was stored as its own field are decoded.
+/// Returns `1` when the record was appended and `0` when the tail moved.
+const APPEND_LINKED_SCRIPT: &str = r"
+local tail_hash = ARGV[4]
+local tail = redis.call('XREVRANGE', KEYS[1], '+', '-', 'COUNT', 1)[1]
+if tail then
+  tail_hash = nil
+  local fields, record = tail[2], nil
+  for i = 1, #fields, 2 do
+    if fields[i] == 'hash' then tail_hash = fields[i + 1] end
+    if fields[i] == 'record' then record = fields[i + 1] end
+  end
+  if not tail_hash and record then
+    tail_hash = cjson.decode(record).hash
+  end
+end
+if tail_hash ~= ARGV[2] then
+  return 0
+end
+redis.call('XADD', KEYS[1], '*', 'record', ARGV[1], 'hash', ARGV[3])
+return 1
+";
+
+/// Audit sink appending records to a Redis stream, one entry per record.
+///
+/// [`AuditSink::append_linked`] checks the stream's tail and appends in one script, so stores on
+/// several hosts can share the stream.
+pub struct RedisStreamAuditSink {
+    client: Client,
+    stream_key: String,
+}
+
+type StreamEntries = Vec<(String, HashMap<String, String>)>;
+
+impl RedisStreamAuditSink {
+    /// Appends to the stream `stream_key` of the Redis server at `url`.
+    pub fn from_url(url: impl AsRef<str>, stream_key: impl Into<String>) -> SessionResult<Self> {
+        let client = Client::open(url.as_ref()).map_err(redis_error)?;
+        Ok(Self {
+            client,
+            stream_key: stream_key.into(),
+        })
+    }
+
+    fn conn(&self) -> SessionResult<Connection> {
+        self.client.get_connection().map_err(redis_error)
+    }
+
+    fn decode(fields: &mut HashMap<String, String>) -> SessionResult<Option<AuditRecord>> {
+        fields
+            .remove(RECORD_FIELD)
+            .map(|raw| serde_json::from_str(&raw).map_err(serde_error))
+            .transpose()
+    }
+}
+
+impl AuditSink for RedisStreamAuditSink {
+    fn append(&self, record: &AuditRecord) -> SessionResult<()> {
+        let payload = serde_json::to_string(record).map_err(serde_error)?;
+        let mut conn = self.conn()?;
+        redis::cmd("XADD")
+            .arg(&self.stream_key)
+            .arg("*")
+            .arg(RECORD_FIELD)
+            .arg(payload)
+            .arg(HASH_FIELD)
+            .arg(&record.hash)
+            .query::<String>(&mut conn)
+            .map_err(redis_error)?;
+        Ok(())
+    }
+
+    fn append_linked(&self, record: &AuditRecord) -> SessionResult<bool> {
+        let payload = serde_json::to_string(record).map_err(serde_error)?;
+        let mut conn = self.conn()?;
+        let appended: i64 = Script::new(APPEND_LINKED_SCRIPT)
+            .key(&self.stream_key)
+            .arg(payload)
+            .arg(&record.prev_hash)
+            .arg(&record.hash)
+            .arg(GENESIS_HASH)
+            .invoke(&mut conn)
+            .map_err(redis_error)?;
+        Ok(appended == 1)
+    }
+
+    fn last_record(&self) -> SessionResult<Option<AuditRecord>> {
+        let mut conn = self.conn()?;
+        let entries: StreamEntries = redis::cmd("XREVRANGE")
+            .arg(&self.stream_key)
+            .arg("+")
+            .arg("-")
+            .arg("COUNT")
+            .arg(1)
+            .query(&mut conn)
+            .map_err(redis_error)?;
+        match entries.into_iter().next() {
+            Some((_, mut fields)) => Self::decode(&mut fields),
+            None => Ok(None),
+        }
+    }
+
+    fn read_all(&self) -> SessionResult<Vec<AuditRecord>> {
+        let mut conn = self.conn()?;
+        let mut records = Vec::new();
+        let mut start = "-".to_string();
+        loop {
+            let entries: StreamEntries = redis::cmd("XRANGE")
+                .arg(&self.stream_key)
+                .arg(&start)
+                .arg("+")
+                .arg("COUNT")
+                .arg(READ_BATCH)
+                .query(&mut conn)
+                .map_err(redis_error)?;
+            let exhausted = entries.len() < READ_BATCH;
+            for (id, mut fields) in entries {
+                start = format!("({id}");
+                records.extend(Self::decode(&mut fields)?);
+            }
+            if exhausted {
+                return Ok(records);
+            }
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! Admin CLI for inspecting and repairing Greentic session stores.
+
+use clap::{Args, Parser, Subcommand};
+use greentic_session::admin;
+use greentic_session::archive::{export_sessions, import_sessions};
+use greentic_session::{
+    ErrorCode, GreenticError, IndexRepairReport, ReplyScope, SessionBackendConfig, SessionFilter,
+    SessionListing, SessionResult, SessionStore, SessionWait, create_session_store,
+};
+use greentic_types::{EnvId, FlowId, PackId, SessionKey, TeamId, TenantCtx, TenantId, UserId};
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// `println!` that ignores write failures: a closed stdout (for example `| head`) is not worth a
+/// panic.
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        let _ = writeln!(io::stdout().lock(), $($arg)*);
+    }};
+}
+
+#[derive(Parser)]
+#[command(
+    name = "greentic-session",
+    version,
+    about = "Inspect and repair Greentic session stores"
+)]
+struct Cli {
+    /// Redis connection URL.
+    #[arg(long, env = "GREENTIC_SESSION_REDIS_URL")]
+    redis_url: String,
+    /// Key namespace of the store; defaults to the backend's own.
+    #[arg(long, env = "GREENTIC_SESSION_NAMESPACE")]
+    namespace: Option<String>,
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the sessions of a tenant.
+    List {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Maximum number of sessions to print.
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+        /// Cursor returned by the previous page.
+        #[arg(long)]
+        cursor: Option<String>,
+    },
+    /// Show a session, its payload and its waits.
+    Show { key: String },
+    /// Delete a session together with its waits.
+    Delete { key: String },
+    /// Reset the lifetime of every wait on a session.
+    Extend {
+        key: String,
+        /// New lifetime in seconds.
+        #[arg(long)]
+        ttl_secs: u64,
+    },
+    /// Resolve a reply scope to the session waiting on it.
+    ResolveScope {
+        #[command(flatten)]
+        user: UserArgs,
+        #[arg(long)]
+        conversation: String,
+        #[arg(long)]
+        thread: Option<String>,
+        #[arg(long)]
+        reply_to: Option<String>,
+        #[arg(long)]
+        correlation: Option<String>,
+    },
+    /// List the sessions waiting on a user.
+    ResolveUser {
+        #[command(flatten)]
+        user: UserArgs,
+    },
+    /// Cross-check scope pointers, user wait sets and session waits.
+    Check {
+        /// Delete dangling index entries and re-index unreachable waits.
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Count the sessions, waits and payload bytes of a tenant.
+    Stats {
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Ping the store; exits with 1 when it is unavailable.
+    Health,
+    /// Write sessions to a JSONL archive.
+    Export {
+        #[command(flatten)]
+        tenant: OptionalTenantArgs,
+        /// Archive file; stdout when omitted.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Restore sessions from a JSONL archive.
+    Import {
+        /// Archive file; stdin when omitted.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    #[arg(long)]
+    env: String,
+    #[arg(long)]
+    tenant: String,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    #[arg(long)]
+    flow: Option<String>,
+    #[arg(long)]
+    pack: Option<String>,
+    /// Only list sessions holding at least one wait.
+    #[arg(long)]
+    waiting: bool,
+}
+
+#[derive(Args)]
+struct UserArgs {
+    #[arg(long)]
+    env: String,
+    #[arg(long)]
+    tenant: String,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: String,
+}
+
+#[derive(Args)]
+struct OptionalTenantArgs {
+    /// Restrict to one environment; requires --tenant.
+    #[arg(long, requires = "tenant")]
+    env: Option<String>,
+    /// Restrict to one tenant; requires --env.
+    #[arg(long, requires = "env")]
+    tenant: Option<String>,
+}
+
+impl FilterArgs {
+    fn filter(&self) -> SessionResult<SessionFilter> {
+        let mut filter = SessionFilter::tenant(
+            EnvId::try_from(self.env.as_str())?,
+            TenantId::try_from(self.tenant.as_str())?,
+        );
+        if let Some(team) = &self.team {
+            filter = filter.with_team(TeamId::try_from(team.as_str())?);
+        }
+        if let Some(user) = &self.user {
+            filter = filter.with_user(UserId::try_from(user.as_str())?);
+        }
+        if let Some(flow) = &self.flow {
+            filter = filter.with_flow(FlowId::try_from(flow.as_str())?);
+        }
+        if let Some(pack) = &self.pack {
+            filter = filter.with_pack(PackId::try_from(pack.as_str())?);
+        }
+        if self.waiting {
+            filter = filter.waiting_only();
+        }
+        Ok(filter)
+    }
+}
+
+impl UserArgs {
+    fn resolve(&self) -> SessionResult<(TenantCtx, UserId)> {
+        let user = UserId::try_from(self.user.as_str())?;
+        let team = self.team.as_deref().map(TeamId::try_from).transpose()?;
+        let ctx = TenantCtx::new(
+            EnvId::try_from(self.env.as_str())?,
+            TenantId::try_from(self.tenant.as_str())?,
+        )
+        .with_team(team)
+        .with_user(Some(user.clone()));
+        Ok((ctx, user))
+    }
+}
+
+impl OptionalTenantArgs {
+    fn filter(&self) -> SessionResult<Option<SessionFilter>> {
+        match (&self.env, &self.tenant) {
+            (Some(env), Some(tenant)) => Ok(Some(SessionFilter::tenant(
+                EnvId::try_from(env.as_str())?,
+                TenantId::try_from(tenant.as_str())?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {} ({:?})", err.message, err.code);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> SessionResult<ExitCode> {
+    let config = match cli.namespace {
+        Some(namespace) => SessionBackendConfig::RedisUrlWithNamespace {
+            url: cli.redis_url,
+            namespace,
+        },
+        None => SessionBackendConfig::RedisUrl(cli.redis_url),
+    };
+    let store = create_session_store(config)?;
+    let json = cli.json;
+    match cli.command {
+        Command::List {
+            filter,
+            limit,
+            cursor,
+        } => {
+            let page = store.list_sessions(&filter.filter()?, cursor.as_deref(), limit)?;
+            if json {
+                print_json(&json!({
+                    "sessions": page.sessions.iter().map(listing_json).collect::<Vec<_>>(),
+                    "next_cursor": page.next_cursor,
+                }));
+            } else {
+                for listing in &page.sessions {
+                    out!(
+                        "{}\t{}\t{}\t{} wait(s)",
+                        listing.key.as_str(),
+                        listing.data.flow_id.as_str(),
+                        listing.data.cursor.node_pointer,
+                        listing.waits.len()
+                    );
+                }
+                if let Some(cursor) = page.next_cursor {
+                    out!("next cursor: {cursor}");
+                }
+            }
+        }
+        Command::Show { key } => {
+            let Some(listing) = admin::show_session(&store, &SessionKey::new(key.clone()))? else {
+                eprintln!("session {key} was not found");
+                return Ok(ExitCode::FAILURE);
+            };
+            if json {
+                print_json(&listing_json(&listing));
+            } else {
+                print_listing(&listing);
+            }
+        }
+        Command::Delete { key } => {
+            store.remove_session(&SessionKey::new(key.clone()))?;
+            report(json, json!({ "deleted": key }), format!("deleted {key}"));
+        }
+        Command::Extend { key, ttl_secs } => {
+            let waits = admin::extend_session(
+                &store,
+                &SessionKey::new(key.clone()),
+                Duration::from_secs(ttl_secs),
+            )?;
+            report(
+                json,
+                json!({ "key": key, "waits": waits, "ttl_secs": ttl_secs }),
+                format!("extended {waits} wait(s) of {key} to {ttl_secs}s"),
+            );
+        }
+        Command::ResolveScope {
+            user,
+            conversation,
+            thread,
+            reply_to,
+            correlation,
+        } => {
+            let (ctx, user) = user.resolve()?;
+            let scope = ReplyScope {
+                conversation,
+                thread,
+                reply_to,
+                correlation,
+            };
+            let key = store.find_wait_by_scope(&ctx, &user, &scope)?;
+            let text = key.as_ref().map_or_else(
+                || "no session is waiting on this scope".into(),
+                |key| key.as_str().to_string(),
+            );
+            report(json, json!({ "key": key }), text);
+        }
+        Command::ResolveUser { user } => {
+            let (ctx, user) = user.resolve()?;
+            let keys = store.list_waits_for_user(&ctx, &user)?;
+            let text = keys
+                .iter()
+                .map(|key| key.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            report(json, json!({ "keys": keys }), text);
+        }
+        Command::Check { repair } => {
+            let result = store.verify_and_repair(repair)?;
+            print_check(json, &result);
+            if !result.is_consistent() && !repair {
+                return Ok(ExitCode::from(2));
+            }
+        }
+        Command::Stats { filter } => {
+            let stats = store.stats(&filter.filter()?)?;
+            let ttl = &stats.ttl;
+            let text = format!(
+                "sessions\t{}\nwaits\t{}\nscope pointers\t{}\npayload bytes\t{}\nexpiring <1m\t{}\nexpiring <1h\t{}\nexpiring <1d\t{}\nexpiring >=1d\t{}\nno expiry\t{}",
+                stats.sessions,
+                stats.waits,
+                stats.scope_pointers,
+                stats.payload_bytes,
+                ttl.under_minute,
+                ttl.under_hour,
+                ttl.under_day,
+                ttl.day_or_more,
+                ttl.no_expiry,
+            );
+            report(json, json!(stats), text);
+        }
+        Command::Health => {
+            let health = store.health();
+            let mut text = format!("{:?}\t{}", health.status, health.backend);
+            if let Some(latency) = health.latency {
+                text.push_str(&format!("\t{:.1} ms", latency.as_secs_f64() * 1000.0));
+            }
+            for reason in &health.reasons {
+                text.push_str(&format!("\n{reason}"));
+            }
+            report(json, json!(health), text);
+            if !health.is_ready() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Export { tenant, output } => {
+            let filter = tenant.filter()?;
+            let to_file = output.is_some();
+            let result = match output {
+                Some(path) => {
+                    let file = File::create(&path).map_err(io_error)?;
+                    export_sessions(&store, filter.as_ref(), BufWriter::new(file))?
+                }
+                None => export_sessions(&store, filter.as_ref(), io::stdout().lock())?,
+            };
+            let value = json!({ "sessions": result.sessions, "waits": result.waits });
+            let text = format!(
+                "exported {} session(s) with {} wait(s)",
+                result.sessions, result.waits
+            );
+            if to_file {
+                report(json, value, text);
+            } else {
+                // The archive is on stdout, so the summary goes to stderr.
+                write_report(&mut io::stderr().lock(), json, &value, &text);
+            }
+        }
+        Command::Import { input } => {
+            let result = match input {
+                Some(path) => {
+                    let file = File::open(&path).map_err(io_error)?;
+                    import_sessions(&store, BufReader::new(file))?
+                }
+                None => import_sessions(&store, io::stdin().lock())?,
+            };
+            report(
+                json,
+                json!({
+                    "sessions": result.sessions,
+                    "waits": result.waits,
+                    "skipped_existing": result.skipped_existing,
+                    "skipped_expired": result.skipped_expired,
+                }),
+                format!(
+                    "imported {} session(s) with {} wait(s); skipped {} existing and {} expired",
+                    result.sessions, result.waits, result.skipped_existing, result.skipped_expired
+                ),
+            );
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn io_error(err: io::Error) -> GreenticError {
+    GreenticError::new(ErrorCode::Unavailable, err.to_string())
+}
+
+fn listing_json(listing: &SessionListing) -> Value {
+    json!({
+        "key": listing.key,
+        "data": listing.data,
+        "waits": listing.waits.iter().map(wait_json).collect::<Vec<_>>(),
+    })
+}
+
+fn wait_json(wait: &SessionWait) -> Value {
+    json!({
+        "name": wait.name,
+        "user_id": wait.user_id,
+        "scope": wait.scope,
+        "expires_in_secs": wait.expires_in.map(|ttl| ttl.as_secs()),
+    })
+}
+
+fn print_listing(listing: &SessionListing) {
+    let data = &listing.data;
+    out!("key:     {}", listing.key.as_str());
+    out!("env:     {}", data.tenant_ctx.env.as_str());
+    out!("tenant:  {}", data.tenant_ctx.tenant_id.as_str());
+    out!("flow:    {}", data.flow_id.as_str());
+    out!("node:    {}", data.cursor.node_pointer);
+    out!("context: {}", data.context_json);
+    for wait in &listing.waits {
+        let expiry = wait.expires_in.map_or_else(
+            || "no expiry".to_string(),
+            |ttl| format!("{}s left", ttl.as_secs()),
+        );
+        out!(
+            "wait:    {} user={} conversation={} ({expiry})",
+            wait.name,
+            wait.user_id.as_str(),
+            wait.scope.conversation
+        );
+    }
+}
+
+fn print_check(json: bool, result: &IndexRepairReport) {
+    if json {
+        print_json(&json!(result));
+        return;
+    }
+    out!(
+        "checked {} session(s), {} scope pointer(s), {} user wait member(s)",
+        result.sessions,
+        result.scope_pointers,
+        result.user_wait_members
+    );
+    let verb = if result.repaired { "removed" } else { "found" };
+    for pointer in &result.dangling_scopes {
+        out!(
+            "{verb} dangling scope\t{}/{}/{}\t{}\t{}",
+            pointer.env.as_str(),
+            pointer.tenant.as_str(),
+            pointer.user.as_str(),
+            pointer.scope_hash,
+            pointer.session.as_str()
+        );
+    }
+    for member in &result.orphaned_user_waits {
+        out!(
+            "{verb} orphaned user wait\t{}/{}/{}\t{}",
+            member.env.as_str(),
+            member.tenant.as_str(),
+            member.user.as_str(),
+            member.session.as_str()
+        );
+    }
+    let verb = if result.repaired { "repaired" } else { "found" };
+    for issue in &result.unindexed_waits {
+        out!(
+            "{verb} unindexed wait\t{}\t{}\t{:?}",
+            issue.key.as_str(),
+            issue.wait,
+            issue.kind
+        );
+    }
+}
+
+fn report(json: bool, value: Value, text: String) {
+    write_report(&mut io::stdout().lock(), json, &value, &text);
+}
+
+fn write_report(out: &mut impl Write, json: bool, value: &Value, text: &str) {
+    if json {
+        write_json(out, value);
+    } else if !text.is_empty() {
+        let _ = writeln!(out, "{text}");
+    }
+}
+
+fn print_json(value: &Value) {
+    write_json(&mut io::stdout().lock(), value);
+}
+
+fn write_json(out: &mut impl Write, value: &Value) {
+    // A closed stream (for example `| head`) is not worth a panic.
+    let _ = serde_json::to_writer_pretty(&mut *out, value);
+    let _ = writeln!(out);
+}
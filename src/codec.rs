@@ -0,0 +1,155 @@
+//! Serialization codecs for persisted session payloads.
+//!
+//! Every payload written by a persistent backend starts with the one-byte [`SessionCodec::tag`]
+//! of the codec that produced it, so stores can switch codecs without rewriting existing data.
+//! Payloads starting with `{` predate the tag and are read as JSON.
+
+use crate::error::{SessionResult, serde_error, unsupported_format};
+use serde_json::Value;
+use std::fmt;
+
+/// Tag of payloads written before codec tags existed.
+const UNTAGGED_JSON: u8 = b'{';
+
+/// Encodes the JSON representation of a stored payload to bytes and back.
+///
+/// Custom codecs must pick a tag distinct from the built-in ones and from `{`.
+pub trait SessionCodec: fmt::Debug + Send + Sync {
+    /// Byte stored in front of every payload written with this codec.
+    fn tag(&self) -> u8;
+
+    /// Encodes `value`.
+    fn encode(&self, value: &Value) -> SessionResult<Vec<u8>>;
+
+    /// Decodes bytes produced by [`SessionCodec::encode`].
+    fn decode(&self, bytes: &[u8]) -> SessionResult<Value>;
+}
+
+/// JSON codec, tagged `J`; the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl SessionCodec for JsonCodec {
+    fn tag(&self) -> u8 {
+        b'J'
+    }
+
+    fn encode(&self, value: &Value) -> SessionResult<Vec<u8>> {
+        serde_json::to_vec(value).map_err(serde_error)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> SessionResult<Value> {
+        serde_json::from_slice(bytes).map_err(serde_error)
+    }
+}
+
+/// CBOR codec, tagged `C`.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl SessionCodec for CborCodec {
+    fn tag(&self) -> u8 {
+        b'C'
+    }
+
+    fn encode(&self, value: &Value) -> SessionResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes)
+            .map_err(|err| unsupported_format(format!("cbor encoding failed: {err}")))?;
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> SessionResult<Value> {
+        ciborium::from_reader(bytes)
+            .map_err(|err| unsupported_format(format!("cbor decoding failed: {err}")))
+    }
+}
+
+/// MessagePack codec, tagged `M`.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl SessionCodec for MessagePackCodec {
+    fn tag(&self) -> u8 {
+        b'M'
+    }
+
+    fn encode(&self, value: &Value) -> SessionResult<Vec<u8>> {
+        rmp_serde::to_vec_named(value)
+            .map_err(|err| unsupported_format(format!("msgpack encoding failed: {err}")))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> SessionResult<Value> {
+        rmp_serde::from_slice(bytes)
+            .map_err(|err| unsupported_format(format!("msgpack decoding failed: {err}")))
+    }
+}
+
+/// Encodes `value` with `codec`, prefixed by its tag.
+pub(crate) fn encode_tagged(codec: &dyn SessionCodec, value: &Value) -> SessionResult<Vec<u8>> {
+    let body = codec.encode(value)?;
+    let mut bytes = Vec::with_capacity(body.len() + 1);
+    bytes.push(codec.tag());
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// Decodes a tagged payload, or a legacy untagged JSON one, whatever codec wrote it.
+///
+/// `configured` is consulted first so custom codecs can be read back.
+pub(crate) fn decode_tagged(configured: &dyn SessionCodec, bytes: &[u8]) -> SessionResult<Value> {
+    let Some((&tag, body)) = bytes.split_first() else {
+        return Err(unsupported_format("payload is empty"));
+    };
+    if tag == UNTAGGED_JSON {
+        return JsonCodec.decode(bytes);
+    }
+    if tag == configured.tag() {
+        return configured.decode(body);
+    }
+    match tag {
+        b'J' => JsonCodec.decode(body),
+        #[cfg(feature = "cbor")]
+        b'C' => CborCodec.decode(body),
+        #[cfg(feature = "msgpack")]
+        b'M' => MessagePackCodec.decode(body),
+        _ => Err(unsupported_format(format!(
+            "payload codec tag {:?} is not available in this build",
+            char::from(tag)
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn payloads_from_other_codecs_stay_readable() {
+        let value = json!({"format_version": 1, "data": {"step": [1, 2, {}]}});
+        let json = encode_tagged(&JsonCodec, &value).expect("json");
+        assert_eq!(json[0], b'J');
+
+        #[cfg(feature = "cbor")]
+        {
+            let cbor = encode_tagged(&CborCodec, &value).expect("cbor");
+            assert_eq!(decode_tagged(&JsonCodec, &cbor).expect("cbor"), value);
+            assert_eq!(decode_tagged(&CborCodec, &json).expect("json"), value);
+        }
+        #[cfg(feature = "msgpack")]
+        {
+            let msgpack = encode_tagged(&MessagePackCodec, &value).expect("msgpack");
+            assert_eq!(decode_tagged(&JsonCodec, &msgpack).expect("msgpack"), value);
+        }
+
+        let legacy = serde_json::to_vec(&value).expect("legacy");
+        assert_eq!(decode_tagged(&JsonCodec, &legacy).expect("legacy"), value);
+        let err = decode_tagged(&JsonCodec, b"Zjunk").expect_err("unknown tag");
+        assert!(err.message.contains("'Z'"), "{}", err.message);
+    }
+}
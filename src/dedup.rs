@@ -0,0 +1,53 @@
+use crate::error::{SessionResult, invalid_argument};
+use greentic_types::SessionKey;
+use std::time::Duration;
+
+/// Longest event id accepted by [`crate::SessionStore::record_event_once`], in bytes.
+pub const MAX_EVENT_ID_LEN: usize = 512;
+
+/// Outcome of [`crate::SessionStore::record_event_once`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventDedup {
+    /// The event was not seen before and is now recorded; process it.
+    FirstSeen,
+    /// The event was already recorded; drop this delivery.
+    Duplicate {
+        /// Session the first delivery resumed, once bound through
+        /// [`crate::SessionStore::bind_event_session`]; `None` while it is still being processed.
+        session: Option<SessionKey>,
+    },
+}
+
+impl EventDedup {
+    /// Returns `true` for [`EventDedup::Duplicate`].
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, Self::Duplicate { .. })
+    }
+
+    /// Returns the session the first delivery resumed, if it was bound.
+    pub fn session(&self) -> Option<&SessionKey> {
+        match self {
+            Self::Duplicate { session } => session.as_ref(),
+            Self::FirstSeen => None,
+        }
+    }
+}
+
+pub(crate) fn validate_event_id(event_id: &str) -> SessionResult<()> {
+    if event_id.is_empty() {
+        return Err(invalid_argument("event id must not be empty"));
+    }
+    if event_id.len() > MAX_EVENT_ID_LEN {
+        return Err(invalid_argument(format!(
+            "event id must be at most {MAX_EVENT_ID_LEN} bytes"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_event_ttl(ttl: Duration) -> SessionResult<()> {
+    if ttl.is_zero() {
+        return Err(invalid_argument("event ttl must be greater than zero"));
+    }
+    Ok(())
+}
@@ -1,24 +1,294 @@
+use crate::fence::FenceViolation;
+use crate::quota::QuotaKind;
 pub use greentic_types::{ErrorCode, GreenticError};
-use greentic_types::{GResult, SessionKey};
+use greentic_types::{GResult, SessionKey, TenantId};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
 pub type SessionResult<T> = GResult<T>;
 
-#[cfg(feature = "redis")]
+/// Typed reason carried as the source of errors raised by this crate.
+///
+/// The message of the [`GreenticError`] stays human-readable; match on the detail instead of the
+/// message to tell failures apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionErrorDetail {
+    /// The tenant fence rejected the call.
+    Fence(FenceViolation),
+    /// No live session is stored under the key.
+    NotFound {
+        /// Key that was looked up.
+        key: SessionKey,
+    },
+    /// The requested history version is not retained.
+    VersionNotFound {
+        /// Session whose history was read.
+        key: SessionKey,
+        /// Version that was requested.
+        version: u64,
+    },
+    /// A session is already stored under the key.
+    SessionExists {
+        /// Key that is taken.
+        key: SessionKey,
+    },
+    /// The session changed between reading and writing it.
+    ConcurrentModification {
+        /// Key of the contended session.
+        key: SessionKey,
+    },
+    /// A tenant quota would be exceeded.
+    QuotaExceeded {
+        /// Tenant whose quota applies.
+        tenant: TenantId,
+        /// Quota that was hit.
+        quota: QuotaKind,
+        /// Configured limit.
+        limit: usize,
+        /// Usage the write ran into: current count, or payload bytes.
+        actual: usize,
+    },
+    /// The serialized payload exceeds the store-wide [`crate::PayloadLimit`].
+    PayloadTooLarge {
+        /// Session being written.
+        key: SessionKey,
+        /// Serialized size of the payload, in bytes.
+        actual: usize,
+        /// Maximum allowed size, in bytes.
+        allowed: usize,
+    },
+    /// The storage backend failed or could not be reached.
+    BackendUnavailable {
+        /// Backend that failed, e.g. `redis`.
+        backend: &'static str,
+        /// Backend specific failure kind.
+        kind: String,
+    },
+    /// An RFC 6902 `test` operation did not match the stored context.
+    PatchTestFailed {
+        /// Index of the failing operation within the patch.
+        operation: usize,
+        /// JSON pointer the operation tested.
+        path: String,
+    },
+    /// A circuit breaker is failing calls fast while the backend recovers.
+    CircuitOpen {
+        /// Time until the breaker lets a probe call through.
+        retry_in: Duration,
+    },
+    /// The store does not implement the operation.
+    Unsupported {
+        /// Name of the [`crate::SessionStore`] method.
+        operation: &'static str,
+    },
+}
+
+impl SessionErrorDetail {
+    /// Returns the detail attached to `err`, if it was raised by this crate.
+    pub fn of(err: &GreenticError) -> Option<&Self> {
+        err.source()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for SessionErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fence(violation) => write!(f, "tenant fence violation ({})", violation.reason),
+            Self::NotFound { key } => write!(f, "session {} not found", key.as_str()),
+            Self::VersionNotFound { key, version } => {
+                write!(f, "version {version} of session {} not found", key.as_str())
+            }
+            Self::SessionExists { key } => write!(f, "session {} exists", key.as_str()),
+            Self::ConcurrentModification { key } => {
+                write!(f, "session {} modified concurrently", key.as_str())
+            }
+            Self::QuotaExceeded { quota, .. } => write!(f, "{quota} quota exceeded"),
+            Self::PayloadTooLarge {
+                actual, allowed, ..
+            } => {
+                write!(f, "payload of {actual} bytes exceeds {allowed} bytes")
+            }
+            Self::BackendUnavailable { backend, kind } => {
+                write!(f, "{backend} backend unavailable ({kind})")
+            }
+            Self::PatchTestFailed { path, .. } => write!(f, "json patch test at {path} failed"),
+            Self::CircuitOpen { retry_in } => write!(f, "circuit open, retry in {retry_in:?}"),
+            Self::Unsupported { operation } => write!(f, "{operation} is not supported"),
+        }
+    }
+}
+
+impl Error for SessionErrorDetail {}
+
+fn detailed(code: ErrorCode, message: String, detail: SessionErrorDetail) -> GreenticError {
+    GreenticError::new(code, message).with_source(detail)
+}
+
 pub(crate) fn serde_error(err: serde_json::Error) -> GreenticError {
     GreenticError::new(ErrorCode::Internal, err.to_string())
 }
 
 #[cfg(feature = "redis")]
 pub(crate) fn redis_error(err: redis::RedisError) -> GreenticError {
-    GreenticError::new(ErrorCode::Unavailable, err.to_string())
+    let kind = format!("{:?}", err.kind());
+    crate::metrics::record_backend_error("redis", kind.clone());
+    detailed(
+        ErrorCode::Unavailable,
+        err.to_string(),
+        SessionErrorDetail::BackendUnavailable {
+            backend: "redis",
+            kind,
+        },
+    )
 }
 
 pub(crate) fn invalid_argument(msg: impl Into<String>) -> GreenticError {
     GreenticError::new(ErrorCode::InvalidInput, msg.into())
 }
 
+pub(crate) fn payload_too_large(key: &SessionKey, actual: usize, allowed: usize) -> GreenticError {
+    detailed(
+        ErrorCode::InvalidInput,
+        format!(
+            "session payload for {} is {actual} bytes, exceeding the maximum of {allowed} bytes",
+            key.as_str()
+        ),
+        SessionErrorDetail::PayloadTooLarge {
+            key: key.clone(),
+            actual,
+            allowed,
+        },
+    )
+}
+
+pub(crate) fn fence_rejected(violation: FenceViolation) -> GreenticError {
+    crate::tracing::record_fence_rejection(
+        &violation.expected,
+        &violation.provided,
+        violation.reason,
+    );
+    detailed(
+        ErrorCode::InvalidInput,
+        violation.to_string(),
+        SessionErrorDetail::Fence(violation),
+    )
+}
+
+pub(crate) fn circuit_open(retry_in: Duration) -> GreenticError {
+    detailed(
+        ErrorCode::Unavailable,
+        format!(
+            "session store circuit is open; retry in {} ms",
+            retry_in.as_millis()
+        ),
+        SessionErrorDetail::CircuitOpen { retry_in },
+    )
+}
+
 pub(crate) fn not_found(key: &SessionKey) -> GreenticError {
-    GreenticError::new(
+    detailed(
         ErrorCode::NotFound,
         format!("session {} was not found", key.as_str()),
+        SessionErrorDetail::NotFound { key: key.clone() },
+    )
+}
+
+pub(crate) fn session_exists(key: &SessionKey) -> GreenticError {
+    detailed(
+        ErrorCode::Conflict,
+        format!("session {} already exists", key.as_str()),
+        SessionErrorDetail::SessionExists { key: key.clone() },
+    )
+}
+
+pub(crate) fn version_not_found(key: &SessionKey, version: u64) -> GreenticError {
+    detailed(
+        ErrorCode::NotFound,
+        format!(
+            "version {version} of session {} was not found",
+            key.as_str()
+        ),
+        SessionErrorDetail::VersionNotFound {
+            key: key.clone(),
+            version,
+        },
+    )
+}
+
+pub(crate) fn patch_test_failed(err: &json_patch::PatchError) -> GreenticError {
+    detailed(
+        ErrorCode::Conflict,
+        format!("json patch test failed: {err}"),
+        SessionErrorDetail::PatchTestFailed {
+            operation: err.operation,
+            path: err.path.to_string(),
+        },
+    )
+}
+
+#[cfg(feature = "redis")]
+pub(crate) fn concurrent_modification(key: &SessionKey) -> GreenticError {
+    detailed(
+        ErrorCode::Conflict,
+        format!(
+            "session {} was modified concurrently; patch was not applied",
+            key.as_str()
+        ),
+        SessionErrorDetail::ConcurrentModification { key: key.clone() },
+    )
+}
+
+pub(crate) fn unsupported(operation: &'static str) -> GreenticError {
+    detailed(
+        ErrorCode::Internal,
+        format!("{operation} is not supported by this session store"),
+        SessionErrorDetail::Unsupported { operation },
+    )
+}
+
+pub(crate) fn unsupported_format(msg: impl AsRef<str>) -> GreenticError {
+    GreenticError::new(
+        ErrorCode::Internal,
+        format!("unsupported session format: {}", msg.as_ref()),
+    )
+}
+
+pub(crate) fn quota_exceeded(
+    tenant: &TenantId,
+    quota: QuotaKind,
+    limit: usize,
+    actual: usize,
+    msg: impl AsRef<str>,
+) -> GreenticError {
+    detailed(
+        ErrorCode::RateLimited,
+        format!("quota exceeded: {}", msg.as_ref()),
+        SessionErrorDetail::QuotaExceeded {
+            tenant: tenant.clone(),
+            quota,
+            limit,
+            actual,
+        },
+    )
+}
+
+pub(crate) fn io_error(err: std::io::Error) -> GreenticError {
+    detailed(
+        ErrorCode::Unavailable,
+        err.to_string(),
+        SessionErrorDetail::BackendUnavailable {
+            backend: "file",
+            kind: format!("{:?}", err.kind()),
+        },
+    )
+}
+
+pub(crate) fn audit_chain_broken(sequence: u64, reason: impl AsRef<str>) -> GreenticError {
+    GreenticError::new(
+        ErrorCode::Conflict,
+        format!(
+            "audit chain broken at sequence {sequence}: {}",
+            reason.as_ref()
+        ),
     )
 }
@@ -0,0 +1,291 @@
+//! Tenant fence policy shared by every built-in backend.
+//!
+//! The fence decides whether a caller context may write or read a session stored under another
+//! context. [`TenantFence::strict`] is the default; the relaxations are opt-in through
+//! [`crate::SessionStoreOptions::with_fence`].
+
+use greentic_types::{SessionData, TeamId, TenantCtx, UserId};
+use std::fmt;
+
+/// Rules applied when a caller context is compared against a stored session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TenantFence {
+    /// Treats a team missing on either side as matching any team.
+    pub allow_missing_team: bool,
+    /// Lets an update add a user to a session stored without one.
+    pub allow_user_introduction: bool,
+    /// Lets callers carrying the [`ADMIN_ATTRIBUTE`] claim read sessions of another team.
+    ///
+    /// A lookup that finds nothing under the caller's team falls back to the same user and scope
+    /// under the tenant's other teams. Redis scans the tenant's pointers for that fallback. Callers
+    /// without the claim keep the strict team check.
+    pub cross_team_reads: bool,
+}
+
+/// `TenantCtx::attributes` key of the admin claim checked for cross-team reads.
+pub const ADMIN_ATTRIBUTE: &str = "greentic.session.admin";
+
+/// Returns `ctx` carrying the admin claim honoured by [`TenantFence::cross_team_reads`].
+pub fn with_admin_claim(mut ctx: TenantCtx) -> TenantCtx {
+    ctx.attributes
+        .insert(ADMIN_ATTRIBUTE.to_string(), "true".to_string());
+    ctx
+}
+
+/// Returns `true` when `ctx` carries the admin claim.
+pub fn has_admin_claim(ctx: &TenantCtx) -> bool {
+    ctx.attributes
+        .get(ADMIN_ATTRIBUTE)
+        .is_some_and(|value| value == "true")
+}
+
+/// Tenant context field a [`FenceViolation`] found mismatched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FenceField {
+    /// Environment.
+    Env,
+    /// Tenant.
+    Tenant,
+    /// Team.
+    Team,
+    /// User.
+    User,
+}
+
+impl fmt::Display for FenceField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Env => "env",
+            Self::Tenant => "tenant",
+            Self::Team => "team",
+            Self::User => "user",
+        })
+    }
+}
+
+/// A call rejected by the [`TenantFence`].
+///
+/// Backends surface it as an [`crate::ErrorCode::InvalidInput`] error whose message starts with
+/// `tenant context mismatch` and whose detail is [`crate::SessionErrorDetail::Fence`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FenceViolation {
+    /// Rule that rejected the call.
+    pub reason: &'static str,
+    /// Fields that did not match, in env, tenant, team, user order.
+    pub fields: Vec<FenceField>,
+    /// Context of the stored session.
+    pub expected: Box<TenantCtx>,
+    /// Context supplied by the caller.
+    pub provided: Box<TenantCtx>,
+}
+
+impl fmt::Display for FenceViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self.fields.iter().map(ToString::to_string).collect();
+        let presence = |ctx: &TenantCtx| {
+            if normalize_user(ctx).is_some() {
+                "present"
+            } else {
+                "missing"
+            }
+        };
+        let team = |ctx| normalize_team(ctx).map(|t| t.as_str()).unwrap_or("-");
+        write!(
+            f,
+            "tenant context mismatch ({}; mismatched: {}): expected env={}, tenant={}, team={}, user={}, got env={}, tenant={}, team={}, user={}",
+            self.reason,
+            fields.join(", "),
+            self.expected.env.as_str(),
+            self.expected.tenant_id.as_str(),
+            team(&self.expected),
+            presence(&self.expected),
+            self.provided.env.as_str(),
+            self.provided.tenant_id.as_str(),
+            team(&self.provided),
+            presence(&self.provided),
+        )
+    }
+}
+
+impl std::error::Error for FenceViolation {}
+
+impl TenantFence {
+    /// Requires env, tenant, team and user to match exactly.
+    pub fn strict() -> Self {
+        Self::default()
+    }
+
+    /// Treats a team missing on either side as matching any team.
+    pub fn allow_missing_team(mut self) -> Self {
+        self.allow_missing_team = true;
+        self
+    }
+
+    /// Lets an update add a user to a session stored without one.
+    pub fn allow_user_introduction(mut self) -> Self {
+        self.allow_user_introduction = true;
+        self
+    }
+
+    /// Lets admin callers' scope and user lookups return sessions of another team.
+    pub fn cross_team_reads(mut self) -> Self {
+        self.cross_team_reads = true;
+        self
+    }
+
+    /// Returns `true` when `ctx` may read sessions of the tenant's other teams.
+    pub fn reads_across_teams(&self, ctx: &TenantCtx) -> bool {
+        self.cross_team_reads && has_admin_claim(ctx)
+    }
+
+    /// Checks that the caller context may write a session carrying `data`.
+    pub fn check_write(&self, ctx: &TenantCtx, data: &SessionData) -> Result<(), FenceViolation> {
+        let stored = &data.tenant_ctx;
+        let mut check = Check::new(stored, ctx);
+        check.tenant("env/tenant must match");
+        if !self.teams_match(stored, ctx) {
+            check.fail(FenceField::Team, "team must match");
+        }
+        if let Some(stored_user) = normalize_user(stored) {
+            match normalize_user(ctx) {
+                None => check.fail(
+                    FenceField::User,
+                    "user required by session but missing in caller context",
+                ),
+                Some(provided_user) if provided_user != stored_user => {
+                    check.fail(FenceField::User, "user must match stored session")
+                }
+                Some(_) => {}
+            }
+        }
+        check.finish()
+    }
+
+    /// Checks that an update from `existing` to `candidate` keeps the session in its fence.
+    pub fn check_update(
+        &self,
+        existing: &TenantCtx,
+        candidate: &TenantCtx,
+    ) -> Result<(), FenceViolation> {
+        let mut check = Check::new(existing, candidate);
+        check.tenant("env/tenant cannot change for an existing session");
+        if !self.teams_match(existing, candidate) {
+            check.fail(
+                FenceField::Team,
+                "team cannot change for an existing session",
+            );
+        }
+        match (normalize_user(existing), normalize_user(candidate)) {
+            (Some(a), Some(b)) if a == b => {}
+            (Some(_), Some(_)) | (Some(_), None) => check.fail(
+                FenceField::User,
+                "user cannot change for an existing session",
+            ),
+            (None, Some(_)) if !self.allow_user_introduction => check.fail(
+                FenceField::User,
+                "user cannot be introduced when none was stored",
+            ),
+            (None, _) => {}
+        }
+        check.finish()
+    }
+
+    /// Checks that a wait registered for `user` agrees with the caller and the session data.
+    pub fn check_wait_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+        data: &SessionData,
+    ) -> Result<(), FenceViolation> {
+        let provided = ctx.clone().with_user(Some(user.clone()));
+        let mut check = Check::new(&data.tenant_ctx, &provided);
+        if normalize_user(ctx).is_some_and(|ctx_user| ctx_user != user) {
+            check.fail(
+                FenceField::User,
+                "user must match tenant context when registering a wait",
+            );
+        } else {
+            match normalize_user(&data.tenant_ctx) {
+                Some(stored_user) if stored_user != user => check.fail(
+                    FenceField::User,
+                    "user must match session data when registering a wait",
+                ),
+                Some(_) => {}
+                None => check.fail(
+                    FenceField::User,
+                    "user required by wait but missing in session data",
+                ),
+            }
+        }
+        check.finish()
+    }
+
+    /// Returns `true` when `ctx` acting for `user_id` may see a session stored under `stored`.
+    pub fn permits_read(&self, stored: &TenantCtx, ctx: &TenantCtx, user_id: &UserId) -> bool {
+        stored.env == ctx.env
+            && stored.tenant_id == ctx.tenant_id
+            && (self.reads_across_teams(ctx) || self.teams_match(stored, ctx))
+            && normalize_user(stored).is_none_or(|stored_user| stored_user == user_id)
+    }
+
+    fn teams_match(&self, a: &TenantCtx, b: &TenantCtx) -> bool {
+        match (normalize_team(a), normalize_team(b)) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => true,
+            _ => self.allow_missing_team,
+        }
+    }
+}
+
+/// Collects the mismatched fields of one fence check, keeping the first reason.
+struct Check<'a> {
+    expected: &'a TenantCtx,
+    provided: &'a TenantCtx,
+    reason: Option<&'static str>,
+    fields: Vec<FenceField>,
+}
+
+impl<'a> Check<'a> {
+    fn new(expected: &'a TenantCtx, provided: &'a TenantCtx) -> Self {
+        Self {
+            expected,
+            provided,
+            reason: None,
+            fields: Vec::new(),
+        }
+    }
+
+    fn tenant(&mut self, reason: &'static str) {
+        if self.expected.env != self.provided.env {
+            self.fail(FenceField::Env, reason);
+        }
+        if self.expected.tenant_id != self.provided.tenant_id {
+            self.fail(FenceField::Tenant, reason);
+        }
+    }
+
+    fn fail(&mut self, field: FenceField, reason: &'static str) {
+        self.reason.get_or_insert(reason);
+        self.fields.push(field);
+    }
+
+    fn finish(self) -> Result<(), FenceViolation> {
+        match self.reason {
+            None => Ok(()),
+            Some(reason) => Err(FenceViolation {
+                reason,
+                fields: self.fields,
+                expected: Box::new(self.expected.clone()),
+                provided: Box::new(self.provided.clone()),
+            }),
+        }
+    }
+}
+
+pub(crate) fn normalize_team(ctx: &TenantCtx) -> Option<&TeamId> {
+    ctx.team_id.as_ref().or(ctx.team.as_ref())
+}
+
+pub(crate) fn normalize_user(ctx: &TenantCtx) -> Option<&UserId> {
+    ctx.user_id.as_ref().or(ctx.user.as_ref())
+}
@@ -0,0 +1,142 @@
+use crate::error::{SessionResult, serde_error, unsupported_format};
+use greentic_types::SessionData;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Format version written by this release when no migrations are registered.
+///
+/// Payloads persisted before the envelope existed are read as version `0`.
+pub const CURRENT_FORMAT_VERSION: u32 = 1;
+
+const VERSION_FIELD: &str = "format_version";
+const DATA_FIELD: &str = "data";
+
+type MigrationFn = dyn Fn(Value) -> SessionResult<Value> + Send + Sync;
+
+/// Upgrade steps applied to persisted session payloads when they are read.
+///
+/// Payloads are stored as `{"format_version": N, "data": <SessionData>}`. A step registered from
+/// version `N` turns the `data` of a version `N` payload into version `N + 1`, and registering it
+/// raises the written format version to at least `N + 1`. The step from the pre-envelope format
+/// (`0`) to [`CURRENT_FORMAT_VERSION`] is built in.
+#[derive(Clone)]
+pub struct MigrationRegistry {
+    steps: BTreeMap<u32, Arc<MigrationFn>>,
+    target: u32,
+}
+
+impl MigrationRegistry {
+    /// Creates a registry holding only the built-in steps.
+    pub fn new() -> Self {
+        let mut steps: BTreeMap<u32, Arc<MigrationFn>> = BTreeMap::new();
+        steps.insert(0, Arc::new(Ok));
+        Self {
+            steps,
+            target: CURRENT_FORMAT_VERSION,
+        }
+    }
+
+    /// Registers the upgrade from `from_version` to `from_version + 1`, replacing any existing
+    /// step for that version.
+    pub fn register(
+        mut self,
+        from_version: u32,
+        migration: impl Fn(Value) -> SessionResult<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.steps.insert(from_version, Arc::new(migration));
+        self.target = self.target.max(from_version.saturating_add(1));
+        self
+    }
+
+    /// Format version written to storage and expected after migration.
+    pub fn target_version(&self) -> u32 {
+        self.target
+    }
+
+    /// Serializes `data` as JSON inside an envelope tagged with the target version.
+    pub fn encode(&self, data: &SessionData) -> SessionResult<String> {
+        serde_json::to_string(&self.envelope(data)?).map_err(serde_error)
+    }
+
+    /// Deserializes a JSON payload, upgrading it to the target version first.
+    pub fn decode(&self, payload: &str) -> SessionResult<SessionData> {
+        let value: Value = serde_json::from_str(payload).map_err(serde_error)?;
+        self.open(value).map(|(_, data)| data)
+    }
+
+    /// Wraps `data` in an envelope tagged with the target version.
+    pub(crate) fn envelope(&self, data: &SessionData) -> SessionResult<Value> {
+        let data = serde_json::to_value(data).map_err(serde_error)?;
+        let mut envelope = Map::new();
+        envelope.insert(VERSION_FIELD.into(), Value::from(self.target));
+        envelope.insert(DATA_FIELD.into(), data);
+        Ok(Value::Object(envelope))
+    }
+
+    /// Upgrades a stored value to the target version and reports the version it was stored with.
+    pub(crate) fn open(&self, value: Value) -> SessionResult<(u32, SessionData)> {
+        let (stored, mut data) = split_envelope(value)?;
+        if stored > self.target {
+            return Err(unsupported_format(format!(
+                "payload format version {stored} is newer than the supported version {}",
+                self.target
+            )));
+        }
+        for version in stored..self.target {
+            let Some(step) = self.steps.get(&version) else {
+                return Err(unsupported_format(format!(
+                    "no migration registered from format version {version}"
+                )));
+            };
+            data = step(data)?;
+        }
+        let data = serde_json::from_value(data).map_err(serde_error)?;
+        Ok((stored, data))
+    }
+}
+
+impl Default for MigrationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MigrationRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MigrationRegistry")
+            .field("steps", &self.steps.keys().collect::<Vec<_>>())
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+/// Splits a stored value into its format version and data, treating bare payloads as version 0.
+fn split_envelope(value: Value) -> SessionResult<(u32, Value)> {
+    let Value::Object(mut fields) = value else {
+        return Ok((0, value));
+    };
+    let enveloped = fields.len() == 2
+        && fields.contains_key(DATA_FIELD)
+        && fields.get(VERSION_FIELD).is_some_and(Value::is_u64);
+    if !enveloped {
+        return Ok((0, Value::Object(fields)));
+    }
+    let version = fields
+        .get(VERSION_FIELD)
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| unsupported_format("payload format version is out of range"))?;
+    let data = fields.remove(DATA_FIELD).unwrap_or_default();
+    Ok((version, data))
+}
+
+/// Outcome of [`crate::SessionStore::migrate_all`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Stored sessions inspected.
+    pub scanned: usize,
+    /// Sessions rewritten in the target format version and configured codec.
+    pub migrated: usize,
+}
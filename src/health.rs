@@ -0,0 +1,96 @@
+use crate::format::CURRENT_FORMAT_VERSION;
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// Overall outcome of [`crate::SessionStore::health`], ordered from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// The store answers normally.
+    Healthy,
+    /// The store answers, but see [`StoreHealth::reasons`].
+    Degraded,
+    /// The store cannot serve requests.
+    Unavailable,
+}
+
+/// Structured result of a store health probe.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StoreHealth {
+    /// Overall status.
+    pub status: HealthStatus,
+    /// Backend that answered, e.g. `redis`.
+    pub backend: String,
+    /// Version of this crate.
+    pub version: &'static str,
+    /// Storage format version written by this crate.
+    pub format_version: u32,
+    /// Key namespace of the backend, if it has one.
+    pub namespace: Option<String>,
+    /// Round trip of the probe, if the backend was reached.
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Option<Duration>,
+    /// Why the store is degraded or unavailable.
+    pub reasons: Vec<String>,
+}
+
+impl StoreHealth {
+    /// A healthy report for `backend`.
+    pub fn healthy(backend: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            backend: backend.into(),
+            version: env!("CARGO_PKG_VERSION"),
+            format_version: CURRENT_FORMAT_VERSION,
+            namespace: None,
+            latency: None,
+            reasons: Vec::new(),
+        }
+    }
+
+    /// An unavailable report for `backend`.
+    pub fn unavailable(backend: impl Into<String>, reason: impl Into<String>) -> Self {
+        let mut health = Self::healthy(backend);
+        health.status = HealthStatus::Unavailable;
+        health.reasons.push(reason.into());
+        health
+    }
+
+    /// Sets the key namespace.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Sets the probe round trip.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Marks the report at least degraded for `reason`.
+    pub fn degrade(mut self, reason: impl Into<String>) -> Self {
+        self.status = self.status.max(HealthStatus::Degraded);
+        self.reasons.push(reason.into());
+        self
+    }
+
+    /// Returns `true` when the store can serve requests, possibly degraded.
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unavailable
+    }
+
+    /// Returns `true` when nothing is wrong.
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+}
+
+fn serialize_millis<S: Serializer>(
+    latency: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    latency
+        .map(|latency| latency.as_secs_f64() * 1000.0)
+        .serialize(serializer)
+}
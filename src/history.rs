@@ -0,0 +1,58 @@
+use greentic_types::SessionCursor;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bounded history of prior session snapshots, configured through
+/// [`crate::SessionStoreOptions::with_history`].
+///
+/// Whenever a session payload is replaced, by [`crate::SessionStore::update_session`], by
+/// re-registering waits or by a rollback, the replaced payload is archived as a new version. Only
+/// the newest `depth` versions are kept, and versions older than `retention` are discarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryPolicy {
+    /// Maximum number of archived versions kept per session.
+    pub depth: usize,
+    /// Maximum age of an archived version; `None` keeps versions until pushed out by `depth`.
+    pub retention: Option<Duration>,
+}
+
+impl HistoryPolicy {
+    /// Keeps up to `depth` versions per session without an age limit.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            retention: None,
+        }
+    }
+
+    /// Discards versions older than `retention`.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Returns `true` when a version recorded at `recorded_at_ms` is still retained at `now_ms`.
+    pub(crate) fn retains(&self, recorded_at_ms: u64, now_ms: u64) -> bool {
+        self.retention.is_none_or(|retention| {
+            let age = u128::from(now_ms.saturating_sub(recorded_at_ms));
+            age <= retention.as_millis()
+        })
+    }
+}
+
+/// Archived session version, as reported by [`crate::SessionStore::list_session_versions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionVersion {
+    /// Version number, increasing with every archived snapshot of the session.
+    pub version: u64,
+    /// Time the snapshot was archived, in milliseconds since the Unix epoch.
+    pub recorded_at_ms: u64,
+    /// Cursor stored in the snapshot.
+    pub cursor: SessionCursor,
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
@@ -0,0 +1,29 @@
+use crate::error::{SessionResult, invalid_argument};
+use crate::history::now_millis;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Input queued on a session by [`crate::SessionStore::inbox_push`] while its flow runs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboxMessage {
+    /// Opaque payload supplied by the runner.
+    pub payload: String,
+    /// When the message was queued, in milliseconds since the Unix epoch.
+    pub enqueued_at_ms: u64,
+}
+
+impl InboxMessage {
+    pub(crate) fn new(payload: &str) -> Self {
+        Self {
+            payload: payload.to_string(),
+            enqueued_at_ms: now_millis(),
+        }
+    }
+}
+
+pub(crate) fn validate_inbox_ttl(ttl: Duration) -> SessionResult<()> {
+    if ttl.is_zero() {
+        return Err(invalid_argument("inbox ttl must be greater than zero"));
+    }
+    Ok(())
+}
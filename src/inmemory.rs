@@ -1,18 +1,41 @@
 use crate::ReplyScope;
+use crate::dedup::{EventDedup, validate_event_id, validate_event_ttl};
 use crate::error::SessionResult;
-use crate::error::{GreenticError, invalid_argument, not_found};
+use crate::error::{
+    fence_rejected, invalid_argument, not_found, session_exists, version_not_found,
+};
+use crate::fence::normalize_user;
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::{HistoryPolicy, SessionVersion, now_millis};
+use crate::inbox::{InboxMessage, validate_inbox_ttl};
+use crate::listing::{SessionFilter, SessionListing, SessionPage, validate_page_limit};
+use crate::metrics::record_stale_index_entries;
+use crate::options::SessionStoreOptions;
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::{
+    DanglingScope, IndexIssue, IndexIssueKind, IndexRepairReport, OrphanedUserWait,
+};
+use crate::stats::SessionStats;
 use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec, session_ttl, validate_waits};
 use greentic_types::{EnvId, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId};
 use parking_lot::RwLock;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// Value of the `backend` metrics label.
+const BACKEND: &str = "inmemory";
+
 /// Simple in-memory implementation backed by hash maps.
+///
+/// Sessions and their routing indices live behind a single lock so multi-wait registration and
+/// clearing are atomic.
 pub struct InMemorySessionStore {
-    sessions: RwLock<HashMap<SessionKey, SessionEntry>>,
-    user_waits: RwLock<HashMap<UserLookupKey, HashSet<SessionKey>>>,
-    scope_index: RwLock<HashMap<ScopeLookupKey, ScopeEntry>>,
+    state: RwLock<StoreState>,
+    options: SessionStoreOptions,
 }
 
 impl Default for InMemorySessionStore {
@@ -24,10 +47,14 @@ impl Default for InMemorySessionStore {
 impl InMemorySessionStore {
     /// Constructs an empty store.
     pub fn new() -> Self {
+        Self::with_options(SessionStoreOptions::default())
+    }
+
+    /// Constructs an empty store enforcing the supplied options.
+    pub fn with_options(options: SessionStoreOptions) -> Self {
         Self {
-            sessions: RwLock::new(HashMap::new()),
-            user_waits: RwLock::new(HashMap::new()),
-            scope_index: RwLock::new(HashMap::new()),
+            state: RwLock::new(StoreState::default()),
+            options,
         }
     }
 
@@ -35,14 +62,6 @@ impl InMemorySessionStore {
         SessionKey::new(Uuid::new_v4().to_string())
     }
 
-    fn normalize_team(ctx: &TenantCtx) -> Option<&TeamId> {
-        ctx.team_id.as_ref().or(ctx.team.as_ref())
-    }
-
-    fn normalize_user(ctx: &TenantCtx) -> Option<&UserId> {
-        ctx.user_id.as_ref().or(ctx.user.as_ref())
-    }
-
     fn ttl_deadline(ttl: Option<Duration>) -> Option<Instant> {
         ttl.map(|value| Instant::now() + value)
     }
@@ -53,311 +72,421 @@ impl InMemorySessionStore {
             .unwrap_or(false)
     }
 
-    fn ctx_mismatch(expected: &TenantCtx, provided: &TenantCtx, reason: &str) -> GreenticError {
-        let expected_team = Self::normalize_team(expected)
-            .map(|t| t.as_str())
-            .unwrap_or("-");
-        let provided_team = Self::normalize_team(provided)
-            .map(|t| t.as_str())
-            .unwrap_or("-");
-        let expected_user_presence = if Self::normalize_user(expected).is_some() {
-            "present"
-        } else {
-            "missing"
-        };
-        let provided_user_presence = if Self::normalize_user(provided).is_some() {
-            "present"
-        } else {
-            "missing"
-        };
-        invalid_argument(format!(
-            "tenant context mismatch ({reason}): expected env={}, tenant={}, team={}, user={}, got env={}, tenant={}, team={}, user={}",
-            expected.env.as_str(),
-            expected.tenant_id.as_str(),
-            expected_team,
-            expected_user_presence,
-            provided.env.as_str(),
-            provided.tenant_id.as_str(),
-            provided_team,
-            provided_user_presence
-        ))
+    fn remaining(deadline: Option<Instant>) -> Option<Duration> {
+        deadline.map(|value| value.saturating_duration_since(Instant::now()))
     }
 
-    fn ensure_alignment(ctx: &TenantCtx, data: &SessionData) -> SessionResult<()> {
-        let stored = &data.tenant_ctx;
-        if ctx.env != stored.env || ctx.tenant_id != stored.tenant_id {
-            return Err(Self::ctx_mismatch(stored, ctx, "env/tenant must match"));
+    fn enforce_payload_limits(&self, key: &SessionKey, data: &SessionData) -> SessionResult<()> {
+        if !self.options.measures_payload(&data.tenant_ctx) {
+            return Ok(());
         }
-        if Self::normalize_team(ctx) != Self::normalize_team(stored) {
-            return Err(Self::ctx_mismatch(stored, ctx, "team must match"));
-        }
-        if let Some(stored_user) = Self::normalize_user(stored) {
-            let Some(provided_user) = Self::normalize_user(ctx) else {
-                return Err(Self::ctx_mismatch(
-                    stored,
-                    ctx,
-                    "user required by session but missing in caller context",
-                ));
-            };
-            if stored_user != provided_user {
-                return Err(Self::ctx_mismatch(
-                    stored,
-                    ctx,
-                    "user must match stored session",
-                ));
-            }
-        }
-        Ok(())
+        let bytes = self.options.encode_payload(data)?.len();
+        self.options.check_payload(key, &data.tenant_ctx, bytes)
     }
 
-    fn ensure_ctx_preserved(existing: &TenantCtx, candidate: &TenantCtx) -> SessionResult<()> {
-        if existing.env != candidate.env || existing.tenant_id != candidate.tenant_id {
-            return Err(Self::ctx_mismatch(
-                existing,
-                candidate,
-                "env/tenant cannot change for an existing session",
-            ));
-        }
-        if Self::normalize_team(existing) != Self::normalize_team(candidate) {
-            return Err(Self::ctx_mismatch(
-                existing,
-                candidate,
-                "team cannot change for an existing session",
-            ));
-        }
-        match (
-            Self::normalize_user(existing),
-            Self::normalize_user(candidate),
-        ) {
-            (Some(a), Some(b)) if a == b => {}
-            (Some(_), Some(_)) | (Some(_), None) => {
-                return Err(Self::ctx_mismatch(
-                    existing,
-                    candidate,
-                    "user cannot change for an existing session",
-                ));
-            }
-            (None, Some(_)) => {
-                return Err(Self::ctx_mismatch(
-                    existing,
-                    candidate,
-                    "user cannot be introduced when none was stored",
-                ));
-            }
-            (None, None) => {}
-        }
-        Ok(())
-    }
-
-    fn ensure_user_matches(
+    /// Returns `true` when the scope pointer is live and routes to a wait `ctx` may read.
+    fn routes_to_readable(
+        &self,
+        state: &mut StoreState,
         ctx: &TenantCtx,
-        user: &UserId,
-        data: &SessionData,
-    ) -> SessionResult<()> {
-        if let Some(ctx_user) = Self::normalize_user(ctx)
-            && ctx_user != user
-        {
-            return Err(invalid_argument(
-                "user must match tenant context when registering a wait",
-            ));
-        }
-        if let Some(stored_user) = Self::normalize_user(&data.tenant_ctx) {
-            if stored_user != user {
-                return Err(invalid_argument(
-                    "user must match session data when registering a wait",
-                ));
-            }
-        } else {
-            return Err(invalid_argument(
-                "user required by wait but missing in session data",
-            ));
-        }
-        Ok(())
-    }
-
-    fn user_lookup_key(ctx: &TenantCtx, user: &UserId) -> UserLookupKey {
-        UserLookupKey::from_ctx(ctx, user)
+        user_id: &UserId,
+        scope_key: &ScopeLookupKey,
+        entry: &ScopeEntry,
+    ) -> bool {
+        !Self::is_expired(entry.expires_at)
+            && state.live_entry(&entry.session_key).is_some_and(|session| {
+                self.options
+                    .fence
+                    .permits_read(&session.data.tenant_ctx, ctx, user_id)
+                    && session
+                        .waits
+                        .get(&entry.wait_name)
+                        .is_some_and(|wait| &wait.scope_key == scope_key)
+            })
     }
 
-    fn scope_lookup_key(ctx: &TenantCtx, user: &UserId, scope: &ReplyScope) -> ScopeLookupKey {
-        ScopeLookupKey::from_ctx(ctx, user, scope)
+    /// Returns `true` when the session is live, waiting and readable by `ctx`.
+    fn waits_readable(
+        &self,
+        state: &mut StoreState,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        key: &SessionKey,
+    ) -> bool {
+        state.live_entry(key).is_some_and(|entry| {
+            self.options
+                .fence
+                .permits_read(&entry.data.tenant_ctx, ctx, user_id)
+                && !entry.waits.is_empty()
+        })
     }
 
-    fn remove_from_user_waits(&self, lookup: &UserLookupKey, key: &SessionKey) {
-        let mut waits = self.user_waits.write();
-        if let Some(entries) = waits.get_mut(lookup) {
-            entries.remove(key);
-            if entries.is_empty() {
-                waits.remove(lookup);
-            }
+    fn enforce_session_quota(&self, state: &StoreState, ctx: &TenantCtx) -> SessionResult<()> {
+        let quotas = &self.options.quotas;
+        if quotas
+            .limits_for(&ctx.tenant_id)
+            .max_sessions_per_tenant
+            .is_none()
+        {
+            return Ok(());
         }
+        let current = state
+            .sessions
+            .values()
+            .filter(|entry| {
+                let stored = &entry.data.tenant_ctx;
+                !Self::is_expired(entry.expires_at)
+                    && stored.env == ctx.env
+                    && stored.tenant_id == ctx.tenant_id
+            })
+            .count();
+        quotas.ensure_session_capacity(&ctx.tenant_id, current)
     }
 
-    fn remove_scope_entry(&self, scope_key: &ScopeLookupKey) -> Option<ScopeEntry> {
-        self.scope_index.write().remove(scope_key)
+    /// Counts the user's live waits outside `key`, whose waits the registration replaces, and
+    /// checks that `adding` more fit the quota.
+    fn enforce_wait_quota(
+        &self,
+        state: &StoreState,
+        lookup: &UserLookupKey,
+        key: &SessionKey,
+        adding: usize,
+    ) -> SessionResult<()> {
+        let quotas = &self.options.quotas;
+        if quotas
+            .limits_for(&lookup.tenant)
+            .max_waits_per_user
+            .is_none()
+        {
+            return Ok(());
+        }
+        let current = state
+            .user_waits
+            .get(lookup)
+            .into_iter()
+            .flatten()
+            .filter(|waiting_key| *waiting_key != key)
+            .filter_map(|waiting_key| state.sessions.get(waiting_key))
+            .filter(|entry| !Self::is_expired(entry.expires_at))
+            .flat_map(|entry| entry.waits.values())
+            .filter(|wait| &wait.user == lookup && !Self::is_expired(wait.expires_at))
+            .count();
+        quotas.ensure_wait_capacity(&lookup.tenant, current, adding)
     }
 
-    fn purge_expired_session(&self, key: &SessionKey, entry: SessionEntry) {
-        if let Some(user_lookup) = &entry.wait_user {
-            self.remove_from_user_waits(user_lookup, key);
-        }
-        if let Some(scope_key) = &entry.scope_key {
-            self.remove_scope_entry(scope_key);
-        }
+    fn live_waits(entry: &SessionEntry) -> Vec<SessionWait> {
+        entry
+            .waits
+            .iter()
+            .filter(|(_, wait)| !Self::is_expired(wait.expires_at))
+            .map(|(name, wait)| SessionWait {
+                name: name.clone(),
+                user_id: wait.user.user.clone(),
+                scope: wait.scope.clone(),
+                expires_in: Self::remaining(wait.expires_at),
+            })
+            .collect()
     }
 }
 
 impl SessionStore for InMemorySessionStore {
     fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
-        Self::ensure_alignment(ctx, &data)?;
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
         let key = Self::next_key();
+        self.enforce_payload_limits(&key, &data)?;
         let entry = SessionEntry {
-            data: data.clone(),
+            data,
             expires_at: None,
-            wait_user: None,
-            scope_key: None,
+            waits: BTreeMap::new(),
+            history: SnapshotLog::default(),
+            inbox: Inbox::default(),
         };
-        self.sessions.write().insert(key.clone(), entry);
+        let mut state = self.state.write();
+        self.enforce_session_quota(&state, ctx)?;
+        state.sessions.insert(key.clone(), entry);
         Ok(key)
     }
 
-    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
-        let mut sessions = self.sessions.write();
-        let Some(entry) = sessions.get(key).cloned() else {
-            return Ok(None);
-        };
-        if Self::is_expired(entry.expires_at) {
-            sessions.remove(key);
-            drop(sessions);
-            self.purge_expired_session(key, entry);
-            return Ok(None);
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
+        self.enforce_payload_limits(key, &data)?;
+        let mut state = self.state.write();
+        if state.live_entry(key).is_some() {
+            return Err(session_exists(key));
         }
-        Ok(Some(entry.data))
+        self.enforce_session_quota(&state, ctx)?;
+        state.sessions.insert(
+            key.clone(),
+            SessionEntry {
+                data,
+                expires_at: None,
+                waits: BTreeMap::new(),
+                history: SnapshotLog::default(),
+                inbox: Inbox::default(),
+            },
+        );
+        Ok(())
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        let mut state = self.state.write();
+        Ok(state.live_entry(key).map(|entry| entry.data.clone()))
     }
 
     fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
-        let mut sessions = self.sessions.write();
-        let Some(previous) = sessions.get(key).cloned() else {
+        self.enforce_payload_limits(key, &data)?;
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
             return Err(not_found(key));
         };
-        Self::ensure_ctx_preserved(&previous.data.tenant_ctx, &data.tenant_ctx)?;
-        let entry = SessionEntry {
-            data: data.clone(),
-            expires_at: previous.expires_at,
-            wait_user: previous.wait_user.clone(),
-            scope_key: previous.scope_key.clone(),
-        };
-        sessions.insert(key.clone(), entry);
+        self.options
+            .fence
+            .check_update(&entry.data.tenant_ctx, &data.tenant_ctx)
+            .map_err(fence_rejected)?;
+        let previous = std::mem::replace(&mut entry.data, data);
+        entry.history.archive(self.options.history(), previous);
         Ok(())
     }
 
     fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
-        if let Some(old) = self.sessions.write().remove(key) {
-            self.purge_expired_session(key, old);
-            Ok(())
-        } else {
-            Err(not_found(key))
+        match self.state.write().purge_session(key) {
+            Some(_) => Ok(()),
+            None => Err(not_found(key)),
         }
     }
 
-    fn register_wait(
+    fn register_waits(
         &self,
         ctx: &TenantCtx,
         user_id: &UserId,
-        scope: &ReplyScope,
         session_key: &SessionKey,
         data: SessionData,
-        ttl: Option<Duration>,
-    ) -> SessionResult<()> {
-        Self::ensure_alignment(ctx, &data)?;
-        Self::ensure_user_matches(ctx, user_id, &data)?;
-        let user_lookup = Self::user_lookup_key(ctx, user_id);
-        let scope_key = Self::scope_lookup_key(ctx, user_id, scope);
-        let expires_at = Self::ttl_deadline(ttl);
-
-        let existing = self.sessions.read().get(session_key).cloned();
-        if let Some(existing) = &existing {
-            Self::ensure_ctx_preserved(&existing.data.tenant_ctx, &data.tenant_ctx)?;
-            if let Some(existing_user) = &existing.wait_user {
-                self.remove_from_user_waits(existing_user, session_key);
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        self.options
+            .fence
+            .check_write(ctx, &data)
+            .map_err(fence_rejected)?;
+        self.options
+            .fence
+            .check_wait_user(ctx, user_id, &data)
+            .map_err(fence_rejected)?;
+        validate_waits(waits)?;
+        self.enforce_payload_limits(session_key, &data)?;
+        let user_lookup = UserLookupKey::from_ctx(ctx, user_id);
+
+        let mut state = self.state.write();
+        let created = match state.live_entry(session_key) {
+            Some(existing) => {
+                self.options
+                    .fence
+                    .check_update(&existing.data.tenant_ctx, &data.tenant_ctx)
+                    .map_err(fence_rejected)?;
+                false
             }
-            if let Some(existing_scope) = &existing.scope_key {
-                self.remove_scope_entry(existing_scope);
+            None => {
+                self.enforce_session_quota(&state, ctx)?;
+                true
             }
-        }
-        let entry = SessionEntry {
-            data,
-            expires_at,
-            wait_user: Some(user_lookup.clone()),
-            scope_key: Some(scope_key.clone()),
         };
-        self.sessions.write().insert(session_key.clone(), entry);
+        self.enforce_wait_quota(&state, &user_lookup, session_key, waits.len())?;
+        state.drop_all_waits(session_key);
 
-        let mut waits = self.user_waits.write();
-        waits
-            .entry(user_lookup.clone())
+        let mut entries = BTreeMap::new();
+        for wait in waits {
+            let scope_key = ScopeLookupKey::from_ctx(ctx, user_id, &wait.scope);
+            let expires_at = Self::ttl_deadline(wait.ttl);
+            if let Some(previous) = state.scope_index.get(&scope_key).cloned() {
+                state.drop_wait(&previous.session_key, &previous.wait_name);
+            }
+            state.scope_index.insert(
+                scope_key.clone(),
+                ScopeEntry {
+                    session_key: session_key.clone(),
+                    wait_name: wait.name.clone(),
+                    expires_at,
+                },
+            );
+            entries.insert(
+                wait.name.clone(),
+                WaitEntry {
+                    user: user_lookup.clone(),
+                    scope: wait.scope.clone(),
+                    scope_key,
+                    expires_at,
+                },
+            );
+        }
+        state
+            .user_waits
+            .entry(user_lookup)
             .or_default()
             .insert(session_key.clone());
-        drop(waits);
-
-        let mut scopes = self.scope_index.write();
-        if let Some(existing) = scopes.get(&scope_key)
-            && existing.session_key != *session_key
-        {
-            self.remove_from_user_waits(&user_lookup, &existing.session_key);
+        let mut history = SnapshotLog::default();
+        let mut inbox = Inbox::default();
+        if let Some(previous) = state.sessions.remove(session_key) {
+            history = previous.history;
+            history.archive(self.options.history(), previous.data);
+            inbox = previous.inbox;
         }
-        scopes.insert(
-            scope_key,
-            ScopeEntry {
-                session_key: session_key.clone(),
-                expires_at,
+        state.sessions.insert(
+            session_key.clone(),
+            SessionEntry {
+                data,
+                expires_at: Self::ttl_deadline(session_ttl(waits)),
+                waits: entries,
+                history,
+                inbox,
             },
         );
+        Ok(created)
+    }
+
+    /// Sessions are held as typed values, so there is never anything to rewrite.
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        Ok(MigrationReport {
+            scanned: self.state.read().sessions.len(),
+            migrated: 0,
+        })
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
+            return Err(not_found(key));
+        };
+        let patched = patch.apply(&entry.data)?;
+        self.enforce_payload_limits(key, &patched)?;
+        let previous = std::mem::replace(&mut entry.data, patched.clone());
+        entry.history.archive(self.options.history(), previous);
+        Ok(patched)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
+            return Err(not_found(key));
+        };
+        entry.history.prune(self.options.history());
+        Ok(entry
+            .history
+            .snapshots
+            .iter()
+            .map(|snapshot| SessionVersion {
+                version: snapshot.version,
+                recorded_at_ms: snapshot.recorded_at_ms,
+                cursor: snapshot.data.cursor.clone(),
+            })
+            .collect())
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
+            return Err(not_found(key));
+        };
+        entry.history.prune(self.options.history());
+        Ok(entry.history.find(version).cloned())
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
+            return Err(not_found(key));
+        };
+        entry.history.prune(self.options.history());
+        let Some(snapshot) = entry.history.find(version).cloned() else {
+            return Err(version_not_found(key, version));
+        };
+        self.enforce_payload_limits(key, &snapshot)?;
+        let previous = std::mem::replace(&mut entry.data, snapshot);
+        entry.history.archive(self.options.history(), previous);
         Ok(())
     }
 
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        let mut state = self.state.write();
+        let Some(entry) = state.live_entry(key) else {
+            return Err(not_found(key));
+        };
+        let expired: Vec<String> = entry
+            .waits
+            .iter()
+            .filter(|(_, wait)| Self::is_expired(wait.expires_at))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let live = Self::live_waits(entry);
+        for name in expired {
+            state.drop_wait(key, &name);
+        }
+        Ok(live)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        let mut state = self.state.write();
+        if state.live_entry(key).is_none() {
+            return Err(not_found(key));
+        }
+        Ok(state.drop_wait(key, name))
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        let mut state = self.state.write();
+        if state.live_entry(key).is_none() {
+            return Err(not_found(key));
+        }
+        Ok(state.drop_all_waits(key))
+    }
+
     fn find_wait_by_scope(
         &self,
         ctx: &TenantCtx,
         user_id: &UserId,
         scope: &ReplyScope,
     ) -> SessionResult<Option<SessionKey>> {
-        let scope_key = Self::scope_lookup_key(ctx, user_id, scope);
-        let entry = self.scope_index.read().get(&scope_key).cloned();
-        let Some(entry) = entry else {
-            return Ok(None);
-        };
-        if Self::is_expired(entry.expires_at) {
-            self.remove_scope_entry(&scope_key);
-            self.remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
-            let removed = self.sessions.write().remove(&entry.session_key);
-            if let Some(session_entry) = removed {
-                self.purge_expired_session(&entry.session_key, session_entry);
+        let scope_key = ScopeLookupKey::from_ctx(ctx, user_id, scope);
+        let mut state = self.state.write();
+        if let Some(entry) = state.scope_index.get(&scope_key).cloned() {
+            if self.routes_to_readable(&mut state, ctx, user_id, &scope_key, &entry) {
+                return Ok(Some(entry.session_key));
             }
-            return Ok(None);
-        }
-        let Some(session) = self.get_session(&entry.session_key)? else {
-            self.remove_scope_entry(&scope_key);
-            self.remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
-            return Ok(None);
-        };
-        let stored_ctx = &session.tenant_ctx;
-        if stored_ctx.env != ctx.env
-            || stored_ctx.tenant_id != ctx.tenant_id
-            || Self::normalize_team(stored_ctx) != Self::normalize_team(ctx)
-        {
-            self.remove_scope_entry(&scope_key);
-            self.remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
-            return Ok(None);
+            state.drop_wait(&entry.session_key, &entry.wait_name);
+            state.scope_index.remove(&scope_key);
+            state
+                .remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
+            record_stale_index_entries(BACKEND, "scope", 1);
         }
-        if let Some(stored_user) = Self::normalize_user(stored_ctx)
-            && stored_user != user_id
-        {
-            self.remove_scope_entry(&scope_key);
-            self.remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
+        if !self.options.fence.reads_across_teams(ctx) {
             return Ok(None);
         }
-        Ok(Some(entry.session_key))
+        let other_teams: Vec<(ScopeLookupKey, ScopeEntry)> = state
+            .scope_index
+            .iter()
+            .filter(|(candidate, _)| candidate.is_other_team_of(&scope_key))
+            .map(|(candidate, entry)| (candidate.clone(), entry.clone()))
+            .collect();
+        Ok(other_teams
+            .into_iter()
+            .filter(|(candidate, entry)| {
+                self.routes_to_readable(&mut state, ctx, user_id, candidate, entry)
+            })
+            .map(|(_, entry)| entry.session_key)
+            .min_by(|a, b| a.as_str().cmp(b.as_str())))
     }
 
     fn list_waits_for_user(
@@ -366,33 +495,34 @@ impl SessionStore for InMemorySessionStore {
         user_id: &UserId,
     ) -> SessionResult<Vec<SessionKey>> {
         let lookup = UserLookupKey::from_ctx(ctx, user_id);
-        let keys: Vec<SessionKey> = self
+        let mut state = self.state.write();
+        let keys: Vec<SessionKey> = state
             .user_waits
-            .read()
             .get(&lookup)
             .map(|set| set.iter().cloned().collect())
             .unwrap_or_default();
         let mut available = Vec::new();
         for key in keys {
-            let Some(data) = self.get_session(&key)? else {
-                self.remove_from_user_waits(&lookup, &key);
-                continue;
-            };
-            let stored_ctx = &data.tenant_ctx;
-            if stored_ctx.env != ctx.env
-                || stored_ctx.tenant_id != ctx.tenant_id
-                || Self::normalize_team(stored_ctx) != Self::normalize_team(ctx)
-            {
-                self.remove_from_user_waits(&lookup, &key);
-                continue;
+            if self.waits_readable(&mut state, ctx, user_id, &key) {
+                available.push(key);
+            } else {
+                state.remove_from_user_waits(&lookup, &key);
+                record_stale_index_entries(BACKEND, "user_waits", 1);
             }
-            if let Some(stored_user) = Self::normalize_user(stored_ctx)
-                && stored_user != user_id
-            {
-                self.remove_from_user_waits(&lookup, &key);
-                continue;
+        }
+        if self.options.fence.reads_across_teams(ctx) {
+            let other_teams: HashSet<SessionKey> = state
+                .user_waits
+                .iter()
+                .filter(|(candidate, _)| candidate.is_other_team_of(&lookup))
+                .flat_map(|(_, keys)| keys.iter().cloned())
+                .collect();
+            for key in other_teams {
+                if !available.contains(&key) && self.waits_readable(&mut state, ctx, user_id, &key)
+                {
+                    available.push(key);
+                }
             }
-            available.push(key);
         }
         Ok(available)
     }
@@ -402,14 +532,349 @@ impl SessionStore for InMemorySessionStore {
         ctx: &TenantCtx,
         user_id: &UserId,
         scope: &ReplyScope,
-    ) -> SessionResult<()> {
-        let scope_key = Self::scope_lookup_key(ctx, user_id, scope);
-        let entry = self.scope_index.write().remove(&scope_key);
-        if let Some(entry) = entry {
-            self.remove_from_user_waits(&UserLookupKey::from_ctx(ctx, user_id), &entry.session_key);
-            self.sessions.write().remove(&entry.session_key);
+    ) -> SessionResult<Option<SessionKey>> {
+        let scope_key = ScopeLookupKey::from_ctx(ctx, user_id, scope);
+        let mut state = self.state.write();
+        let Some(entry) = state.scope_index.get(&scope_key).cloned() else {
+            return Ok(None);
+        };
+        let routed = |wait: &WaitEntry| wait.scope_key == scope_key;
+        let live = !Self::is_expired(entry.expires_at)
+            && state.live_entry(&entry.session_key).is_some_and(|session| {
+                session
+                    .waits
+                    .get(&entry.wait_name)
+                    .is_some_and(|wait| routed(wait) && !Self::is_expired(wait.expires_at))
+            });
+        if !live {
+            // An expired or re-routed wait only costs its pointer; the session keeps its other
+            // waits.
+            let expired_wait = state
+                .sessions
+                .get(&entry.session_key)
+                .and_then(|session| session.waits.get(&entry.wait_name))
+                .is_some_and(routed);
+            if expired_wait {
+                state.drop_wait(&entry.session_key, &entry.wait_name);
+            }
+            state.scope_index.remove(&scope_key);
+            record_stale_index_entries(BACKEND, "scope", 1);
+            return Ok(None);
+        }
+        state.scope_index.remove(&scope_key);
+        state.purge_session(&entry.session_key);
+        Ok(Some(entry.session_key))
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        validate_page_limit(limit)?;
+        let mut state = self.state.write();
+        let mut keys: Vec<SessionKey> = state
+            .sessions
+            .keys()
+            .filter(|key| cursor.is_none_or(|cursor| key.as_str() > cursor))
+            .cloned()
+            .collect();
+        keys.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        let mut page = SessionPage::default();
+        for key in keys {
+            if page.sessions.len() == limit {
+                page.next_cursor = page
+                    .sessions
+                    .last()
+                    .map(|listing| listing.key.as_str().to_string());
+                break;
+            }
+            let Some(entry) = state.live_entry(&key) else {
+                continue;
+            };
+            let waits = Self::live_waits(entry);
+            if filter.matches(&entry.data, !waits.is_empty()) {
+                page.sessions.push(SessionListing {
+                    key,
+                    data: entry.data.clone(),
+                    waits,
+                });
+            }
+        }
+        Ok(page)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        let state = self.state.read();
+        let tenants: BTreeSet<(EnvId, TenantId)> = state
+            .sessions
+            .values()
+            .filter(|entry| !Self::is_expired(entry.expires_at))
+            .map(|entry| {
+                let ctx = &entry.data.tenant_ctx;
+                (ctx.env.clone(), ctx.tenant_id.clone())
+            })
+            .collect();
+        Ok(tenants.into_iter().collect())
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let mut state = self.state.write();
+        state
+            .events
+            .retain(|lookup, _| lookup.env != *env || lookup.tenant != *tenant);
+        Ok(state.purge_matching(
+            |ctx| ctx.env == *env && ctx.tenant_id == *tenant,
+            |lookup| lookup.env == *env && lookup.tenant == *tenant,
+        ))
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let mut state = self.state.write();
+        Ok(state.purge_matching(
+            |ctx| ctx.env == *env && ctx.tenant_id == *tenant && normalize_user(ctx) == Some(user),
+            |lookup| lookup.env == *env && lookup.tenant == *tenant && lookup.user == *user,
+        ))
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        let mut state = self.state.write();
+        let expired: Vec<SessionKey> = state
+            .sessions
+            .iter()
+            .filter(|(_, entry)| Self::is_expired(entry.expires_at))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            state.purge_session(key);
+        }
+        state
+            .events
+            .retain(|_, entry| !Self::is_expired(entry.expires_at));
+        Ok(expired)
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        let mut state = self.state.write();
+        let mut report = IndexRepairReport {
+            repaired: repair,
+            sessions: state.sessions.len(),
+            scope_pointers: state.scope_index.len(),
+            user_wait_members: state.user_waits.values().map(HashSet::len).sum(),
+            ..IndexRepairReport::default()
+        };
+
+        let dangling: Vec<ScopeLookupKey> = state
+            .scope_index
+            .iter()
+            .filter(|(scope_key, entry)| !state.scope_routes(scope_key, entry))
+            .map(|(scope_key, _)| scope_key.clone())
+            .collect();
+        for scope_key in dangling {
+            let session = state.scope_index[&scope_key].session_key.clone();
+            report.dangling_scopes.push(DanglingScope {
+                env: scope_key.env.clone(),
+                tenant: scope_key.tenant.clone(),
+                team: scope_key.team.clone(),
+                user: scope_key.user.clone(),
+                scope_hash: scope_key.scope_hash.clone(),
+                session,
+            });
+            if repair {
+                state.scope_index.remove(&scope_key);
+                record_stale_index_entries(BACKEND, "scope", 1);
+            }
+        }
+
+        let orphaned: Vec<(UserLookupKey, SessionKey)> = state
+            .user_waits
+            .iter()
+            .flat_map(|(lookup, keys)| keys.iter().map(move |key| (lookup, key)))
+            .filter(|(lookup, key)| {
+                !state.sessions.get(*key).is_some_and(|entry| {
+                    !Self::is_expired(entry.expires_at)
+                        && entry.waits.values().any(|wait| wait.user == **lookup)
+                })
+            })
+            .map(|(lookup, key)| (lookup.clone(), key.clone()))
+            .collect();
+        for (lookup, key) in orphaned {
+            if repair {
+                state.remove_from_user_waits(&lookup, &key);
+                record_stale_index_entries(BACKEND, "user_waits", 1);
+            }
+            report.orphaned_user_waits.push(OrphanedUserWait {
+                env: lookup.env,
+                tenant: lookup.tenant,
+                team: lookup.team,
+                user: lookup.user,
+                session: key,
+            });
+        }
+
+        let mut unindexed = Vec::new();
+        for (key, entry) in &state.sessions {
+            if Self::is_expired(entry.expires_at) {
+                continue;
+            }
+            for (name, wait) in &entry.waits {
+                if Self::is_expired(wait.expires_at) {
+                    continue;
+                }
+                let routed = state
+                    .scope_index
+                    .get(&wait.scope_key)
+                    .is_some_and(|scope| scope.session_key == *key && scope.wait_name == *name);
+                if !routed {
+                    unindexed.push((key.clone(), name.clone(), IndexIssueKind::ScopeNotRouted));
+                }
+                let indexed = state
+                    .user_waits
+                    .get(&wait.user)
+                    .is_some_and(|keys| keys.contains(key));
+                if !indexed {
+                    unindexed.push((key.clone(), name.clone(), IndexIssueKind::UserNotIndexed));
+                }
+            }
+        }
+        for (key, name, kind) in unindexed {
+            if repair {
+                state.reindex_wait(&key, &name, kind);
+            }
+            report.unindexed_waits.push(IndexIssue {
+                key,
+                wait: name,
+                kind,
+            });
+        }
+        Ok(report)
+    }
+
+    fn health(&self) -> StoreHealth {
+        let started = Instant::now();
+        drop(self.state.read());
+        StoreHealth::healthy(BACKEND).with_latency(started.elapsed())
+    }
+
+    /// Computed from the maps on every call, so the figures are exact.
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        let state = self.state.read();
+        let mut routed: HashMap<&SessionKey, usize> = HashMap::new();
+        for (scope_key, entry) in &state.scope_index {
+            if state.scope_routes(scope_key, entry) {
+                *routed.entry(&entry.session_key).or_default() += 1;
+            }
+        }
+        let mut stats = SessionStats::default();
+        for (key, entry) in &state.sessions {
+            if Self::is_expired(entry.expires_at) {
+                continue;
+            }
+            let waits = entry
+                .waits
+                .values()
+                .filter(|wait| !Self::is_expired(wait.expires_at))
+                .count();
+            if !filter.matches(&entry.data, waits > 0) {
+                continue;
+            }
+            let payload_bytes = self.options.encode_payload(&entry.data)?.len();
+            stats.record(payload_bytes, waits, Self::remaining(entry.expires_at));
+            stats.scope_pointers += routed.get(key).copied().unwrap_or_default();
+        }
+        Ok(stats)
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        validate_inbox_ttl(ttl)?;
+        let mut state = self.state.write();
+        let entry = state.live_entry(key).ok_or_else(|| not_found(key))?;
+        let messages = entry.inbox.live_mut();
+        messages.push_back(InboxMessage::new(payload));
+        let len = messages.len();
+        // A TTL too long to represent never expires.
+        entry.inbox.expires_at = Instant::now().checked_add(ttl);
+        Ok(len)
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let state = self.state.read();
+        Ok(state
+            .sessions
+            .get(key)
+            .filter(|entry| !Self::is_expired(entry.expires_at))
+            .and_then(|entry| entry.inbox.live())
+            .and_then(|messages| messages.front().cloned()))
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let mut state = self.state.write();
+        Ok(state
+            .live_entry(key)
+            .and_then(|entry| entry.inbox.live_mut().pop_front()))
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        let state = self.state.read();
+        Ok(state
+            .sessions
+            .get(key)
+            .filter(|entry| !Self::is_expired(entry.expires_at))
+            .and_then(|entry| entry.inbox.live())
+            .map_or(0, VecDeque::len))
+    }
+
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        validate_event_id(event_id)?;
+        validate_event_ttl(ttl)?;
+        let lookup = EventLookupKey::from_ctx(ctx, event_id);
+        let mut state = self.state.write();
+        if let Some(entry) = state.events.get(&lookup)
+            && !Self::is_expired(entry.expires_at)
+        {
+            return Ok(EventDedup::Duplicate {
+                session: entry.session.clone(),
+            });
+        }
+        state.events.insert(
+            lookup,
+            EventEntry {
+                session: None,
+                // A TTL too long to represent never expires.
+                expires_at: Instant::now().checked_add(ttl),
+            },
+        );
+        Ok(EventDedup::FirstSeen)
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        validate_event_id(event_id)?;
+        let lookup = EventLookupKey::from_ctx(ctx, event_id);
+        let mut state = self.state.write();
+        match state.events.get_mut(&lookup) {
+            Some(entry) if !Self::is_expired(entry.expires_at) => {
+                entry.session = Some(key.clone());
+                Ok(true)
+            }
+            _ => Ok(false),
         }
-        Ok(())
     }
 
     fn find_by_user(
@@ -432,17 +897,246 @@ impl SessionStore for InMemorySessionStore {
     }
 }
 
-#[derive(Clone)]
+#[derive(Default)]
+struct StoreState {
+    sessions: HashMap<SessionKey, SessionEntry>,
+    user_waits: HashMap<UserLookupKey, HashSet<SessionKey>>,
+    scope_index: HashMap<ScopeLookupKey, ScopeEntry>,
+    events: HashMap<EventLookupKey, EventEntry>,
+}
+
+impl StoreState {
+    /// Returns the session entry unless it is missing or expired; expired entries are purged.
+    fn live_entry(&mut self, key: &SessionKey) -> Option<&mut SessionEntry> {
+        let expired = InMemorySessionStore::is_expired(self.sessions.get(key)?.expires_at);
+        if expired {
+            self.purge_session(key);
+            return None;
+        }
+        self.sessions.get_mut(key)
+    }
+
+    fn remove_from_user_waits(&mut self, lookup: &UserLookupKey, key: &SessionKey) {
+        if let Some(entries) = self.user_waits.get_mut(lookup) {
+            entries.remove(key);
+            if entries.is_empty() {
+                self.user_waits.remove(lookup);
+            }
+        }
+    }
+
+    fn release_scope(&mut self, scope_key: &ScopeLookupKey, key: &SessionKey, name: &str) {
+        let owned = self
+            .scope_index
+            .get(scope_key)
+            .is_some_and(|entry| entry.session_key == *key && entry.wait_name == name);
+        if owned {
+            self.scope_index.remove(scope_key);
+        }
+    }
+
+    /// Returns `true` when `entry` names a live wait registered on `scope_key`.
+    fn scope_routes(&self, scope_key: &ScopeLookupKey, entry: &ScopeEntry) -> bool {
+        !InMemorySessionStore::is_expired(entry.expires_at)
+            && self
+                .sessions
+                .get(&entry.session_key)
+                .is_some_and(|session| {
+                    !InMemorySessionStore::is_expired(session.expires_at)
+                        && session
+                            .waits
+                            .get(&entry.wait_name)
+                            .is_some_and(|wait| wait.scope_key == *scope_key)
+                })
+    }
+
+    /// Restores the index entry `kind` reports missing for a wait, or drops the wait when its
+    /// scope now routes to another session.
+    fn reindex_wait(&mut self, key: &SessionKey, name: &str, kind: IndexIssueKind) {
+        let Some(wait) = self
+            .sessions
+            .get(key)
+            .and_then(|entry| entry.waits.get(name))
+        else {
+            return;
+        };
+        let (scope_key, user, expires_at) =
+            (wait.scope_key.clone(), wait.user.clone(), wait.expires_at);
+        match kind {
+            IndexIssueKind::ScopeNotRouted if self.scope_index.contains_key(&scope_key) => {
+                self.drop_wait(key, name);
+            }
+            IndexIssueKind::ScopeNotRouted => {
+                self.scope_index.insert(
+                    scope_key,
+                    ScopeEntry {
+                        session_key: key.clone(),
+                        wait_name: name.to_string(),
+                        expires_at,
+                    },
+                );
+            }
+            IndexIssueKind::UserNotIndexed => {
+                self.user_waits.entry(user).or_default().insert(key.clone());
+            }
+        }
+    }
+
+    fn drop_wait(&mut self, key: &SessionKey, name: &str) -> bool {
+        let Some(entry) = self.sessions.get_mut(key) else {
+            return false;
+        };
+        let Some(wait) = entry.waits.remove(name) else {
+            return false;
+        };
+        let exhausted = entry.waits.is_empty();
+        self.release_scope(&wait.scope_key, key, name);
+        if exhausted {
+            self.remove_from_user_waits(&wait.user, key);
+        }
+        true
+    }
+
+    fn drop_all_waits(&mut self, key: &SessionKey) -> Vec<String> {
+        let Some(entry) = self.sessions.get_mut(key) else {
+            return Vec::new();
+        };
+        let waits = std::mem::take(&mut entry.waits);
+        let mut names = Vec::with_capacity(waits.len());
+        for (name, wait) in waits {
+            self.release_scope(&wait.scope_key, key, &name);
+            self.remove_from_user_waits(&wait.user, key);
+            names.push(name);
+        }
+        names
+    }
+
+    /// Deletes sessions whose context satisfies `session_matches` and every routing index
+    /// belonging to a user lookup satisfying `lookup_matches`.
+    fn purge_matching(
+        &mut self,
+        session_matches: impl Fn(&TenantCtx) -> bool,
+        lookup_matches: impl Fn(&UserLookupKey) -> bool,
+    ) -> PurgeReport {
+        let mut report = PurgeReport::default();
+        self.scope_index.retain(|scope_key, _| {
+            let matched = lookup_matches(&scope_key.user_lookup());
+            report.scope_pointers += usize::from(matched);
+            !matched
+        });
+        self.user_waits.retain(|lookup, _| {
+            let matched = lookup_matches(lookup);
+            report.user_wait_sets += usize::from(matched);
+            !matched
+        });
+        let keys: Vec<SessionKey> = self
+            .sessions
+            .iter()
+            .filter(|(_, entry)| session_matches(&entry.data.tenant_ctx))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.purge_session(&key);
+            report.sessions += 1;
+        }
+        report
+    }
+
+    fn purge_session(&mut self, key: &SessionKey) -> Option<SessionEntry> {
+        self.drop_all_waits(key);
+        self.sessions.remove(key)
+    }
+}
+
 struct SessionEntry {
     data: SessionData,
     expires_at: Option<Instant>,
-    wait_user: Option<UserLookupKey>,
-    scope_key: Option<ScopeLookupKey>,
+    waits: BTreeMap<String, WaitEntry>,
+    history: SnapshotLog,
+    inbox: Inbox,
+}
+
+/// Messages queued on a session, oldest first.
+#[derive(Default)]
+struct Inbox {
+    messages: VecDeque<InboxMessage>,
+    expires_at: Option<Instant>,
+}
+
+impl Inbox {
+    /// Returns the queued messages, or `None` once the inbox expired.
+    fn live(&self) -> Option<&VecDeque<InboxMessage>> {
+        (!InMemorySessionStore::is_expired(self.expires_at)).then_some(&self.messages)
+    }
+
+    /// Returns the queued messages for modification, emptying the inbox first if it expired.
+    fn live_mut(&mut self) -> &mut VecDeque<InboxMessage> {
+        if InMemorySessionStore::is_expired(self.expires_at) {
+            self.messages.clear();
+            self.expires_at = None;
+        }
+        &mut self.messages
+    }
+}
+
+/// Archived payloads of a session, newest first.
+#[derive(Default)]
+struct SnapshotLog {
+    snapshots: VecDeque<Snapshot>,
+    last_version: u64,
+}
+
+struct Snapshot {
+    version: u64,
+    recorded_at_ms: u64,
+    data: SessionData,
+}
+
+impl SnapshotLog {
+    fn archive(&mut self, policy: Option<&HistoryPolicy>, data: SessionData) {
+        let Some(policy) = policy else {
+            return;
+        };
+        self.last_version += 1;
+        self.snapshots.push_front(Snapshot {
+            version: self.last_version,
+            recorded_at_ms: now_millis(),
+            data,
+        });
+        self.prune(Some(policy));
+    }
+
+    /// Drops snapshots outside the policy, or all of them when history is disabled.
+    fn prune(&mut self, policy: Option<&HistoryPolicy>) {
+        let Some(policy) = policy else {
+            self.snapshots.clear();
+            return;
+        };
+        let now = now_millis();
+        self.snapshots.truncate(policy.depth);
+        self.snapshots
+            .retain(|snapshot| policy.retains(snapshot.recorded_at_ms, now));
+    }
+
+    fn find(&self, version: u64) -> Option<&SessionData> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.version == version)
+            .map(|snapshot| &snapshot.data)
+    }
+}
+
+struct WaitEntry {
+    user: UserLookupKey,
+    scope: ReplyScope,
+    scope_key: ScopeLookupKey,
+    expires_at: Option<Instant>,
 }
 
 #[derive(Clone)]
 struct ScopeEntry {
     session_key: SessionKey,
+    wait_name: String,
     expires_at: Option<Instant>,
 }
 
@@ -456,6 +1150,22 @@ struct ScopeLookupKey {
 }
 
 impl ScopeLookupKey {
+    /// Returns `true` for the same user and scope under another team of the tenant.
+    fn is_other_team_of(&self, other: &Self) -> bool {
+        self.team != other.team
+            && self.scope_hash == other.scope_hash
+            && self.user_lookup().is_other_team_of(&other.user_lookup())
+    }
+
+    fn user_lookup(&self) -> UserLookupKey {
+        UserLookupKey {
+            env: self.env.clone(),
+            tenant: self.tenant.clone(),
+            team: self.team.clone(),
+            user: self.user.clone(),
+        }
+    }
+
     fn from_ctx(ctx: &TenantCtx, user: &UserId, scope: &ReplyScope) -> Self {
         Self {
             env: ctx.env.clone(),
@@ -476,6 +1186,14 @@ struct UserLookupKey {
 }
 
 impl UserLookupKey {
+    /// Returns `true` for the same user under another team of the tenant.
+    fn is_other_team_of(&self, other: &Self) -> bool {
+        self.team != other.team
+            && self.env == other.env
+            && self.tenant == other.tenant
+            && self.user == other.user
+    }
+
     fn from_ctx(ctx: &TenantCtx, user: &UserId) -> Self {
         Self {
             env: ctx.env.clone(),
@@ -485,3 +1203,28 @@ impl UserLookupKey {
         }
     }
 }
+
+/// Recorded inbound event, keyed like the other lookups by env, tenant and team.
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct EventLookupKey {
+    env: EnvId,
+    tenant: TenantId,
+    team: Option<TeamId>,
+    event_id: String,
+}
+
+impl EventLookupKey {
+    fn from_ctx(ctx: &TenantCtx, event_id: &str) -> Self {
+        Self {
+            env: ctx.env.clone(),
+            tenant: ctx.tenant_id.clone(),
+            team: ctx.team_id.clone().or_else(|| ctx.team.clone()),
+            event_id: event_id.to_string(),
+        }
+    }
+}
+
+struct EventEntry {
+    session: Option<SessionKey>,
+    expires_at: Option<Instant>,
+}
@@ -2,14 +2,67 @@
 
 mod backends;
 
+pub mod admin;
+pub mod archive;
+pub mod audit;
+pub mod codec;
+pub mod dedup;
 pub mod error;
+pub mod fence;
+pub mod format;
+pub mod health;
+pub mod history;
+pub mod inbox;
 pub mod inmemory;
+pub mod listing;
 pub mod mapping;
+pub mod metrics;
+pub mod migrating;
+pub mod observer;
+pub mod options;
+pub mod patch;
+pub mod payload;
+pub mod purge;
+pub mod quota;
+pub mod repair;
+pub mod resilient;
+pub mod stats;
 pub mod store;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod tracing;
+pub mod wait;
 
-pub use error::{ErrorCode, GreenticError, SessionResult};
+#[cfg(feature = "cbor")]
+pub use codec::CborCodec;
+#[cfg(feature = "msgpack")]
+pub use codec::MessagePackCodec;
+pub use codec::{JsonCodec, SessionCodec};
+pub use dedup::{EventDedup, MAX_EVENT_ID_LEN};
+pub use error::{ErrorCode, GreenticError, SessionErrorDetail, SessionResult};
+pub use fence::{
+    ADMIN_ATTRIBUTE, FenceField, FenceViolation, TenantFence, has_admin_claim, with_admin_claim,
+};
+pub use format::{CURRENT_FORMAT_VERSION, MigrationRegistry, MigrationReport};
 pub use greentic_types::{ReplyScope, SessionData, SessionKey, WaitScope};
+pub use health::{HealthStatus, StoreHealth};
+pub use history::{HistoryPolicy, SessionVersion};
+pub use inbox::InboxMessage;
+pub use listing::{SessionFilter, SessionListing, SessionPage};
+pub use migrating::{MigratingSessionStore, MigrationPhase};
+pub use observer::{ObservedSessionStore, SessionObserver};
+pub use options::SessionStoreOptions;
+pub use patch::{ContextPatch, JsonPatch, SessionPatch};
+pub use payload::{PayloadLimit, PayloadSizeWarning};
+pub use purge::PurgeReport;
+pub use quota::{QuotaKind, QuotaPolicy, SessionLimits};
+pub use repair::IndexRepairReport;
+pub use resilient::{
+    CircuitBreakerPolicy, CircuitHealth, CircuitState, ResilientSessionStore, RetryPolicy,
+};
+pub use stats::{SessionStats, TtlBuckets};
 pub use store::SessionStore;
+pub use wait::{DEFAULT_WAIT_NAME, SessionWait, WaitSpec};
 
 /// Configuration for selecting a session backend.
 #[derive(Debug, Clone)]
@@ -26,17 +79,28 @@ pub enum SessionBackendConfig {
 
 /// Creates a boxed session store using the provided backend configuration.
 pub fn create_session_store(config: SessionBackendConfig) -> SessionResult<Box<dyn SessionStore>> {
+    create_session_store_with_options(config, SessionStoreOptions::default())
+}
+
+/// Creates a boxed session store using the provided backend configuration and options.
+pub fn create_session_store_with_options(
+    config: SessionBackendConfig,
+    options: SessionStoreOptions,
+) -> SessionResult<Box<dyn SessionStore>> {
     match config {
-        SessionBackendConfig::InMemory => Ok(Box::new(inmemory::InMemorySessionStore::new())),
+        SessionBackendConfig::InMemory => Ok(Box::new(
+            inmemory::InMemorySessionStore::with_options(options),
+        )),
         #[cfg(feature = "redis")]
         SessionBackendConfig::RedisUrl(url) => {
-            let store = backends::redis::RedisSessionStore::from_url(&url)?;
+            let store = backends::redis::RedisSessionStore::from_url(&url)?.with_options(options);
             Ok(Box::new(store))
         }
         #[cfg(feature = "redis")]
         SessionBackendConfig::RedisUrlWithNamespace { url, namespace } => {
             let store =
-                backends::redis::RedisSessionStore::from_url_with_namespace(&url, namespace)?;
+                backends::redis::RedisSessionStore::from_url_with_namespace(&url, namespace)?
+                    .with_options(options);
             Ok(Box::new(store))
         }
     }
@@ -0,0 +1,131 @@
+use crate::error::{SessionResult, invalid_argument};
+use crate::fence::{normalize_team, normalize_user};
+use crate::wait::SessionWait;
+use greentic_types::{EnvId, FlowId, PackId, SessionData, SessionKey, TeamId, TenantId, UserId};
+
+/// Selects the sessions returned by [`crate::SessionStore::list_sessions`].
+///
+/// Listing is always scoped to one env + tenant; the remaining fields narrow the result further.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionFilter {
+    /// Environment the sessions belong to.
+    pub env: EnvId,
+    /// Tenant the sessions belong to.
+    pub tenant: TenantId,
+    /// Only sessions bound to this team.
+    pub team: Option<TeamId>,
+    /// Only sessions bound to this user.
+    pub user: Option<UserId>,
+    /// Only sessions executing this flow.
+    pub flow_id: Option<FlowId>,
+    /// Only sessions tied to this pack.
+    pub pack_id: Option<PackId>,
+    /// Only sessions holding at least one live wait.
+    pub waiting_only: bool,
+}
+
+impl SessionFilter {
+    /// Matches every session of the tenant.
+    pub fn tenant(env: EnvId, tenant: TenantId) -> Self {
+        Self {
+            env,
+            tenant,
+            team: None,
+            user: None,
+            flow_id: None,
+            pack_id: None,
+            waiting_only: false,
+        }
+    }
+
+    /// Restricts the filter to a team.
+    pub fn with_team(mut self, team: TeamId) -> Self {
+        self.team = Some(team);
+        self
+    }
+
+    /// Restricts the filter to a user.
+    pub fn with_user(mut self, user: UserId) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// Restricts the filter to a flow.
+    pub fn with_flow(mut self, flow_id: FlowId) -> Self {
+        self.flow_id = Some(flow_id);
+        self
+    }
+
+    /// Restricts the filter to a pack.
+    pub fn with_pack(mut self, pack_id: PackId) -> Self {
+        self.pack_id = Some(pack_id);
+        self
+    }
+
+    /// Only returns sessions that are currently waiting for input.
+    pub fn waiting_only(mut self) -> Self {
+        self.waiting_only = true;
+        self
+    }
+
+    /// Returns `true` when the filter selects every session of the tenant.
+    #[cfg(feature = "redis")]
+    pub(crate) fn is_tenant_wide(&self) -> bool {
+        self.team.is_none()
+            && self.user.is_none()
+            && self.flow_id.is_none()
+            && self.pack_id.is_none()
+            && !self.waiting_only
+    }
+
+    /// Returns `true` when the session data satisfies every field of the filter.
+    pub(crate) fn matches(&self, data: &SessionData, waiting: bool) -> bool {
+        let ctx = &data.tenant_ctx;
+        ctx.env == self.env
+            && ctx.tenant_id == self.tenant
+            && self
+                .team
+                .as_ref()
+                .is_none_or(|team| normalize_team(ctx) == Some(team))
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|user| normalize_user(ctx) == Some(user))
+            && self
+                .flow_id
+                .as_ref()
+                .is_none_or(|flow| data.flow_id == *flow)
+            && self
+                .pack_id
+                .as_ref()
+                .is_none_or(|pack| data.pack_id.as_ref() == Some(pack))
+            && (!self.waiting_only || waiting)
+    }
+}
+
+/// Session returned by [`crate::SessionStore::list_sessions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionListing {
+    /// Key of the session.
+    pub key: SessionKey,
+    /// Stored session payload.
+    pub data: SessionData,
+    /// Live waits registered on the session.
+    pub waits: Vec<SessionWait>,
+}
+
+/// One page of sessions ordered by session key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionPage {
+    /// Sessions on this page.
+    pub sessions: Vec<SessionListing>,
+    /// Opaque cursor for the next page; `None` once the listing is exhausted.
+    pub next_cursor: Option<String>,
+}
+
+pub(crate) fn validate_page_limit(limit: usize) -> SessionResult<()> {
+    if limit == 0 {
+        return Err(invalid_argument("page limit must be greater than zero"));
+    }
+    Ok(())
+}
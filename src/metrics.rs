@@ -0,0 +1,467 @@
+//! Metrics emitted through the [`metrics`](https://docs.rs/metrics) facade when the `metrics`
+//! feature is enabled.
+//!
+//! [`MeteredSessionStore`] records, for every [`SessionStore`] operation:
+//!
+//! - `greentic_session_operations_total` (counter)
+//! - `greentic_session_operation_duration_seconds` (histogram)
+//!
+//! both labeled with `backend`, `operation`, `outcome` (`ok` or the error code) and `tenant`.
+//! `find_wait_by_scope` additionally feeds `greentic_session_wait_lookups_total` labeled with
+//! `result` (`hit` or `miss`).
+//!
+//! Backends report maintenance work independently of the wrapper:
+//! `greentic_session_stale_index_entries_purged_total` (labels `backend`, `index`) counts routing
+//! and listing entries dropped because their session or wait had gone, and
+//! `greentic_session_backend_errors_total` (labels `backend`, `kind`) counts Redis errors.
+
+#[cfg(feature = "metrics")]
+pub use metered::{MeteredSessionStore, MetricsConfig, TenantLabels};
+
+/// Records `count` stale index entries purged by a backend.
+pub(crate) fn record_stale_index_entries(backend: &'static str, index: &'static str, count: usize) {
+    #[cfg(feature = "metrics")]
+    if count > 0 {
+        ::metrics::counter!(
+            "greentic_session_stale_index_entries_purged_total",
+            "backend" => backend,
+            "index" => index
+        )
+        .increment(u64::try_from(count).unwrap_or(u64::MAX));
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (backend, index, count);
+}
+
+/// Records an error raised by the backend client before it is mapped to a [`crate::GreenticError`].
+#[cfg_attr(not(feature = "redis"), allow(dead_code))]
+pub(crate) fn record_backend_error(backend: &'static str, kind: String) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(
+        "greentic_session_backend_errors_total",
+        "backend" => backend,
+        "kind" => kind
+    )
+    .increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (backend, kind);
+}
+
+#[cfg(feature = "metrics")]
+mod metered {
+    use crate::ReplyScope;
+    use crate::dedup::EventDedup;
+    use crate::error::{ErrorCode, SessionResult};
+    use crate::format::MigrationReport;
+    use crate::health::StoreHealth;
+    use crate::history::SessionVersion;
+    use crate::inbox::InboxMessage;
+    use crate::listing::{SessionFilter, SessionPage};
+    use crate::patch::SessionPatch;
+    use crate::purge::PurgeReport;
+    use crate::repair::IndexRepairReport;
+    use crate::stats::SessionStats;
+    use crate::store::SessionStore;
+    use crate::wait::{SessionWait, WaitSpec};
+    use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+    use parking_lot::RwLock;
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    /// Label used for tenants folded away by [`TenantLabels`].
+    const OTHER_TENANT: &str = "other";
+    /// Label used when an operation is not tied to a single tenant.
+    const NO_TENANT: &str = "-";
+
+    /// Controls how the `tenant` label is populated, bounding metric cardinality.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum TenantLabels {
+        /// Every series carries `tenant="-"`.
+        Omit,
+        /// Only the listed tenants are labeled; every other tenant reports as `other`.
+        Allowlist(HashSet<TenantId>),
+        /// The first `n` tenants observed are labeled; later tenants report as `other`.
+        FirstSeen(usize),
+    }
+
+    impl Default for TenantLabels {
+        fn default() -> Self {
+            Self::FirstSeen(100)
+        }
+    }
+
+    /// Configuration of a [`MeteredSessionStore`].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct MetricsConfig {
+        /// Value of the `backend` label, for example `redis` or `inmemory`.
+        pub backend: String,
+        /// Cardinality control applied to the `tenant` label.
+        pub tenant_labels: TenantLabels,
+    }
+
+    impl MetricsConfig {
+        /// Labels series with `backend` and the default tenant cardinality limit.
+        pub fn new(backend: impl Into<String>) -> Self {
+            Self {
+                backend: backend.into(),
+                tenant_labels: TenantLabels::default(),
+            }
+        }
+
+        /// Overrides how the `tenant` label is populated.
+        pub fn with_tenant_labels(mut self, tenant_labels: TenantLabels) -> Self {
+            self.tenant_labels = tenant_labels;
+            self
+        }
+    }
+
+    /// [`SessionStore`] wrapper recording per-operation counters and latency histograms.
+    pub struct MeteredSessionStore<S> {
+        inner: S,
+        config: MetricsConfig,
+        seen_tenants: RwLock<HashSet<TenantId>>,
+    }
+
+    impl<S: SessionStore> MeteredSessionStore<S> {
+        /// Wraps `inner`, labeling its series according to `config`.
+        pub fn new(inner: S, config: MetricsConfig) -> Self {
+            Self {
+                inner,
+                config,
+                seen_tenants: RwLock::new(HashSet::new()),
+            }
+        }
+
+        /// Returns the wrapped store.
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+
+        /// Unwraps the store.
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+
+        fn tenant_label(&self, tenant: Option<&TenantId>) -> String {
+            let Some(tenant) = tenant else {
+                return NO_TENANT.to_string();
+            };
+            let labeled = match &self.config.tenant_labels {
+                TenantLabels::Omit => return NO_TENANT.to_string(),
+                TenantLabels::Allowlist(allowed) => allowed.contains(tenant),
+                TenantLabels::FirstSeen(limit) => {
+                    self.seen_tenants.read().contains(tenant) || {
+                        let mut seen = self.seen_tenants.write();
+                        if seen.len() < *limit {
+                            seen.insert(tenant.clone());
+                        }
+                        seen.contains(tenant)
+                    }
+                }
+            };
+            if labeled {
+                tenant.as_str().to_string()
+            } else {
+                OTHER_TENANT.to_string()
+            }
+        }
+
+        /// Runs `op` and records its outcome and latency under `operation`.
+        fn measure<T>(
+            &self,
+            operation: &'static str,
+            tenant: Option<&TenantId>,
+            op: impl FnOnce() -> SessionResult<T>,
+        ) -> SessionResult<T> {
+            let started = Instant::now();
+            let result = op();
+            let elapsed = started.elapsed().as_secs_f64();
+            let outcome = match &result {
+                Ok(_) => "ok",
+                Err(err) => outcome_label(err.code),
+            };
+            let backend = self.config.backend.clone();
+            let tenant = self.tenant_label(tenant);
+            ::metrics::counter!(
+                "greentic_session_operations_total",
+                "backend" => backend.clone(),
+                "operation" => operation,
+                "outcome" => outcome,
+                "tenant" => tenant.clone()
+            )
+            .increment(1);
+            ::metrics::histogram!(
+                "greentic_session_operation_duration_seconds",
+                "backend" => backend,
+                "operation" => operation,
+                "outcome" => outcome,
+                "tenant" => tenant
+            )
+            .record(elapsed);
+            result
+        }
+    }
+
+    fn outcome_label(code: ErrorCode) -> &'static str {
+        match code {
+            ErrorCode::Unknown => "unknown",
+            ErrorCode::InvalidInput => "invalid_input",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::PermissionDenied => "permission_denied",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    impl<S: SessionStore> SessionStore for MeteredSessionStore<S> {
+        fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+            self.measure("create_session", Some(&ctx.tenant_id), || {
+                self.inner.create_session(ctx, data)
+            })
+        }
+
+        fn insert_session(
+            &self,
+            ctx: &TenantCtx,
+            key: &SessionKey,
+            data: SessionData,
+        ) -> SessionResult<()> {
+            self.measure("insert_session", Some(&ctx.tenant_id), || {
+                self.inner.insert_session(ctx, key, data)
+            })
+        }
+
+        fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+            self.measure("get_session", None, || self.inner.get_session(key))
+        }
+
+        fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+            let tenant = data.tenant_ctx.tenant_id.clone();
+            self.measure("update_session", Some(&tenant), || {
+                self.inner.update_session(key, data)
+            })
+        }
+
+        fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+            self.measure("remove_session", None, || self.inner.remove_session(key))
+        }
+
+        fn migrate_all(&self) -> SessionResult<MigrationReport> {
+            self.measure("migrate_all", None, || self.inner.migrate_all())
+        }
+
+        fn patch_session(
+            &self,
+            key: &SessionKey,
+            patch: &SessionPatch,
+        ) -> SessionResult<SessionData> {
+            self.measure("patch_session", None, || {
+                self.inner.patch_session(key, patch)
+            })
+        }
+
+        fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+            self.measure("list_session_versions", None, || {
+                self.inner.list_session_versions(key)
+            })
+        }
+
+        fn get_session_version(
+            &self,
+            key: &SessionKey,
+            version: u64,
+        ) -> SessionResult<Option<SessionData>> {
+            self.measure("get_session_version", None, || {
+                self.inner.get_session_version(key, version)
+            })
+        }
+
+        fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+            self.measure("rollback_session", None, || {
+                self.inner.rollback_session(key, version)
+            })
+        }
+
+        fn register_waits(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            session_key: &SessionKey,
+            data: SessionData,
+            waits: &[WaitSpec],
+        ) -> SessionResult<bool> {
+            self.measure("register_waits", Some(&ctx.tenant_id), || {
+                self.inner
+                    .register_waits(ctx, user_id, session_key, data, waits)
+            })
+        }
+
+        fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+            self.measure("list_session_waits", None, || {
+                self.inner.list_session_waits(key)
+            })
+        }
+
+        fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+            self.measure("clear_session_wait", None, || {
+                self.inner.clear_session_wait(key, name)
+            })
+        }
+
+        fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+            self.measure("clear_session_waits", None, || {
+                self.inner.clear_session_waits(key)
+            })
+        }
+
+        fn find_wait_by_scope(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            scope: &ReplyScope,
+        ) -> SessionResult<Option<SessionKey>> {
+            let found = self.measure("find_wait_by_scope", Some(&ctx.tenant_id), || {
+                self.inner.find_wait_by_scope(ctx, user_id, scope)
+            })?;
+            ::metrics::counter!(
+                "greentic_session_wait_lookups_total",
+                "backend" => self.config.backend.clone(),
+                "result" => if found.is_some() { "hit" } else { "miss" },
+                "tenant" => self.tenant_label(Some(&ctx.tenant_id))
+            )
+            .increment(1);
+            Ok(found)
+        }
+
+        fn list_waits_for_user(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+        ) -> SessionResult<Vec<SessionKey>> {
+            self.measure("list_waits_for_user", Some(&ctx.tenant_id), || {
+                self.inner.list_waits_for_user(ctx, user_id)
+            })
+        }
+
+        fn clear_wait(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            scope: &ReplyScope,
+        ) -> SessionResult<Option<SessionKey>> {
+            self.measure("clear_wait", Some(&ctx.tenant_id), || {
+                self.inner.clear_wait(ctx, user_id, scope)
+            })
+        }
+
+        fn list_sessions(
+            &self,
+            filter: &SessionFilter,
+            cursor: Option<&str>,
+            limit: usize,
+        ) -> SessionResult<SessionPage> {
+            self.measure("list_sessions", Some(&filter.tenant), || {
+                self.inner.list_sessions(filter, cursor, limit)
+            })
+        }
+
+        fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+            self.measure("list_tenants", None, || self.inner.list_tenants())
+        }
+
+        fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+            self.measure("purge_tenant", Some(tenant), || {
+                self.inner.purge_tenant(env, tenant)
+            })
+        }
+
+        fn purge_user(
+            &self,
+            env: &EnvId,
+            tenant: &TenantId,
+            user: &UserId,
+        ) -> SessionResult<PurgeReport> {
+            self.measure("purge_user", Some(tenant), || {
+                self.inner.purge_user(env, tenant, user)
+            })
+        }
+
+        fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+            self.measure("purge_expired", None, || self.inner.purge_expired())
+        }
+
+        fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+            self.measure("verify_and_repair", None, || {
+                self.inner.verify_and_repair(repair)
+            })
+        }
+
+        fn health(&self) -> StoreHealth {
+            self.inner.health()
+        }
+
+        fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+            self.measure("stats", Some(&filter.tenant), || self.inner.stats(filter))
+        }
+
+        fn record_event_once(
+            &self,
+            ctx: &TenantCtx,
+            event_id: &str,
+            ttl: Duration,
+        ) -> SessionResult<EventDedup> {
+            self.measure("record_event_once", Some(&ctx.tenant_id), || {
+                self.inner.record_event_once(ctx, event_id, ttl)
+            })
+        }
+
+        fn bind_event_session(
+            &self,
+            ctx: &TenantCtx,
+            event_id: &str,
+            key: &SessionKey,
+        ) -> SessionResult<bool> {
+            self.measure("bind_event_session", Some(&ctx.tenant_id), || {
+                self.inner.bind_event_session(ctx, event_id, key)
+            })
+        }
+
+        fn inbox_push(
+            &self,
+            key: &SessionKey,
+            payload: &str,
+            ttl: Duration,
+        ) -> SessionResult<usize> {
+            self.measure("inbox_push", None, || {
+                self.inner.inbox_push(key, payload, ttl)
+            })
+        }
+
+        fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+            self.measure("inbox_peek", None, || self.inner.inbox_peek(key))
+        }
+
+        fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+            self.measure("inbox_pop", None, || self.inner.inbox_pop(key))
+        }
+
+        fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+            self.measure("inbox_len", None, || self.inner.inbox_len(key))
+        }
+
+        #[allow(deprecated)]
+        fn find_by_user(
+            &self,
+            ctx: &TenantCtx,
+            user: &UserId,
+        ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+            self.measure("find_by_user", Some(&ctx.tenant_id), || {
+                self.inner.find_by_user(ctx, user)
+            })
+        }
+    }
+}
@@ -0,0 +1,644 @@
+//! Live migration of sessions from one backend to another.
+//!
+//! [`MigratingSessionStore`] sits in front of an old and a new store while sessions are moved
+//! across. Writes always land in the new store; reads prefer it and fall back to the old one
+//! until the migration is flipped to [`MigrationPhase::NewOnly`]. A session that only exists in
+//! the old store is copied over, waits included, the first time it is written to, and
+//! [`MigratingSessionStore::backfill`] copies the rest.
+
+use crate::ReplyScope;
+use crate::archive::{ImportReport, restore_listing};
+use crate::dedup::EventDedup;
+use crate::error::{ErrorCode, SessionResult, not_found, session_exists};
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::InboxMessage;
+use crate::listing::{SessionFilter, SessionListing, SessionPage};
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::IndexRepairReport;
+use crate::stats::SessionStats;
+use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec};
+use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const BACKFILL_PAGE_SIZE: usize = 256;
+
+/// Stage of a live migration, changed at runtime through
+/// [`MigratingSessionStore::set_phase`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MigrationPhase {
+    /// Writes go to the new store and are mirrored to the old one, so the migration can still be
+    /// rolled back by dropping the new store.
+    #[default]
+    DualWrite,
+    /// Writes go to the new store only; every session written is removed from the old store,
+    /// which drains as traffic and backfill move sessions across.
+    NewWrites,
+    /// The old store is no longer consulted.
+    NewOnly,
+}
+
+impl MigrationPhase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::DualWrite,
+            1 => Self::NewWrites,
+            _ => Self::NewOnly,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Self::DualWrite => 0,
+            Self::NewWrites => 1,
+            Self::NewOnly => 2,
+        }
+    }
+}
+
+/// [`SessionStore`] moving sessions from an `old` backend to a `new` one without downtime.
+///
+/// Paused flows keep resuming while the migration runs: lookups that miss the new store are
+/// answered from the old one, and the session is copied over before it is changed. Listings and
+/// purges cover both stores until the phase is [`MigrationPhase::NewOnly`]. History recorded by
+/// the old store is not carried over.
+pub struct MigratingSessionStore<O, N> {
+    old: O,
+    new: N,
+    phase: AtomicU8,
+}
+
+impl<O: SessionStore, N: SessionStore> MigratingSessionStore<O, N> {
+    /// Starts a migration from `old` to `new` in [`MigrationPhase::DualWrite`].
+    pub fn new(old: O, new: N) -> Self {
+        Self {
+            old,
+            new,
+            phase: AtomicU8::new(MigrationPhase::DualWrite.as_u8()),
+        }
+    }
+
+    /// Starts the migration in `phase` instead.
+    pub fn with_phase(self, phase: MigrationPhase) -> Self {
+        self.set_phase(phase);
+        self
+    }
+
+    /// Returns the current phase.
+    pub fn phase(&self) -> MigrationPhase {
+        MigrationPhase::from_u8(self.phase.load(Ordering::Acquire))
+    }
+
+    /// Switches the phase; operations already running finish under the previous one.
+    pub fn set_phase(&self, phase: MigrationPhase) {
+        self.phase.store(phase.as_u8(), Ordering::Release);
+    }
+
+    /// Returns the store being migrated from.
+    pub fn old_store(&self) -> &O {
+        &self.old
+    }
+
+    /// Returns the store being migrated to.
+    pub fn new_store(&self) -> &N {
+        &self.new
+    }
+
+    /// Unwraps the new store once the migration is complete.
+    pub fn into_new_store(self) -> N {
+        self.new
+    }
+
+    /// Copies the sessions matching `filter`, or every session when `None`, that the new store
+    /// does not hold yet.
+    ///
+    /// Sessions already in the new store are left alone, so the backfill can be re-run after an
+    /// interruption. In [`MigrationPhase::NewWrites`] copied sessions are removed from the old
+    /// store.
+    pub fn backfill(&self, filter: Option<&SessionFilter>) -> SessionResult<ImportReport> {
+        let mut report = ImportReport::default();
+        if self.phase() == MigrationPhase::NewOnly {
+            return Ok(report);
+        }
+        let filters = match filter {
+            Some(filter) => vec![filter.clone()],
+            None => self
+                .old
+                .list_tenants()?
+                .into_iter()
+                .map(|(env, tenant)| SessionFilter::tenant(env, tenant))
+                .collect(),
+        };
+        for filter in filters {
+            let mut cursor: Option<String> = None;
+            loop {
+                let page =
+                    self.old
+                        .list_sessions(&filter, cursor.as_deref(), BACKFILL_PAGE_SIZE)?;
+                for listing in page.sessions {
+                    let key = listing.key.clone();
+                    restore_listing(&self.new, listing, &mut report)?;
+                    if self.phase() == MigrationPhase::NewWrites {
+                        ignore_missing(self.old.remove_session(&key))?;
+                    }
+                }
+                cursor = page.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Runs [`MigratingSessionStore::backfill`] on a background thread.
+    pub fn spawn_backfill(
+        self: &Arc<Self>,
+        filter: Option<SessionFilter>,
+    ) -> JoinHandle<SessionResult<ImportReport>> {
+        let store = Arc::clone(self);
+        thread::spawn(move || store.backfill(filter.as_ref()))
+    }
+
+    /// Whether the old store still has to be consulted.
+    fn reads_old(&self) -> bool {
+        self.phase() != MigrationPhase::NewOnly
+    }
+
+    /// Store holding the authoritative copy of `key`.
+    fn source(&self, key: &SessionKey) -> SessionResult<&dyn SessionStore> {
+        if self.reads_old() && self.new.get_session(key)?.is_none() {
+            return Ok(&self.old);
+        }
+        Ok(&self.new)
+    }
+
+    /// Copies `key` and its waits from the old store unless the new store already holds it.
+    fn promote(&self, key: &SessionKey) -> SessionResult<()> {
+        if !self.reads_old() || self.new.get_session(key)?.is_some() {
+            return Ok(());
+        }
+        let Some(data) = self.old.get_session(key)? else {
+            return Ok(());
+        };
+        let listing = SessionListing {
+            key: key.clone(),
+            data,
+            waits: self.old.list_session_waits(key)?,
+        };
+        restore_listing(&self.new, listing, &mut ImportReport::default())
+    }
+
+    /// Applies a write that succeeded on the new store to the old one: mirrored while dual
+    /// writing, or by retiring the old copy afterwards.
+    fn mirror(
+        &self,
+        key: &SessionKey,
+        write: impl FnOnce(&O) -> SessionResult<()>,
+    ) -> SessionResult<()> {
+        match self.phase() {
+            MigrationPhase::DualWrite => ignore_missing(write(&self.old)),
+            MigrationPhase::NewWrites => ignore_missing(self.old.remove_session(key)),
+            MigrationPhase::NewOnly => Ok(()),
+        }
+    }
+
+    fn mirror_current(&self, key: &SessionKey) -> SessionResult<()> {
+        if self.phase() != MigrationPhase::DualWrite {
+            return self.mirror(key, |_| Ok(()));
+        }
+        match self.new.get_session(key)? {
+            Some(data) => self.mirror(key, |old| old.update_session(key, data)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<O: SessionStore, N: SessionStore> SessionStore for MigratingSessionStore<O, N> {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        let key = if self.phase() == MigrationPhase::DualWrite {
+            let key = self.new.create_session(ctx, data.clone())?;
+            self.old.insert_session(ctx, &key, data)?;
+            key
+        } else {
+            self.new.create_session(ctx, data)?
+        };
+        Ok(key)
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        match self.new.get_session(key)? {
+            Some(data) => Ok(Some(data)),
+            None if self.reads_old() => self.old.get_session(key),
+            None => Ok(None),
+        }
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        if self.reads_old() && self.old.get_session(key)?.is_some() {
+            return Err(session_exists(key));
+        }
+        self.new.insert_session(ctx, key, data.clone())?;
+        if self.phase() == MigrationPhase::DualWrite {
+            self.old.insert_session(ctx, key, data)?;
+        }
+        Ok(())
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.promote(key)?;
+        self.new.update_session(key, data.clone())?;
+        self.mirror(key, |old| old.update_session(key, data))
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        if !self.reads_old() {
+            return self.new.remove_session(key);
+        }
+        let removed_new = ignore_missing_flag(self.new.remove_session(key))?;
+        let removed_old = ignore_missing_flag(self.old.remove_session(key))?;
+        if removed_new || removed_old {
+            Ok(())
+        } else {
+            Err(not_found(key))
+        }
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        let mut report = self.new.migrate_all()?;
+        if self.reads_old() {
+            let old = self.old.migrate_all()?;
+            report.scanned += old.scanned;
+            report.migrated += old.migrated;
+        }
+        Ok(report)
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        self.promote(key)?;
+        let data = self.new.patch_session(key, patch)?;
+        self.mirror(key, |old| old.update_session(key, data.clone()))?;
+        Ok(data)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        self.source(key)?.list_session_versions(key)
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        self.source(key)?.get_session_version(key, version)
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        if self.reads_old() && self.new.get_session(key)?.is_none() {
+            // The archived versions only exist in the old store until the session is copied.
+            self.old.rollback_session(key, version)?;
+            self.promote(key)?;
+            return self.mirror(key, |_| Ok(()));
+        }
+        self.new.rollback_session(key, version)?;
+        self.mirror_current(key)
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        let created = self
+            .new
+            .register_waits(ctx, user_id, session_key, data.clone(), waits)?;
+        self.mirror(session_key, |old| {
+            old.register_waits(ctx, user_id, session_key, data, waits)
+                .map(drop)
+        })?;
+        Ok(created)
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        self.source(key)?.list_session_waits(key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        self.promote(key)?;
+        let cleared = self.new.clear_session_wait(key, name)?;
+        self.mirror(key, |old| old.clear_session_wait(key, name).map(drop))?;
+        Ok(cleared)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        self.promote(key)?;
+        let cleared = self.new.clear_session_waits(key)?;
+        self.mirror(key, |old| old.clear_session_waits(key).map(drop))?;
+        Ok(cleared)
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        match self.new.find_wait_by_scope(ctx, user_id, scope)? {
+            Some(key) => Ok(Some(key)),
+            None if self.reads_old() => self.old.find_wait_by_scope(ctx, user_id, scope),
+            None => Ok(None),
+        }
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        let mut keys = self.new.list_waits_for_user(ctx, user_id)?;
+        if self.reads_old() {
+            for key in self.old.list_waits_for_user(ctx, user_id)? {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        let removed = self.new.clear_wait(ctx, user_id, scope)?;
+        if self.reads_old() {
+            let removed_old = self.old.clear_wait(ctx, user_id, scope)?;
+            return Ok(removed.or(removed_old));
+        }
+        Ok(removed)
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        if !self.reads_old() {
+            return self.new.list_sessions(filter, cursor, limit);
+        }
+        let old = self.old.list_sessions(filter, cursor, limit)?;
+        let new = self.new.list_sessions(filter, cursor, limit)?;
+        // Keys past the end of a truncated page may still be missing from the other store's
+        // page, so the merged page stops at the earliest truncation point.
+        let bound = [&old, &new]
+            .into_iter()
+            .filter(|page| page.next_cursor.is_some())
+            .filter_map(|page| page.sessions.last())
+            .map(|listing| listing.key.as_str().to_string())
+            .min();
+        let mut merged = BTreeMap::new();
+        for listing in old.sessions.into_iter().chain(new.sessions) {
+            merged.insert(listing.key.as_str().to_string(), listing);
+        }
+        let mut page = SessionPage::default();
+        for (key, listing) in merged {
+            if bound.as_ref().is_some_and(|bound| key > *bound) {
+                break;
+            }
+            if page.sessions.len() == limit {
+                break;
+            }
+            page.sessions.push(listing);
+        }
+        if bound.is_some() || page.sessions.len() == limit {
+            page.next_cursor = page
+                .sessions
+                .last()
+                .map(|listing| listing.key.as_str().to_string());
+        }
+        Ok(page)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        let mut tenants: BTreeSet<_> = self.new.list_tenants()?.into_iter().collect();
+        if self.reads_old() {
+            tenants.extend(self.old.list_tenants()?);
+        }
+        Ok(tenants.into_iter().collect())
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let mut report = self.new.purge_tenant(env, tenant)?;
+        if self.reads_old() {
+            add_purge(&mut report, self.old.purge_tenant(env, tenant)?);
+        }
+        Ok(report)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let mut report = self.new.purge_user(env, tenant, user)?;
+        if self.reads_old() {
+            add_purge(&mut report, self.old.purge_user(env, tenant, user)?);
+        }
+        Ok(report)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        let mut keys = self.new.purge_expired()?;
+        if self.reads_old() {
+            for key in self.old.purge_expired()? {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        let mut report = self.new.verify_and_repair(repair)?;
+        if self.reads_old() {
+            let old = self.old.verify_and_repair(repair)?;
+            report.sessions += old.sessions;
+            report.scope_pointers += old.scope_pointers;
+            report.user_wait_members += old.user_wait_members;
+            report.dangling_scopes.extend(old.dangling_scopes);
+            report.orphaned_user_waits.extend(old.orphaned_user_waits);
+            report.unindexed_waits.extend(old.unindexed_waits);
+        }
+        Ok(report)
+    }
+
+    /// Reports the worse of both stores while the old one is still consulted.
+    fn health(&self) -> StoreHealth {
+        let new = self.new.health();
+        if !self.reads_old() {
+            return new;
+        }
+        let old = self.old.health();
+        let mut health = StoreHealth {
+            status: new.status.max(old.status),
+            backend: format!("migrating({} -> {})", old.backend, new.backend),
+            latency: new.latency.max(old.latency),
+            reasons: Vec::new(),
+            ..new.clone()
+        };
+        for (label, store) in [("old", &old), ("new", &new)] {
+            health.reasons.extend(
+                store
+                    .reasons
+                    .iter()
+                    .map(|reason| format!("{label}: {reason}")),
+            );
+        }
+        health
+    }
+
+    /// Sums both stores while the old one is still consulted, so sessions mirrored to both are
+    /// counted twice, matching the storage they occupy.
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        let mut stats = self.new.stats(filter)?;
+        if self.reads_old() {
+            add_stats(&mut stats, self.old.stats(filter)?);
+        }
+        Ok(stats)
+    }
+
+    /// Also records the event in the old store during [`MigrationPhase::DualWrite`], so
+    /// deliveries remembered there before the migration are still caught.
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        let new = self.new.record_event_once(ctx, event_id, ttl)?;
+        if self.phase() != MigrationPhase::DualWrite {
+            return Ok(new);
+        }
+        let old = self.old.record_event_once(ctx, event_id, ttl)?;
+        Ok(match (new, old) {
+            (EventDedup::FirstSeen, old) => old,
+            (EventDedup::Duplicate { session: None }, EventDedup::Duplicate { session }) => {
+                EventDedup::Duplicate { session }
+            }
+            (new, _) => new,
+        })
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        let bound = self.new.bind_event_session(ctx, event_id, key)?;
+        if self.phase() != MigrationPhase::DualWrite {
+            return Ok(bound);
+        }
+        Ok(self.old.bind_event_session(ctx, event_id, key)? || bound)
+    }
+
+    /// Queues on the new store, promoting the session first; inboxes are not mirrored, so a
+    /// message is never delivered by both stores.
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        self.promote(key)?;
+        self.new.inbox_push(key, payload, ttl)
+    }
+
+    /// Serves an inbox left in the old store first, as its messages are older.
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        if self.reads_old()
+            && let Some(message) = self.old.inbox_peek(key)?
+        {
+            return Ok(Some(message));
+        }
+        self.new.inbox_peek(key)
+    }
+
+    /// Drains an inbox left in the old store first, as its messages are older.
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        if self.reads_old()
+            && let Some(message) = self.old.inbox_pop(key)?
+        {
+            return Ok(Some(message));
+        }
+        self.new.inbox_pop(key)
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        let mut len = self.new.inbox_len(key)?;
+        if self.reads_old() {
+            len += self.old.inbox_len(key)?;
+        }
+        Ok(len)
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        match self.new.find_by_user(ctx, user)? {
+            Some(found) => Ok(Some(found)),
+            None if self.reads_old() => self.old.find_by_user(ctx, user),
+            None => Ok(None),
+        }
+    }
+}
+
+fn add_purge(total: &mut PurgeReport, other: PurgeReport) {
+    total.sessions += other.sessions;
+    total.scope_pointers += other.scope_pointers;
+    total.user_wait_sets += other.user_wait_sets;
+}
+
+fn add_stats(total: &mut SessionStats, other: SessionStats) {
+    total.sessions += other.sessions;
+    total.waits += other.waits;
+    total.scope_pointers += other.scope_pointers;
+    total.payload_bytes += other.payload_bytes;
+    total.ttl.under_minute += other.ttl.under_minute;
+    total.ttl.under_hour += other.ttl.under_hour;
+    total.ttl.under_day += other.ttl.under_day;
+    total.ttl.day_or_more += other.ttl.day_or_more;
+    total.ttl.no_expiry += other.ttl.no_expiry;
+}
+
+/// Treats a `NotFound` from a store that may not hold the session as success.
+fn ignore_missing(result: SessionResult<()>) -> SessionResult<()> {
+    ignore_missing_flag(result).map(drop)
+}
+
+/// Like [`ignore_missing`], reporting whether the session was there.
+fn ignore_missing_flag(result: SessionResult<()>) -> SessionResult<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(err) if err.code == ErrorCode::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
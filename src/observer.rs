@@ -0,0 +1,335 @@
+use crate::ReplyScope;
+use crate::dedup::EventDedup;
+use crate::error::SessionResult;
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::InboxMessage;
+use crate::listing::{SessionFilter, SessionPage};
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::IndexRepairReport;
+use crate::stats::SessionStats;
+use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec};
+use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receives session lifecycle events from an [`ObservedSessionStore`].
+///
+/// Every method defaults to a no-op so observers only implement the events they care about.
+/// Events fire after the wrapped backend reported success, on the calling thread; observers
+/// should hand slow work off rather than block the store operation.
+pub trait SessionObserver: Send + Sync + 'static {
+    /// A session was created.
+    fn on_created(&self, _key: &SessionKey, _data: &SessionData) {}
+
+    /// The payload of a session was replaced, either directly or while registering waits.
+    fn on_updated(&self, _key: &SessionKey, _data: &SessionData) {}
+
+    /// A session was removed together with its waits.
+    fn on_removed(&self, _key: &SessionKey) {}
+
+    /// Waits were registered on a session, replacing any it held before.
+    fn on_wait_registered(&self, _key: &SessionKey, _waits: &[WaitSpec]) {}
+
+    /// Named waits were cleared from a session that is kept.
+    fn on_wait_cleared(&self, _key: &SessionKey, _names: &[String]) {}
+
+    /// A session was reclaimed by [`SessionStore::purge_expired`] after its lifetime elapsed.
+    ///
+    /// Only fires during those sweeps. Sessions that a read finds expired are dropped lazily by
+    /// the backend without passing through the wrapper, so run `purge_expired` periodically when
+    /// every expiry must be observed.
+    fn on_expired(&self, _key: &SessionKey) {}
+
+    /// Sessions of a tenant, or of one user when `user` is set, were purged.
+    fn on_purged(
+        &self,
+        _env: &EnvId,
+        _tenant: &TenantId,
+        _user: Option<&UserId>,
+        _report: &PurgeReport,
+    ) {
+    }
+}
+
+/// [`SessionStore`] wrapper notifying [`SessionObserver`]s about every successful change made
+/// through it.
+///
+/// Works with any backend, including the boxed stores returned by
+/// [`crate::create_session_store`]. Reads are forwarded untouched.
+pub struct ObservedSessionStore<S> {
+    inner: S,
+    observers: Vec<Arc<dyn SessionObserver>>,
+}
+
+impl<S: SessionStore> ObservedSessionStore<S> {
+    /// Wraps `inner` without any observers attached.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Attaches an observer; observers are notified in the order they were added.
+    pub fn with_observer(mut self, observer: impl SessionObserver) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Attaches an observer that is shared with other owners.
+    pub fn with_shared_observer(mut self, observer: Arc<dyn SessionObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwraps the store, dropping the observers.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn notify(&self, event: impl Fn(&dyn SessionObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
+}
+
+impl<S: SessionStore> SessionStore for ObservedSessionStore<S> {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        let key = self.inner.create_session(ctx, data.clone())?;
+        self.notify(|observer| observer.on_created(&key, &data));
+        Ok(key)
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.inner.insert_session(ctx, key, data.clone())?;
+        self.notify(|observer| observer.on_created(key, &data));
+        Ok(())
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        self.inner.get_session(key)
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.inner.update_session(key, data.clone())?;
+        self.notify(|observer| observer.on_updated(key, &data));
+        Ok(())
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        self.inner.remove_session(key)?;
+        self.notify(|observer| observer.on_removed(key));
+        Ok(())
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        self.inner.migrate_all()
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        let data = self.inner.patch_session(key, patch)?;
+        self.notify(|observer| observer.on_updated(key, &data));
+        Ok(data)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        self.inner.list_session_versions(key)
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        self.inner.get_session_version(key, version)
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        self.inner.rollback_session(key, version)?;
+        if let Some(data) = self.inner.get_session(key)? {
+            self.notify(|observer| observer.on_updated(key, &data));
+        }
+        Ok(())
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        let created = self
+            .inner
+            .register_waits(ctx, user_id, session_key, data.clone(), waits)?;
+        self.notify(|observer| {
+            if created {
+                observer.on_created(session_key, &data);
+            } else {
+                observer.on_updated(session_key, &data);
+            }
+            observer.on_wait_registered(session_key, waits);
+        });
+        Ok(created)
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        self.inner.list_session_waits(key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        let cleared = self.inner.clear_session_wait(key, name)?;
+        if cleared {
+            let names = [name.to_string()];
+            self.notify(|observer| observer.on_wait_cleared(key, &names));
+        }
+        Ok(cleared)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        let cleared = self.inner.clear_session_waits(key)?;
+        if !cleared.is_empty() {
+            self.notify(|observer| observer.on_wait_cleared(key, &cleared));
+        }
+        Ok(cleared)
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.inner.find_wait_by_scope(ctx, user_id, scope)
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        self.inner.list_waits_for_user(ctx, user_id)
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        let removed = self.inner.clear_wait(ctx, user_id, scope)?;
+        if let Some(key) = &removed {
+            self.notify(|observer| observer.on_removed(key));
+        }
+        Ok(removed)
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        self.inner.list_sessions(filter, cursor, limit)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        self.inner.list_tenants()
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let report = self.inner.purge_tenant(env, tenant)?;
+        self.notify(|observer| observer.on_purged(env, tenant, None, &report));
+        Ok(report)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let report = self.inner.purge_user(env, tenant, user)?;
+        self.notify(|observer| observer.on_purged(env, tenant, Some(user), &report));
+        Ok(report)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        let expired = self.inner.purge_expired()?;
+        for key in &expired {
+            self.notify(|observer| observer.on_expired(key));
+        }
+        Ok(expired)
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        self.inner.verify_and_repair(repair)
+    }
+
+    fn health(&self) -> StoreHealth {
+        self.inner.health()
+    }
+
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        self.inner.stats(filter)
+    }
+
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        self.inner.record_event_once(ctx, event_id, ttl)
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        self.inner.bind_event_session(ctx, event_id, key)
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        self.inner.inbox_push(key, payload, ttl)
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_peek(key)
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_pop(key)
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        self.inner.inbox_len(key)
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        self.inner.find_by_user(ctx, user)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::codec::{JsonCodec, SessionCodec, decode_tagged, encode_tagged};
+use crate::error::SessionResult;
+use crate::fence::TenantFence;
+use crate::format::MigrationRegistry;
+use crate::history::HistoryPolicy;
+use crate::payload::PayloadLimit;
+use crate::quota::QuotaPolicy;
+use greentic_types::{SessionData, SessionKey, TenantCtx};
+use std::sync::Arc;
+
+/// Behavioural options shared by every backend.
+#[derive(Clone, Debug)]
+pub struct SessionStoreOptions {
+    /// Per-tenant quotas enforced on writes.
+    pub quotas: QuotaPolicy,
+    /// Store-wide ceiling on the serialized payload size, checked before every write.
+    pub payload_limit: Option<PayloadLimit>,
+    /// Snapshot history kept for rollbacks; disabled when `None`.
+    pub history: Option<HistoryPolicy>,
+    /// Upgrade steps applied to persisted payloads written in older format versions.
+    pub migrations: MigrationRegistry,
+    /// Codec used to write persisted payloads; payloads from any built-in codec stay readable.
+    pub codec: Arc<dyn SessionCodec>,
+    /// Rules comparing caller contexts against stored sessions.
+    pub fence: TenantFence,
+}
+
+impl Default for SessionStoreOptions {
+    fn default() -> Self {
+        Self {
+            quotas: QuotaPolicy::default(),
+            payload_limit: None,
+            history: None,
+            migrations: MigrationRegistry::default(),
+            codec: Arc::new(JsonCodec),
+            fence: TenantFence::default(),
+        }
+    }
+}
+
+impl SessionStoreOptions {
+    /// Sets the quota policy.
+    pub fn with_quotas(mut self, quotas: QuotaPolicy) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Sets the store-wide payload size ceiling.
+    pub fn with_payload_limit(mut self, limit: PayloadLimit) -> Self {
+        self.payload_limit = Some(limit);
+        self
+    }
+
+    /// Enables bounded snapshot history.
+    pub fn with_history(mut self, history: HistoryPolicy) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Sets the migrations used to read older persisted payloads.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Sets the codec used to write persisted payloads.
+    pub fn with_codec(mut self, codec: impl SessionCodec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Sets the tenant fence policy.
+    pub fn with_fence(mut self, fence: TenantFence) -> Self {
+        self.fence = fence;
+        self
+    }
+
+    /// Serializes `data` the way persistent backends store it: tagged with the configured codec
+    /// and wrapped in the current format envelope.
+    pub fn encode_payload(&self, data: &SessionData) -> SessionResult<Vec<u8>> {
+        encode_tagged(self.codec.as_ref(), &self.migrations.envelope(data)?)
+    }
+
+    /// Deserializes a stored payload written with any available codec and format version.
+    pub fn decode_payload(&self, bytes: &[u8]) -> SessionResult<SessionData> {
+        self.decode_versioned_payload(bytes).map(|(_, data)| data)
+    }
+
+    /// Deserializes a stored payload and reports the format version it was stored with.
+    pub(crate) fn decode_versioned_payload(
+        &self,
+        bytes: &[u8],
+    ) -> SessionResult<(u32, SessionData)> {
+        self.migrations
+            .open(decode_tagged(self.codec.as_ref(), bytes)?)
+    }
+
+    /// Returns the history policy when it keeps at least one version.
+    pub(crate) fn history(&self) -> Option<&HistoryPolicy> {
+        self.history.as_ref().filter(|policy| policy.depth > 0)
+    }
+
+    /// Checks a serialized payload of `bytes` against the payload ceiling and tenant quota.
+    pub(crate) fn check_payload(
+        &self,
+        key: &SessionKey,
+        ctx: &TenantCtx,
+        bytes: usize,
+    ) -> SessionResult<()> {
+        if let Some(limit) = &self.payload_limit {
+            limit.check(key, ctx, bytes)?;
+        }
+        self.quotas.ensure_payload_size(&ctx.tenant_id, bytes)
+    }
+
+    /// Returns the size up to which a payload passes [`Self::check_payload`] for any tenant, or
+    /// `None` when no limit applies.
+    #[cfg(feature = "redis")]
+    pub(crate) fn payload_ceiling(&self) -> Option<usize> {
+        let store = self.payload_limit.as_ref().map(PayloadLimit::max_bytes);
+        store
+            .into_iter()
+            .chain(self.quotas.min_payload_bytes())
+            .min()
+    }
+
+    /// Returns `true` when writes need the serialized payload size.
+    pub(crate) fn measures_payload(&self, ctx: &TenantCtx) -> bool {
+        self.payload_limit.is_some()
+            || self
+                .quotas
+                .limits_for(&ctx.tenant_id)
+                .max_payload_bytes
+                .is_some()
+    }
+}
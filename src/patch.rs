@@ -0,0 +1,92 @@
+use crate::error::{SessionResult, invalid_argument, patch_test_failed};
+use greentic_types::{SessionCursor, SessionData};
+use json_patch::PatchErrorKind;
+use serde_json::Value;
+
+pub use json_patch::Patch as JsonPatch;
+
+/// Change applied to a session's `context_json` by [`crate::SessionStore::patch_session`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContextPatch {
+    /// RFC 7396 JSON merge patch.
+    Merge(Value),
+    /// RFC 6902 JSON patch.
+    Json(JsonPatch),
+}
+
+/// Partial session update: a context patch and/or a new cursor.
+///
+/// The tenant context, flow and pack of the session are never changed by a patch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionPatch {
+    /// Patch applied to `context_json`; `None` leaves the context untouched.
+    pub context: Option<ContextPatch>,
+    /// Cursor replacing the stored one; `None` keeps the current cursor.
+    pub cursor: Option<SessionCursor>,
+}
+
+impl SessionPatch {
+    /// Patches the context with an RFC 7396 merge patch.
+    pub fn merge(patch: Value) -> Self {
+        Self {
+            context: Some(ContextPatch::Merge(patch)),
+            cursor: None,
+        }
+    }
+
+    /// Patches the context with RFC 6902 operations.
+    pub fn json_patch(patch: JsonPatch) -> Self {
+        Self {
+            context: Some(ContextPatch::Json(patch)),
+            cursor: None,
+        }
+    }
+
+    /// Moves the session to `cursor`, with or without a context patch.
+    pub fn with_cursor(mut self, cursor: SessionCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Returns a short description used in logs and audit records.
+    pub(crate) fn describe(&self) -> &'static str {
+        match (&self.context, self.cursor.is_some()) {
+            (Some(ContextPatch::Merge(_)), false) => "merge",
+            (Some(ContextPatch::Merge(_)), true) => "merge+cursor",
+            (Some(ContextPatch::Json(_)), false) => "json",
+            (Some(ContextPatch::Json(_)), true) => "json+cursor",
+            (None, _) => "cursor",
+        }
+    }
+
+    /// Applies the patch to a copy of `data`.
+    ///
+    /// A failed RFC 6902 `test` operation reports `Conflict`; any other failure reports
+    /// `InvalidInput`.
+    pub(crate) fn apply(&self, data: &SessionData) -> SessionResult<SessionData> {
+        let mut patched = data.clone();
+        if let Some(context) = &self.context {
+            let mut doc: Value = if data.context_json.trim().is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_str(&data.context_json).map_err(|err| {
+                    invalid_argument(format!("stored context_json is not valid JSON: {err}"))
+                })?
+            };
+            match context {
+                ContextPatch::Merge(patch) => json_patch::merge(&mut doc, patch),
+                ContextPatch::Json(patch) => {
+                    json_patch::patch(&mut doc, patch).map_err(|err| match err.kind {
+                        PatchErrorKind::TestFailed => patch_test_failed(&err),
+                        _ => invalid_argument(format!("json patch rejected: {err}")),
+                    })?
+                }
+            }
+            patched.context_json = doc.to_string();
+        }
+        if let Some(cursor) = &self.cursor {
+            patched.cursor = cursor.clone();
+        }
+        Ok(patched)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::{SessionResult, payload_too_large};
+use greentic_types::{SessionKey, TenantCtx};
+use std::fmt;
+use std::sync::Arc;
+
+/// Payload about to be written while close to the configured [`PayloadLimit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayloadSizeWarning {
+    /// Session being written.
+    pub key: SessionKey,
+    /// Tenant context stored with the session.
+    pub tenant_ctx: TenantCtx,
+    /// Serialized size of the payload, in bytes.
+    pub bytes: usize,
+    /// Maximum allowed size, in bytes.
+    pub max_bytes: usize,
+}
+
+type WarningHook = Arc<dyn Fn(&PayloadSizeWarning) + Send + Sync>;
+
+/// Store-wide ceiling on the serialized size of a session payload.
+///
+/// Every backend checks the limit before writing; oversized payloads are rejected with
+/// [`crate::ErrorCode::InvalidInput`] and a [`crate::SessionErrorDetail::PayloadTooLarge`] detail
+/// stating the actual and allowed size. Payloads at or above the
+/// warning threshold are still written but reported to the optional hook.
+#[derive(Clone)]
+pub struct PayloadLimit {
+    max_bytes: usize,
+    warn_bytes: usize,
+    hook: Option<WarningHook>,
+}
+
+impl PayloadLimit {
+    /// Rejects payloads larger than `max_bytes` and warns from 80% of the limit.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            warn_bytes: max_bytes - max_bytes / 5,
+            hook: None,
+        }
+    }
+
+    /// Maximum allowed size, in bytes.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Size from which the near-limit hook fires, in bytes.
+    pub fn warn_bytes(&self) -> usize {
+        self.warn_bytes
+    }
+
+    /// Overrides the size from which the near-limit hook fires.
+    pub fn with_warn_bytes(mut self, warn_bytes: usize) -> Self {
+        self.warn_bytes = warn_bytes.min(self.max_bytes);
+        self
+    }
+
+    /// Registers a hook invoked for payloads at or above the warning threshold.
+    pub fn on_near_limit(
+        mut self,
+        hook: impl Fn(&PayloadSizeWarning) + Send + Sync + 'static,
+    ) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Rejects payloads over the limit and reports those approaching it.
+    pub(crate) fn check(
+        &self,
+        key: &SessionKey,
+        ctx: &TenantCtx,
+        bytes: usize,
+    ) -> SessionResult<()> {
+        if bytes > self.max_bytes {
+            return Err(payload_too_large(key, bytes, self.max_bytes));
+        }
+        if bytes >= self.warn_bytes
+            && let Some(hook) = &self.hook
+        {
+            hook(&PayloadSizeWarning {
+                key: key.clone(),
+                tenant_ctx: ctx.clone(),
+                bytes,
+                max_bytes: self.max_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PayloadLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PayloadLimit")
+            .field("max_bytes", &self.max_bytes)
+            .field("warn_bytes", &self.warn_bytes)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
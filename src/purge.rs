@@ -0,0 +1,13 @@
+/// Outcome of [`crate::SessionStore::purge_tenant`] and [`crate::SessionStore::purge_user`].
+///
+/// Purges are idempotent: re-running an interrupted purge removes whatever is left and reports
+/// only the entries deleted by that run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PurgeReport {
+    /// Sessions deleted.
+    pub sessions: usize,
+    /// Scope pointers deleted, including dangling ones left behind by expired sessions.
+    pub scope_pointers: usize,
+    /// Per-user wait sets deleted.
+    pub user_wait_sets: usize,
+}
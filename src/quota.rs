@@ -0,0 +1,165 @@
+use crate::error::{SessionResult, quota_exceeded};
+use greentic_types::TenantId;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Per-tenant limits; `None` leaves a dimension unbounded.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionLimits {
+    /// Maximum number of live sessions a tenant may hold in one environment.
+    pub max_sessions_per_tenant: Option<usize>,
+    /// Maximum number of live waits registered for one user, counting every named wait of a
+    /// fan-out registration.
+    pub max_waits_per_user: Option<usize>,
+    /// Maximum serialized size of a single session payload, in bytes.
+    pub max_payload_bytes: Option<usize>,
+}
+
+impl SessionLimits {
+    /// Limits with every dimension unbounded.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Caps the number of live sessions per tenant.
+    pub fn with_max_sessions_per_tenant(mut self, max: usize) -> Self {
+        self.max_sessions_per_tenant = Some(max);
+        self
+    }
+
+    /// Caps the number of sessions waiting for one user.
+    pub fn with_max_waits_per_user(mut self, max: usize) -> Self {
+        self.max_waits_per_user = Some(max);
+        self
+    }
+
+    /// Caps the serialized size of a session payload.
+    pub fn with_max_payload_bytes(mut self, max: usize) -> Self {
+        self.max_payload_bytes = Some(max);
+        self
+    }
+}
+
+/// Quota dimension reported by [`crate::SessionErrorDetail::QuotaExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuotaKind {
+    /// [`SessionLimits::max_sessions_per_tenant`].
+    SessionsPerTenant,
+    /// [`SessionLimits::max_waits_per_user`].
+    WaitsPerUser,
+    /// [`SessionLimits::max_payload_bytes`].
+    PayloadBytes,
+}
+
+impl fmt::Display for QuotaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::SessionsPerTenant => "sessions per tenant",
+            Self::WaitsPerUser => "waits per user",
+            Self::PayloadBytes => "payload bytes",
+        })
+    }
+}
+
+/// Quota configuration: limits applied to every tenant plus optional per-tenant overrides.
+///
+/// Violations are reported as [`crate::ErrorCode::RateLimited`] errors whose message starts with
+/// `quota exceeded` and whose detail is [`crate::SessionErrorDetail::QuotaExceeded`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QuotaPolicy {
+    /// Limits applied to tenants without an override.
+    pub defaults: SessionLimits,
+    /// Limits replacing the defaults for specific tenants.
+    pub overrides: HashMap<TenantId, SessionLimits>,
+}
+
+impl QuotaPolicy {
+    /// Applies `defaults` to every tenant.
+    pub fn new(defaults: SessionLimits) -> Self {
+        Self {
+            defaults,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Replaces the default limits for one tenant.
+    pub fn with_tenant_limits(mut self, tenant: TenantId, limits: SessionLimits) -> Self {
+        self.overrides.insert(tenant, limits);
+        self
+    }
+
+    /// Returns the limits in force for `tenant`.
+    pub fn limits_for(&self, tenant: &TenantId) -> &SessionLimits {
+        self.overrides.get(tenant).unwrap_or(&self.defaults)
+    }
+
+    /// Returns the smallest payload quota of any tenant, which a payload fits whatever its tenant.
+    #[cfg(feature = "redis")]
+    pub(crate) fn min_payload_bytes(&self) -> Option<usize> {
+        std::iter::once(&self.defaults)
+            .chain(self.overrides.values())
+            .filter_map(|limits| limits.max_payload_bytes)
+            .min()
+    }
+
+    /// Fails when admitting one more session would exceed the tenant's session quota.
+    pub(crate) fn ensure_session_capacity(
+        &self,
+        tenant: &TenantId,
+        current: usize,
+    ) -> SessionResult<()> {
+        match self.limits_for(tenant).max_sessions_per_tenant {
+            Some(limit) if current >= limit => Err(quota_exceeded(
+                tenant,
+                QuotaKind::SessionsPerTenant,
+                limit,
+                current,
+                format!(
+                    "tenant {} already holds {current} sessions (limit {limit})",
+                    tenant.as_str()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails when `adding` more waits on top of the user's `current` waits would exceed the
+    /// user's wait quota.
+    pub(crate) fn ensure_wait_capacity(
+        &self,
+        tenant: &TenantId,
+        current: usize,
+        adding: usize,
+    ) -> SessionResult<()> {
+        match self.limits_for(tenant).max_waits_per_user {
+            Some(limit) if current.saturating_add(adding) > limit => Err(quota_exceeded(
+                tenant,
+                QuotaKind::WaitsPerUser,
+                limit,
+                current,
+                format!(
+                    "user already has {current} waits in tenant {} and cannot add {adding} (limit {limit})",
+                    tenant.as_str()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails when a payload of `bytes` exceeds the tenant's payload quota.
+    pub(crate) fn ensure_payload_size(&self, tenant: &TenantId, bytes: usize) -> SessionResult<()> {
+        match self.limits_for(tenant).max_payload_bytes {
+            Some(limit) if bytes > limit => Err(quota_exceeded(
+                tenant,
+                QuotaKind::PayloadBytes,
+                limit,
+                bytes,
+                format!(
+                    "session payload of {bytes} bytes exceeds the {limit} byte quota of tenant {}",
+                    tenant.as_str()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use greentic_types::{EnvId, SessionKey, TeamId, TenantId, UserId};
+use serde::Serialize;
+
+/// Scope pointer that no longer routes to a live wait.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DanglingScope {
+    /// Environment of the pointer.
+    pub env: EnvId,
+    /// Tenant of the pointer.
+    pub tenant: TenantId,
+    /// Team of the pointer, if any.
+    pub team: Option<TeamId>,
+    /// User the pointer routes replies for.
+    pub user: UserId,
+    /// Hash of the reply scope.
+    pub scope_hash: String,
+    /// Session the pointer still names.
+    pub session: SessionKey,
+}
+
+/// Member of a user's wait set whose session is gone or no longer waits for that user.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OrphanedUserWait {
+    /// Environment of the wait set.
+    pub env: EnvId,
+    /// Tenant of the wait set.
+    pub tenant: TenantId,
+    /// Team of the wait set, if any.
+    pub team: Option<TeamId>,
+    /// User owning the wait set.
+    pub user: UserId,
+    /// Session listed in the set.
+    pub session: SessionKey,
+}
+
+/// Wait whose routing entries are missing or point elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IndexIssue {
+    /// Session whose wait is affected.
+    pub key: SessionKey,
+    /// Name of the wait.
+    pub wait: String,
+    /// What is wrong with it.
+    pub kind: IndexIssueKind,
+}
+
+/// Kind of [`IndexIssue`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexIssueKind {
+    /// The wait's scope does not resolve to the session.
+    ScopeNotRouted,
+    /// The session is missing from its user's wait set.
+    UserNotIndexed,
+}
+
+/// Outcome of [`crate::SessionStore::verify_and_repair`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct IndexRepairReport {
+    /// Whether the problems listed were fixed.
+    pub repaired: bool,
+    /// Sessions inspected.
+    pub sessions: usize,
+    /// Scope pointers inspected.
+    pub scope_pointers: usize,
+    /// User wait set members inspected.
+    pub user_wait_members: usize,
+    /// Scope pointers to sessions that are gone or no longer wait on that scope.
+    pub dangling_scopes: Vec<DanglingScope>,
+    /// User wait set members without a matching wait.
+    pub orphaned_user_waits: Vec<OrphanedUserWait>,
+    /// Waits that scope or user lookups cannot reach.
+    pub unindexed_waits: Vec<IndexIssue>,
+}
+
+impl IndexRepairReport {
+    /// Returns `true` when no problem was found.
+    pub fn is_consistent(&self) -> bool {
+        self.dangling_scopes.is_empty()
+            && self.orphaned_user_waits.is_empty()
+            && self.unindexed_waits.is_empty()
+    }
+}
@@ -0,0 +1,523 @@
+//! Retries and a circuit breaker for flaky backends.
+//!
+//! [`ResilientSessionStore`] retries calls that fail with [`ErrorCode::Unavailable`] or
+//! [`ErrorCode::Timeout`] and, after repeated failures, fails calls fast until the backend
+//! recovers. Other errors are returned untouched and count as the backend answering.
+
+use crate::ReplyScope;
+use crate::dedup::EventDedup;
+use crate::error::{ErrorCode, GreenticError, SessionResult, circuit_open};
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::InboxMessage;
+use crate::listing::{SessionFilter, SessionPage};
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::IndexRepairReport;
+use crate::stats::SessionStats;
+use crate::store::SessionStore;
+use crate::wait::{SessionWait, WaitSpec};
+use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+use parking_lot::Mutex;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How transient failures are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per call, including the first; `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after every retry.
+    pub multiplier: f64,
+    /// Fraction of each delay, between `0.0` and `1.0`, that is randomized away.
+    pub jitter: f64,
+    /// Also retries calls whose repetition may change their outcome, such as `create_session`.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes `max_attempts` attempts per call with the default backoff.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// Never retries.
+    pub fn disabled() -> Self {
+        Self::new(1)
+    }
+
+    /// Sets the first delay and the delay ceiling.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets the factor applied to the delay after every retry.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the fraction of each delay that is randomized away.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retries non-idempotent calls as well.
+    pub fn retry_non_idempotent(mut self) -> Self {
+        self.retry_non_idempotent = true;
+        self
+    }
+
+    /// Delay before retry number `retry`, starting at 1.
+    fn delay(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let backoff = self
+            .initial_backoff
+            .mul_f64(self.multiplier.max(1.0).powi(exponent).min(1e6))
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        backoff.mul_f64(1.0 - jitter * random)
+    }
+}
+
+/// When the circuit breaker opens and how long it stays open.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    /// Consecutive transient failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How long the circuit fails calls fast before letting a probe through.
+    pub open_for: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerPolicy {
+    /// Opens after `failure_threshold` consecutive failures for `open_for`.
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_for,
+        }
+    }
+}
+
+/// State of the circuit breaker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Calls reach the backend.
+    Closed,
+    /// Calls fail fast without reaching the backend.
+    Open,
+    /// The next call probes the backend; success closes the circuit, failure reopens it.
+    HalfOpen,
+}
+
+/// Snapshot returned by [`ResilientSessionStore::circuit_health`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitHealth {
+    /// Current state.
+    pub state: CircuitState,
+    /// Transient failures since the backend last answered.
+    pub consecutive_failures: u32,
+    /// Time until an open circuit lets a probe through.
+    pub retry_in: Option<Duration>,
+    /// Message of the last transient failure, cleared once the backend answers.
+    pub last_error: Option<String>,
+}
+
+impl CircuitHealth {
+    /// Returns `true` when calls reach the backend.
+    pub fn is_healthy(&self) -> bool {
+        self.state == CircuitState::Closed
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+    last_error: Option<String>,
+}
+
+/// [`SessionStore`] wrapper retrying transient failures with exponential backoff and failing
+/// fast through a circuit breaker while the backend is down.
+///
+/// Only idempotent calls are retried unless [`RetryPolicy::retry_non_idempotent`] is set:
+/// `create_session`, `insert_session`, `update_session`, `remove_session`, `patch_session`,
+/// `rollback_session`, `register_waits`, `clear_session_wait`, `clear_session_waits`, `clear_wait`
+/// and `purge_expired` could otherwise duplicate work or report a misleading outcome when an attempt
+/// succeeded but its reply was lost. Repeated updates and registrations archive the same payload
+/// twice with history enabled, a repeated registration reports the session as already existing,
+/// and repeated clears report nothing cleared. Open circuits fail with [`ErrorCode::Unavailable`] and a
+/// [`crate::SessionErrorDetail::CircuitOpen`] detail. Retries sleep on the calling thread.
+pub struct ResilientSessionStore<S> {
+    inner: S,
+    retry: RetryPolicy,
+    breaker_policy: CircuitBreakerPolicy,
+    breaker: Mutex<Breaker>,
+}
+
+impl<S: SessionStore> ResilientSessionStore<S> {
+    /// Wraps `inner` with the default retry and circuit breaker policies.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            retry: RetryPolicy::default(),
+            breaker_policy: CircuitBreakerPolicy::default(),
+            breaker: Mutex::new(Breaker::default()),
+        }
+    }
+
+    /// Sets the retry policy.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the circuit breaker policy.
+    pub fn with_circuit_breaker(mut self, policy: CircuitBreakerPolicy) -> Self {
+        self.breaker_policy = policy;
+        self
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwraps the store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Reports the circuit breaker state.
+    pub fn circuit_health(&self) -> CircuitHealth {
+        let breaker = self.breaker.lock();
+        let remaining = breaker
+            .opened_at
+            .map(|at| self.breaker_policy.open_for.saturating_sub(at.elapsed()));
+        let state = match remaining {
+            None => CircuitState::Closed,
+            Some(remaining) if remaining > Duration::ZERO => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        };
+        CircuitHealth {
+            state,
+            consecutive_failures: breaker.consecutive_failures,
+            retry_in: remaining.filter(|_| state == CircuitState::Open),
+            last_error: breaker.last_error.clone(),
+        }
+    }
+
+    /// Closes the circuit and forgets past failures.
+    pub fn reset_circuit(&self) {
+        *self.breaker.lock() = Breaker::default();
+    }
+
+    fn idempotent<T>(&self, op: impl Fn() -> SessionResult<T>) -> SessionResult<T> {
+        self.call(self.retry.max_attempts, op)
+    }
+
+    fn once<T>(&self, op: impl Fn() -> SessionResult<T>) -> SessionResult<T> {
+        let attempts = if self.retry.retry_non_idempotent {
+            self.retry.max_attempts
+        } else {
+            1
+        };
+        self.call(attempts, op)
+    }
+
+    fn call<T>(&self, attempts: u32, op: impl Fn() -> SessionResult<T>) -> SessionResult<T> {
+        let mut attempt = 1;
+        loop {
+            self.admit()?;
+            let err = match op() {
+                Err(err) if is_transient(&err) => err,
+                result => {
+                    self.record_answer();
+                    return result;
+                }
+            };
+            self.record_failure(&err);
+            if attempt >= attempts.max(1) || self.circuit_health().state == CircuitState::Open {
+                return Err(err);
+            }
+            sleep(self.retry.delay(attempt));
+            attempt += 1;
+        }
+    }
+
+    fn admit(&self) -> SessionResult<()> {
+        let mut breaker = self.breaker.lock();
+        let Some(opened_at) = breaker.opened_at else {
+            return Ok(());
+        };
+        let remaining = self
+            .breaker_policy
+            .open_for
+            .saturating_sub(opened_at.elapsed());
+        if remaining > Duration::ZERO || breaker.probing {
+            return Err(circuit_open(remaining));
+        }
+        breaker.probing = true;
+        Ok(())
+    }
+
+    fn record_answer(&self) {
+        *self.breaker.lock() = Breaker::default();
+    }
+
+    fn record_failure(&self, err: &GreenticError) {
+        let mut breaker = self.breaker.lock();
+        breaker.consecutive_failures = breaker.consecutive_failures.saturating_add(1);
+        breaker.last_error = Some(err.message.clone());
+        if breaker.probing
+            || breaker.consecutive_failures >= self.breaker_policy.failure_threshold.max(1)
+        {
+            breaker.opened_at = Some(Instant::now());
+        }
+        breaker.probing = false;
+    }
+}
+
+fn is_transient(err: &GreenticError) -> bool {
+    matches!(err.code, ErrorCode::Unavailable | ErrorCode::Timeout)
+}
+
+impl<S: SessionStore> SessionStore for ResilientSessionStore<S> {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        self.once(|| self.inner.create_session(ctx, data.clone()))
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.once(|| self.inner.insert_session(ctx, key, data.clone()))
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        self.idempotent(|| self.inner.get_session(key))
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.once(|| self.inner.update_session(key, data.clone()))
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        self.once(|| self.inner.remove_session(key))
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        self.idempotent(|| self.inner.migrate_all())
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        self.once(|| self.inner.patch_session(key, patch))
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        self.idempotent(|| self.inner.list_session_versions(key))
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        self.idempotent(|| self.inner.get_session_version(key, version))
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        self.once(|| self.inner.rollback_session(key, version))
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        self.once(|| {
+            self.inner
+                .register_waits(ctx, user_id, session_key, data.clone(), waits)
+        })
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        self.idempotent(|| self.inner.list_session_waits(key))
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        self.once(|| self.inner.clear_session_wait(key, name))
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        self.once(|| self.inner.clear_session_waits(key))
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.idempotent(|| self.inner.find_wait_by_scope(ctx, user_id, scope))
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        self.idempotent(|| self.inner.list_waits_for_user(ctx, user_id))
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.once(|| self.inner.clear_wait(ctx, user_id, scope))
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        self.idempotent(|| self.inner.list_sessions(filter, cursor, limit))
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        self.idempotent(|| self.inner.list_tenants())
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        self.idempotent(|| self.inner.purge_tenant(env, tenant))
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        self.idempotent(|| self.inner.purge_user(env, tenant, user))
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        self.once(|| self.inner.purge_expired())
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        self.idempotent(|| self.inner.verify_and_repair(repair))
+    }
+
+    /// Reports the wrapped store's health, degraded while the circuit is not closed.
+    fn health(&self) -> StoreHealth {
+        let health = self.inner.health();
+        let circuit = self.circuit_health();
+        match circuit.state {
+            CircuitState::Closed => health,
+            CircuitState::Open => health.degrade(format!(
+                "circuit open after {} consecutive failures",
+                circuit.consecutive_failures
+            )),
+            CircuitState::HalfOpen => health.degrade("circuit half-open"),
+        }
+    }
+
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        self.idempotent(|| self.inner.stats(filter))
+    }
+
+    /// Tried once by default: a retried attempt could find the record its failed predecessor
+    /// wrote and misreport the event as a duplicate.
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        self.once(|| self.inner.record_event_once(ctx, event_id, ttl))
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        self.idempotent(|| self.inner.bind_event_session(ctx, event_id, key))
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        self.once(|| self.inner.inbox_push(key, payload, ttl))
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.idempotent(|| self.inner.inbox_peek(key))
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.once(|| self.inner.inbox_pop(key))
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        self.idempotent(|| self.inner.inbox_len(key))
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        self.idempotent(|| self.inner.find_by_user(ctx, user))
+    }
+}
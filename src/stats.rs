@@ -0,0 +1,74 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// Upper bounds of the [`TtlBuckets`] with a finite lifetime, shortest first.
+pub(crate) const TTL_BOUNDS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(24 * 60 * 60),
+];
+
+/// Sessions grouped by remaining lifetime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TtlBuckets {
+    /// Sessions expiring within a minute.
+    pub under_minute: usize,
+    /// Sessions expiring within an hour, but not a minute.
+    pub under_hour: usize,
+    /// Sessions expiring within a day, but not an hour.
+    pub under_day: usize,
+    /// Sessions expiring in a day or more.
+    pub day_or_more: usize,
+    /// Sessions without an expiry.
+    pub no_expiry: usize,
+}
+
+impl TtlBuckets {
+    /// Counts a session with `remaining` lifetime, `None` meaning it does not expire.
+    pub(crate) fn record(&mut self, remaining: Option<Duration>) {
+        let Some(remaining) = remaining else {
+            self.no_expiry += 1;
+            return;
+        };
+        let bucket = match TTL_BOUNDS.iter().position(|bound| remaining < *bound) {
+            Some(0) => &mut self.under_minute,
+            Some(1) => &mut self.under_hour,
+            Some(_) => &mut self.under_day,
+            None => &mut self.day_or_more,
+        };
+        *bucket += 1;
+    }
+}
+
+/// Outcome of [`crate::SessionStore::stats`].
+///
+/// Figures maintained incrementally by a backend are approximate: see the backend for what may
+/// drift.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SessionStats {
+    /// Live sessions.
+    pub sessions: usize,
+    /// Waits registered on those sessions.
+    pub waits: usize,
+    /// Scope pointers routing replies to those sessions.
+    pub scope_pointers: usize,
+    /// Encoded size of the session payloads, excluding history snapshots.
+    pub payload_bytes: u64,
+    /// Sessions grouped by remaining lifetime.
+    pub ttl: TtlBuckets,
+}
+
+impl SessionStats {
+    /// Counts one session; scope pointers are left to the caller.
+    pub(crate) fn record(
+        &mut self,
+        payload_bytes: usize,
+        waits: usize,
+        remaining: Option<Duration>,
+    ) {
+        self.sessions += 1;
+        self.waits += waits;
+        self.payload_bytes += payload_bytes as u64;
+        self.ttl.record(remaining);
+    }
+}
@@ -1,13 +1,31 @@
 use crate::ReplyScope;
-use crate::error::SessionResult;
-use greentic_types::{SessionData, SessionKey, TenantCtx, UserId};
-use std::time::Duration;
+use crate::dedup::EventDedup;
+use crate::error::{SessionResult, not_found, unsupported, version_not_found};
+use crate::format::MigrationReport;
+use crate::health::StoreHealth;
+use crate::history::SessionVersion;
+use crate::inbox::InboxMessage;
+use crate::listing::{SessionFilter, SessionPage};
+use crate::patch::SessionPatch;
+use crate::purge::PurgeReport;
+use crate::repair::IndexRepairReport;
+use crate::stats::SessionStats;
+use crate::wait::{DEFAULT_WAIT_NAME, SessionWait, WaitSpec};
+use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
 /// Persistent session storage interface used by Greentic runtimes.
 ///
 /// `SessionData` captures the tenant context, flow identifier, cursor, and serialized execution
 /// state snapshot for an in-flight flow. Implementations store that payload so runners can pause
 /// execution, persist the snapshot, and resume the flow consistently after new input arrives.
+///
+/// Only the methods present in 0.4.x are required. The ones added since have default bodies so
+/// existing implementations keep compiling: `patch_session` falls back to a non-atomic read,
+/// patch and update, history reads report no versions, `health` probes with a lookup, and the
+/// rest fail with [`crate::SessionErrorDetail::Unsupported`]. `register_wait` and `clear_wait`
+/// changed their return types, see the changelog.
 pub trait SessionStore: Send + Sync + 'static {
     /// Creates a new session associated with the supplied tenant context and returns its key.
     fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey>;
@@ -15,13 +33,78 @@ pub trait SessionStore: Send + Sync + 'static {
     /// Fetches the session payload for the provided key, if it exists.
     fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>>;
 
+    /// Stores a new session under a caller-chosen key, failing with `Conflict` when it is taken.
+    ///
+    /// Meant for restoring archived sessions; new sessions should use
+    /// [`SessionStore::create_session`].
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        let _ = (ctx, key, data);
+        Err(unsupported("insert_session"))
+    }
+
     /// Replaces the session payload for the provided key.
     fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()>;
 
     /// Removes the session entry and clears any lookup indices.
     fn remove_session(&self, key: &SessionKey) -> SessionResult<()>;
 
+    /// Rewrites every stored session in the current format version.
+    ///
+    /// Reads already upgrade older payloads on the fly; this makes the upgrade permanent so old
+    /// migration steps can eventually be retired.
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        Err(unsupported("migrate_all"))
+    }
+
+    /// Applies a partial update to a session atomically and returns the patched payload.
+    ///
+    /// The tenant context is preserved, so the tenant fence cannot be bypassed through a patch.
+    /// Backends may apply the patch client-side: Redis reads and writes the full payload, so a
+    /// patch saves rebuilding [`SessionData`] but not network traffic. The default reads, patches
+    /// and updates without any atomicity guarantee.
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        let current = self.get_session(key)?.ok_or_else(|| not_found(key))?;
+        let patched = patch.apply(&current)?;
+        self.update_session(key, patched.clone())?;
+        Ok(patched)
+    }
+
+    /// Lists the archived versions of a session, newest first.
+    ///
+    /// The list is empty unless history is enabled through
+    /// [`crate::SessionStoreOptions::with_history`].
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        let _ = key;
+        Ok(Vec::new())
+    }
+
+    /// Fetches the payload archived under `version`, if it is still retained.
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        let _ = (key, version);
+        Ok(None)
+    }
+
+    /// Restores the payload archived under `version`, archiving the current payload first.
+    ///
+    /// Waits and the session lifetime are left untouched.
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        Err(version_not_found(key, version))
+    }
+
     /// Registers a paused flow wait, persisting the session and routing indices.
+    ///
+    /// The wait is registered under [`DEFAULT_WAIT_NAME`] and replaces any waits the session
+    /// already holds; use [`SessionStore::register_waits`] to wait on several scopes at once.
+    /// Returns `true` when the call created the session.
     fn register_wait(
         &self,
         ctx: &TenantCtx,
@@ -30,7 +113,54 @@ pub trait SessionStore: Send + Sync + 'static {
         session_key: &SessionKey,
         data: SessionData,
         ttl: Option<Duration>,
-    ) -> SessionResult<()>;
+    ) -> SessionResult<bool> {
+        let wait = WaitSpec {
+            name: DEFAULT_WAIT_NAME.to_string(),
+            scope: scope.clone(),
+            ttl,
+        };
+        self.register_waits(ctx, user_id, session_key, data, &[wait])
+    }
+
+    /// Registers several named waits for one session, replacing any waits it already holds.
+    ///
+    /// Each wait indexes its own scope and expires independently; the session itself lives as
+    /// long as its longest wait. A scope already claimed by another session is taken over.
+    ///
+    /// Returns `true` when the call created the session and `false` when it replaced an existing
+    /// one.
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        let _ = (ctx, user_id, session_key, data, waits);
+        Err(unsupported("register_waits"))
+    }
+
+    /// Lists the live waits registered on a session.
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        let _ = key;
+        Err(unsupported("list_session_waits"))
+    }
+
+    /// Clears one named wait while keeping the session and its other waits.
+    ///
+    /// Returns `false` when the session holds no wait with that name.
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        let _ = (key, name);
+        Err(unsupported("clear_session_wait"))
+    }
+
+    /// Clears every wait registered on a session while keeping the session itself, typically
+    /// right before the runner resumes it. Returns the names of the waits cleared.
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        let _ = key;
+        Err(unsupported("clear_session_waits"))
+    }
 
     /// Finds a wait registered for the provided scope, if one exists.
     fn find_wait_by_scope(
@@ -47,13 +177,151 @@ pub trait SessionStore: Send + Sync + 'static {
         user_id: &UserId,
     ) -> SessionResult<Vec<SessionKey>>;
 
-    /// Clears a wait registration for the provided scope.
+    /// Clears the wait registered for the provided scope together with its session and any
+    /// sibling waits.
+    ///
+    /// Returns the key of the removed session, or `None` when no live wait held the scope.
     fn clear_wait(
         &self,
         ctx: &TenantCtx,
         user_id: &UserId,
         scope: &ReplyScope,
-    ) -> SessionResult<()>;
+    ) -> SessionResult<Option<SessionKey>>;
+
+    /// Lists the sessions matching `filter`, ordered by session key.
+    ///
+    /// Pass the `next_cursor` of the previous page to continue; at most `limit` sessions are
+    /// returned per page.
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        let _ = (filter, cursor, limit);
+        Err(unsupported("list_sessions"))
+    }
+
+    /// Lists the env + tenant pairs holding at least one session, sorted.
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        Err(unsupported("list_tenants"))
+    }
+
+    /// Deletes every session of the tenant together with its waits and routing indices, for
+    /// tenant offboarding.
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        let _ = (env, tenant);
+        Err(unsupported("purge_tenant"))
+    }
+
+    /// Deletes every session bound to the user across all teams of the tenant together with the
+    /// user's waits and routing indices, for erasure requests.
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        let _ = (env, tenant, user);
+        Err(unsupported("purge_user"))
+    }
+
+    /// Removes every session whose lifetime has elapsed together with its routing indices and
+    /// returns the keys of the removed sessions.
+    ///
+    /// Backends otherwise reclaim expired sessions lazily; call this periodically to bound the
+    /// state left behind and to learn which sessions expired.
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        Err(unsupported("purge_expired"))
+    }
+
+    /// Cross-checks the routing indices against the stored waits and, when `repair` is set,
+    /// fixes what does not match.
+    ///
+    /// Reports scope pointers and user wait set members that lead nowhere, and waits that scope
+    /// or user lookups cannot reach. Repairs delete the former and re-index the latter, except
+    /// waits whose scope was taken over by another session, which are dropped. The whole store
+    /// is scanned, so run it from maintenance jobs rather than request paths.
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        let _ = repair;
+        Err(unsupported("verify_and_repair"))
+    }
+
+    /// Probes the backend for liveness and readiness checks.
+    ///
+    /// Never fails: an unreachable backend is reported as [`crate::HealthStatus::Unavailable`].
+    fn health(&self) -> StoreHealth {
+        let started = Instant::now();
+        match self.get_session(&SessionKey::new(format!("health-probe-{}", Uuid::new_v4()))) {
+            Ok(_) => StoreHealth::healthy("custom").with_latency(started.elapsed()),
+            Err(err) => StoreHealth::unavailable("custom", err.message),
+        }
+    }
+
+    /// Counts the sessions matching `filter` with their waits, scope pointers, payload bytes and
+    /// remaining lifetimes, for capacity planning.
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        let _ = filter;
+        Err(unsupported("stats"))
+    }
+
+    /// Records delivery of the inbound event `event_id` for the caller's tenant and team,
+    /// remembering it for `ttl`.
+    ///
+    /// Returns [`EventDedup::Duplicate`] while an earlier delivery is remembered, so provider
+    /// retries can be dropped instead of resuming a flow twice.
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        let _ = (ctx, event_id, ttl);
+        Err(unsupported("record_event_once"))
+    }
+
+    /// Binds a recorded event to the session its first delivery resumed, so duplicates report
+    /// that session.
+    ///
+    /// Returns `false` when the event is not recorded, for example because it expired.
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        let _ = (ctx, event_id, key);
+        Err(unsupported("bind_event_session"))
+    }
+
+    /// Appends `payload` to the session's FIFO inbox, for input arriving while its flow runs, and
+    /// returns the number of queued messages.
+    ///
+    /// Fails with `NotFound` when the session does not exist. The inbox expires `ttl` after the
+    /// last push, or with the session if that comes first, and is deleted together with the
+    /// session; reading the inbox of a session that no longer exists finds it empty.
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        let _ = (key, payload, ttl);
+        Err(unsupported("inbox_push"))
+    }
+
+    /// Returns the oldest message of the session's inbox without removing it.
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let _ = key;
+        Err(unsupported("inbox_peek"))
+    }
+
+    /// Removes and returns the oldest message of the session's inbox.
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        let _ = key;
+        Err(unsupported("inbox_pop"))
+    }
+
+    /// Returns the number of messages queued in the session's inbox.
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        let _ = key;
+        Err(unsupported("inbox_len"))
+    }
 
     /// Finds the active session bound to the specified tenant + user combination.
     #[deprecated(note = "use find_wait_by_scope or list_waits_for_user instead")]
@@ -63,3 +331,200 @@ pub trait SessionStore: Send + Sync + 'static {
         user: &UserId,
     ) -> SessionResult<Option<(SessionKey, SessionData)>>;
 }
+
+impl<S: SessionStore + ?Sized> SessionStore for Box<S> {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        (**self).create_session(ctx, data)
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        (**self).get_session(key)
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        (**self).insert_session(ctx, key, data)
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        (**self).update_session(key, data)
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        (**self).remove_session(key)
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        (**self).migrate_all()
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        (**self).patch_session(key, patch)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        (**self).list_session_versions(key)
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        (**self).get_session_version(key, version)
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        (**self).rollback_session(key, version)
+    }
+
+    fn register_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+        session_key: &SessionKey,
+        data: SessionData,
+        ttl: Option<Duration>,
+    ) -> SessionResult<bool> {
+        (**self).register_wait(ctx, user_id, scope, session_key, data, ttl)
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        (**self).register_waits(ctx, user_id, session_key, data, waits)
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        (**self).list_session_waits(key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        (**self).clear_session_wait(key, name)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        (**self).clear_session_waits(key)
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        (**self).find_wait_by_scope(ctx, user_id, scope)
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        (**self).list_waits_for_user(ctx, user_id)
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        (**self).clear_wait(ctx, user_id, scope)
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        (**self).list_sessions(filter, cursor, limit)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        (**self).list_tenants()
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        (**self).purge_tenant(env, tenant)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        (**self).purge_user(env, tenant, user)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        (**self).purge_expired()
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        (**self).verify_and_repair(repair)
+    }
+
+    fn health(&self) -> StoreHealth {
+        (**self).health()
+    }
+
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        (**self).stats(filter)
+    }
+
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        (**self).record_event_once(ctx, event_id, ttl)
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        (**self).bind_event_session(ctx, event_id, key)
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        (**self).inbox_push(key, payload, ttl)
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        (**self).inbox_peek(key)
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        (**self).inbox_pop(key)
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        (**self).inbox_len(key)
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        (**self).find_by_user(ctx, user)
+    }
+}
@@ -0,0 +1,879 @@
+//! Behavioural conformance suite for [`SessionStore`] implementations.
+//!
+//! Every check works against a store the caller provides and panics with a descriptive message
+//! when the store diverges from the built-in backends, so the suite slots into a plain `#[test]`.
+//! Each check runs in a fresh tenant with fresh session keys, so one store instance can be
+//! shared between checks and with other data. The fence checks expect the default
+//! [`crate::TenantFence::strict`] policy.
+//!
+//! ```
+//! use greentic_session::inmemory::InMemorySessionStore;
+//!
+//! greentic_session::testkit::run_all(&InMemorySessionStore::new());
+//! ```
+
+use crate::dedup::EventDedup;
+use crate::error::ErrorCode;
+use crate::listing::SessionFilter;
+use crate::store::SessionStore;
+use crate::wait::{DEFAULT_WAIT_NAME, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, ReplyScope, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId,
+    UserId,
+};
+use std::thread::sleep;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Lifetime given to waits that the TTL checks let expire.
+const SHORT_TTL: Duration = Duration::from_millis(50);
+/// How long the TTL checks wait for [`SHORT_TTL`] to elapse.
+const EXPIRY_GRACE: Duration = Duration::from_millis(150);
+
+/// Runs every check of the suite against `store`.
+pub fn run_all<S: SessionStore + ?Sized>(store: &S) {
+    check_crud(store);
+    check_tenant_fence(store);
+    check_scope_routing(store);
+    check_ttl_expiry(store);
+    check_stale_index_cleanup(store);
+    check_stats(store);
+    check_event_dedup(store);
+    check_inbox(store);
+    check_health(store);
+}
+
+/// Checks creating, inserting, reading, updating and removing sessions.
+pub fn check_crud<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+
+    let key = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+    let fetched = store.get_session(&key).expect("get_session");
+    assert_eq!(
+        fetched.map(|data| data.cursor.node_pointer),
+        Some("node.start".to_string()),
+        "get_session must return the created payload"
+    );
+    let other = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+    assert_ne!(key, other, "create_session must mint distinct keys");
+
+    store
+        .update_session(&key, fx.data(&ctx, "node.next"))
+        .expect("update_session");
+    assert_eq!(
+        store
+            .get_session(&key)
+            .expect("get_session")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.next".to_string()),
+        "update_session must replace the payload"
+    );
+
+    let chosen = fx.key("inserted");
+    store
+        .insert_session(&ctx, &chosen, fx.data(&ctx, "node.restored"))
+        .expect("insert_session");
+    let err = store
+        .insert_session(&ctx, &chosen, fx.data(&ctx, "node.other"))
+        .expect_err("insert_session must refuse a taken key");
+    assert_eq!(err.code, ErrorCode::Conflict, "{}", err.message);
+    assert_eq!(
+        store
+            .get_session(&chosen)
+            .expect("get_session")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.restored".to_string()),
+        "a refused insert must leave the session untouched"
+    );
+
+    store.remove_session(&key).expect("remove_session");
+    assert!(
+        store.get_session(&key).expect("get_session").is_none(),
+        "removed sessions must not be readable"
+    );
+
+    let missing = fx.key("missing");
+    assert!(store.get_session(&missing).expect("get_session").is_none());
+    let err = store
+        .update_session(&missing, fx.data(&ctx, "node.start"))
+        .expect_err("update_session on a missing key");
+    assert_eq!(err.code, ErrorCode::NotFound, "{}", err.message);
+    let err = store
+        .remove_session(&missing)
+        .expect_err("remove_session on a missing key");
+    assert_eq!(err.code, ErrorCode::NotFound, "{}", err.message);
+}
+
+/// Checks that sessions and waits stay inside their env, tenant, team and user.
+pub fn check_tenant_fence<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let user = fx.user("user-a");
+
+    for (caller, reason) in [
+        (fx.ctx("team-b", "user-a"), "team"),
+        (fx.ctx("team-a", "user-b"), "user"),
+        (fx.other_tenant_ctx("team-a", "user-a"), "tenant"),
+    ] {
+        let err = store
+            .create_session(&caller, fx.data(&ctx, "node.start"))
+            .expect_err("create_session must reject a foreign caller");
+        assert_eq!(
+            err.code,
+            ErrorCode::InvalidInput,
+            "{reason}: {}",
+            err.message
+        );
+    }
+
+    let key = fx.key("fenced");
+    let scope = fx.scope("fenced");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            fx.data(&ctx, "node.wait"),
+            &[WaitSpec::new("reply", scope.clone())],
+        )
+        .expect("register_waits");
+
+    for (candidate, reason) in [
+        (fx.ctx("team-b", "user-a"), "team"),
+        (fx.ctx("team-a", "user-b"), "user"),
+        (fx.other_tenant_ctx("team-a", "user-a"), "tenant"),
+    ] {
+        let err = store
+            .update_session(&key, fx.data(&candidate, "node.moved"))
+            .expect_err("update_session must not move a session");
+        assert_eq!(
+            err.code,
+            ErrorCode::InvalidInput,
+            "{reason}: {}",
+            err.message
+        );
+    }
+    assert_eq!(
+        store
+            .get_session(&key)
+            .expect("get_session")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.wait".to_string()),
+        "rejected updates must leave the session untouched"
+    );
+
+    let err = store
+        .register_waits(
+            &ctx,
+            &fx.user("user-b"),
+            &fx.key("wrong-user"),
+            fx.data(&ctx, "node.wait"),
+            &[WaitSpec::new("reply", fx.scope("wrong-user"))],
+        )
+        .expect_err("register_waits must reject a user foreign to the session");
+    assert_eq!(err.code, ErrorCode::InvalidInput, "{}", err.message);
+
+    for (caller, lookup_user, reason) in [
+        (fx.ctx("team-b", "user-a"), "user-a", "team"),
+        (fx.ctx("team-a", "user-b"), "user-b", "user"),
+        (fx.other_tenant_ctx("team-a", "user-a"), "user-a", "tenant"),
+    ] {
+        let lookup_user = fx.user(lookup_user);
+        assert_eq!(
+            store
+                .find_wait_by_scope(&caller, &lookup_user, &scope)
+                .expect("find_wait_by_scope"),
+            None,
+            "scope lookups must not cross the {reason} fence"
+        );
+        assert!(
+            store
+                .list_waits_for_user(&caller, &lookup_user)
+                .expect("list_waits_for_user")
+                .is_empty(),
+            "user lookups must not cross the {reason} fence"
+        );
+    }
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope)
+            .expect("find_wait_by_scope"),
+        Some(key),
+        "cross-fence lookups must not disturb the owner's wait"
+    );
+}
+
+/// Checks routing replies to named waits, scope takeover and clearing waits.
+pub fn check_scope_routing<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let user = fx.user("user-a");
+    let (chat, email) = (fx.scope("chat"), fx.scope("email"));
+
+    let key = fx.key("routed");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            fx.data(&ctx, "node.wait"),
+            &[
+                WaitSpec::new("chat", chat.clone()),
+                WaitSpec::new("email", email.clone()),
+            ],
+        )
+        .expect("register_waits");
+    for scope in [&chat, &email] {
+        assert_eq!(
+            store
+                .find_wait_by_scope(&ctx, &user, scope)
+                .expect("find_wait_by_scope"),
+            Some(key.clone()),
+            "every wait must route its own scope"
+        );
+    }
+    assert_eq!(
+        store
+            .list_waits_for_user(&ctx, &user)
+            .expect("list_waits_for_user"),
+        vec![key.clone()],
+        "a session with several waits must be listed once for its user"
+    );
+    let mut names: Vec<String> = store
+        .list_session_waits(&key)
+        .expect("list_session_waits")
+        .into_iter()
+        .map(|wait| wait.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["chat", "email"]);
+
+    assert!(
+        store
+            .clear_session_wait(&key, "email")
+            .expect("clear_session_wait"),
+        "clear_session_wait must report the cleared wait"
+    );
+    assert!(
+        !store
+            .clear_session_wait(&key, "email")
+            .expect("clear_session_wait"),
+        "clear_session_wait must report a missing wait"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &email)
+            .expect("find_wait_by_scope"),
+        None,
+        "a cleared wait must stop routing its scope"
+    );
+
+    let rival = fx.key("rival");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &chat,
+            &rival,
+            fx.data(&ctx, "node.rival"),
+            None,
+        )
+        .expect("register_wait");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &chat)
+            .expect("find_wait_by_scope"),
+        Some(rival.clone()),
+        "registering a claimed scope must take it over"
+    );
+
+    assert_eq!(
+        store
+            .clear_session_waits(&rival)
+            .expect("clear_session_waits"),
+        [DEFAULT_WAIT_NAME]
+    );
+    assert!(
+        store.get_session(&rival).expect("get_session").is_some(),
+        "clear_session_waits must keep the session"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &chat)
+            .expect("find_wait_by_scope"),
+        None
+    );
+
+    let resumed = fx.key("resumed");
+    let created = store
+        .register_wait(
+            &ctx,
+            &user,
+            &email,
+            &resumed,
+            fx.data(&ctx, "node.resume"),
+            None,
+        )
+        .expect("register_wait");
+    assert!(created, "register_wait must report the session it created");
+    assert_eq!(
+        store.clear_wait(&ctx, &user, &email).expect("clear_wait"),
+        Some(resumed.clone()),
+        "clear_wait must report the session it removed"
+    );
+    assert_eq!(
+        store.clear_wait(&ctx, &user, &email).expect("clear_wait"),
+        None,
+        "clear_wait must report nothing once the scope is free"
+    );
+    assert!(
+        store.get_session(&resumed).expect("get_session").is_none(),
+        "clear_wait must remove the session"
+    );
+    assert!(
+        !store
+            .list_waits_for_user(&ctx, &user)
+            .expect("list_waits_for_user")
+            .contains(&resumed),
+        "clear_wait must drop the session from its user's waits"
+    );
+}
+
+/// Checks that waits and the sessions they keep alive expire with their TTL.
+pub fn check_ttl_expiry<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let user = fx.user("user-a");
+    let (short, long) = (fx.scope("short"), fx.scope("long"));
+    let mixed_short = fx.scope("mixed-short");
+
+    let expiring = fx.key("expiring");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &short,
+            &expiring,
+            fx.data(&ctx, "node.wait"),
+            Some(SHORT_TTL),
+        )
+        .expect("register_wait");
+    let mixed = fx.key("mixed");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &mixed,
+            fx.data(&ctx, "node.wait"),
+            &[
+                WaitSpec::new("short", mixed_short.clone()).with_ttl(SHORT_TTL),
+                WaitSpec::new("long", long.clone()).with_ttl(Duration::from_secs(3600)),
+            ],
+        )
+        .expect("register_waits");
+    let remaining = store
+        .list_session_waits(&expiring)
+        .expect("list_session_waits")
+        .first()
+        .and_then(|wait| wait.expires_in);
+    assert!(
+        remaining.is_some_and(|ttl| ttl <= SHORT_TTL),
+        "list_session_waits must report the remaining lifetime, got {remaining:?}"
+    );
+
+    sleep(EXPIRY_GRACE);
+
+    assert!(
+        store.get_session(&expiring).expect("get_session").is_none(),
+        "a session must expire with its last wait"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &short)
+            .expect("find_wait_by_scope"),
+        None,
+        "an expired wait must stop routing its scope"
+    );
+    assert!(
+        store.get_session(&mixed).expect("get_session").is_some(),
+        "a session must live as long as its longest wait"
+    );
+    let filter = SessionFilter::tenant(fx.env.clone(), fx.tenant.clone()).waiting_only();
+    let page = store
+        .list_sessions(&filter, None, 10)
+        .expect("list_sessions");
+    let listed: Vec<(SessionKey, Vec<String>)> = page
+        .sessions
+        .into_iter()
+        .map(|listing| {
+            let names = listing.waits.into_iter().map(|wait| wait.name).collect();
+            (listing.key, names)
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![(mixed.clone(), vec!["long".to_string()])],
+        "listings must skip expired sessions and report only live sibling waits"
+    );
+    assert_eq!(
+        store
+            .clear_wait(&ctx, &user, &mixed_short)
+            .expect("clear_wait"),
+        None,
+        "clearing an expired wait must not report its session"
+    );
+    assert!(
+        store.get_session(&mixed).expect("get_session").is_some(),
+        "clearing an expired wait must keep the session of its live sibling"
+    );
+    let names: Vec<String> = store
+        .list_session_waits(&mixed)
+        .expect("list_session_waits")
+        .into_iter()
+        .map(|wait| wait.name)
+        .collect();
+    assert_eq!(names, ["long"], "expired waits must not be listed");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &long)
+            .expect("find_wait_by_scope"),
+        Some(mixed.clone())
+    );
+    assert_eq!(
+        store
+            .list_waits_for_user(&ctx, &user)
+            .expect("list_waits_for_user"),
+        vec![mixed],
+        "user lookups must skip expired sessions"
+    );
+}
+
+/// Checks that routing entries left behind by expired or removed sessions are cleaned up
+/// instead of resolving to the wrong session.
+///
+/// `verify_and_repair` is only run to verify, and its findings outside the fixture are ignored,
+/// so other data in the store is neither repaired nor judged.
+pub fn check_stale_index_cleanup<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let user = fx.user("user-a");
+    let scope = fx.scope("reused");
+
+    let removed = fx.key("removed");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &scope,
+            &removed,
+            fx.data(&ctx, "node.wait"),
+            None,
+        )
+        .expect("register_wait");
+    store.remove_session(&removed).expect("remove_session");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope)
+            .expect("find_wait_by_scope"),
+        None,
+        "removing a session must drop its scope routing"
+    );
+    assert!(
+        store
+            .list_waits_for_user(&ctx, &user)
+            .expect("list_waits_for_user")
+            .is_empty(),
+        "removing a session must drop it from its user's waits"
+    );
+
+    let expired = fx.key("expired");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &scope,
+            &expired,
+            fx.data(&ctx, "node.wait"),
+            Some(SHORT_TTL),
+        )
+        .expect("register_wait");
+    sleep(EXPIRY_GRACE);
+    let successor = fx.key("successor");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &scope,
+            &successor,
+            fx.data(&ctx, "node.wait"),
+            None,
+        )
+        .expect("register_wait");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope)
+            .expect("find_wait_by_scope"),
+        Some(successor.clone()),
+        "a scope freed by expiry must route to its new owner"
+    );
+    assert_eq!(
+        store
+            .list_waits_for_user(&ctx, &user)
+            .expect("list_waits_for_user"),
+        vec![successor.clone()]
+    );
+
+    let recycled = fx.key("recycled");
+    let other_scope = fx.scope("recycled");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &other_scope,
+            &recycled,
+            fx.data(&ctx, "node.wait"),
+            None,
+        )
+        .expect("register_wait");
+    store.remove_session(&recycled).expect("remove_session");
+    store
+        .insert_session(&ctx, &recycled, fx.data(&ctx, "node.idle"))
+        .expect("insert_session");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &other_scope)
+            .expect("find_wait_by_scope"),
+        None,
+        "a recreated session must not inherit the routing of its predecessor"
+    );
+
+    // Repairs apply store-wide, so only verify, and only judge the fixture's own entries.
+    let report = store.verify_and_repair(false).expect("verify_and_repair");
+    let dangling: Vec<_> = report
+        .dangling_scopes
+        .iter()
+        .filter(|issue| issue.env == fx.env && issue.tenant == fx.tenant)
+        .collect();
+    let orphaned: Vec<_> = report
+        .orphaned_user_waits
+        .iter()
+        .filter(|issue| issue.env == fx.env && issue.tenant == fx.tenant)
+        .collect();
+    let unindexed: Vec<_> = report
+        .unindexed_waits
+        .iter()
+        .filter(|issue| fx.owns(&issue.key))
+        .collect();
+    assert!(
+        dangling.is_empty() && orphaned.is_empty() && unindexed.is_empty(),
+        "lookups must leave consistent indices: {dangling:?} {orphaned:?} {unindexed:?}"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope)
+            .expect("find_wait_by_scope"),
+        Some(successor),
+        "verification must keep live waits routed"
+    );
+}
+
+/// Checks that stats follow writes, waits and removals, whichever filter answers them.
+pub fn check_stats<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let user = fx.user("user-a");
+    let filter = SessionFilter::tenant(fx.env.clone(), fx.tenant.clone());
+    let by_flow = filter
+        .clone()
+        .with_flow(FlowId::try_from("flow.conformance").expect("flow id"));
+
+    let plain = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+    let waiting = fx.key("stats");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &waiting,
+            fx.data(&ctx, "node.wait"),
+            &[
+                WaitSpec::new("first", fx.scope("stats-first")).with_ttl(Duration::from_secs(30)),
+                WaitSpec::new("second", fx.scope("stats-second")).with_ttl(Duration::from_secs(30)),
+            ],
+        )
+        .expect("register_waits");
+    store
+        .clear_session_wait(&waiting, "second")
+        .expect("clear_session_wait");
+    store
+        .update_session(&plain, fx.data(&ctx, "node.next"))
+        .expect("update_session");
+    let removed = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+    store.remove_session(&removed).expect("remove_session");
+    let other = fx.other_tenant_ctx("team-a", "user-a");
+    store
+        .create_session(&other, fx.data(&other, "node.start"))
+        .expect("create_session");
+
+    let stats = store.stats(&filter).expect("stats");
+    assert_eq!(
+        stats.sessions, 2,
+        "stats must count the tenant's live sessions"
+    );
+    assert_eq!(stats.waits, 1, "stats must follow cleared waits");
+    assert_eq!(
+        stats.scope_pointers, 1,
+        "each live wait owns a scope pointer"
+    );
+    assert!(stats.payload_bytes > 0, "stats must measure payloads");
+    assert_eq!(
+        (stats.ttl.under_minute, stats.ttl.no_expiry),
+        (1, 1),
+        "stats must bucket sessions by remaining lifetime"
+    );
+    assert_eq!(
+        stats,
+        store.stats(&by_flow).expect("stats"),
+        "tenant-wide and filtered stats must agree"
+    );
+}
+
+/// Checks that inbound events are recorded once per tenant and team and report their binding.
+pub fn check_event_dedup<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let event_id = format!("event-{}", fx.run);
+    let ttl = Duration::from_secs(60);
+
+    assert_eq!(
+        store
+            .record_event_once(&ctx, &event_id, ttl)
+            .expect("record_event_once"),
+        EventDedup::FirstSeen,
+        "a new event must be reported as first seen"
+    );
+    assert_eq!(
+        store
+            .record_event_once(&ctx, &event_id, ttl)
+            .expect("record_event_once"),
+        EventDedup::Duplicate { session: None },
+        "a repeated event must be reported as a duplicate"
+    );
+    let key = fx.key("event");
+    assert!(
+        store
+            .bind_event_session(&ctx, &event_id, &key)
+            .expect("bind_event_session"),
+        "a recorded event must accept a session binding"
+    );
+    assert_eq!(
+        store
+            .record_event_once(&ctx, &event_id, ttl)
+            .expect("record_event_once")
+            .session(),
+        Some(&key),
+        "duplicates must report the bound session"
+    );
+    let other_team = fx.ctx("team-b", "user-a");
+    assert_eq!(
+        store
+            .record_event_once(&other_team, &event_id, ttl)
+            .expect("record_event_once"),
+        EventDedup::FirstSeen,
+        "events must be scoped to the team"
+    );
+
+    let short = format!("short-{}", fx.run);
+    store
+        .record_event_once(&ctx, &short, SHORT_TTL)
+        .expect("record_event_once");
+    sleep(EXPIRY_GRACE);
+    assert_eq!(
+        store
+            .record_event_once(&ctx, &short, SHORT_TTL)
+            .expect("record_event_once"),
+        EventDedup::FirstSeen,
+        "an expired event must be recorded again"
+    );
+}
+
+/// Checks that the session inbox is drained in order and removed or expired with the session.
+pub fn check_inbox<S: SessionStore + ?Sized>(store: &S) {
+    let fx = Fixture::new();
+    let ctx = fx.ctx("team-a", "user-a");
+    let ttl = Duration::from_secs(60);
+    let key = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+
+    assert_eq!(store.inbox_len(&key).expect("inbox_len"), 0);
+    assert!(store.inbox_pop(&key).expect("inbox_pop").is_none());
+    for (index, payload) in ["first", "second", "third"].into_iter().enumerate() {
+        assert_eq!(
+            store.inbox_push(&key, payload, ttl).expect("inbox_push"),
+            index + 1,
+            "inbox_push must return the new length"
+        );
+    }
+    assert_eq!(
+        store
+            .inbox_peek(&key)
+            .expect("inbox_peek")
+            .map(|message| message.payload),
+        Some("first".to_string()),
+        "inbox_peek must return the oldest message"
+    );
+    assert_eq!(store.inbox_len(&key).expect("inbox_len"), 3);
+    let drained: Vec<String> = std::iter::from_fn(|| store.inbox_pop(&key).expect("inbox_pop"))
+        .map(|message| message.payload)
+        .collect();
+    assert_eq!(drained, ["first", "second", "third"], "inbox must be FIFO");
+
+    let err = store
+        .inbox_push(&fx.key("missing"), "lost", ttl)
+        .expect_err("inbox_push must refuse a missing session");
+    assert_eq!(err.code, ErrorCode::NotFound, "{}", err.message);
+
+    store.inbox_push(&key, "pending", ttl).expect("inbox_push");
+    store.remove_session(&key).expect("remove_session");
+    assert_eq!(
+        store.inbox_len(&key).expect("inbox_len"),
+        0,
+        "removing a session must drop its inbox"
+    );
+
+    let short = store
+        .create_session(&ctx, fx.data(&ctx, "node.start"))
+        .expect("create_session");
+    store
+        .inbox_push(&short, "stale", SHORT_TTL)
+        .expect("inbox_push");
+    sleep(EXPIRY_GRACE);
+    assert!(
+        store.inbox_pop(&short).expect("inbox_pop").is_none(),
+        "an expired inbox must be empty"
+    );
+
+    let user = ctx.user_id.clone().expect("fixture user");
+    let expiring = fx.key("inbox-expiring");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &fx.scope("inbox-expiring"),
+            &expiring,
+            fx.data(&ctx, "node.wait"),
+            Some(SHORT_TTL),
+        )
+        .expect("register_wait");
+    store
+        .inbox_push(&expiring, "orphaned", ttl)
+        .expect("inbox_push");
+    sleep(EXPIRY_GRACE);
+    assert_eq!(
+        store.inbox_len(&expiring).expect("inbox_len"),
+        0,
+        "an inbox must expire with its session"
+    );
+    assert!(store.inbox_peek(&expiring).expect("inbox_peek").is_none());
+    assert!(store.inbox_pop(&expiring).expect("inbox_pop").is_none());
+    let err = store
+        .inbox_push(&expiring, "late", ttl)
+        .expect_err("inbox_push must refuse an expired session");
+    assert_eq!(err.code, ErrorCode::NotFound, "{}", err.message);
+}
+
+/// Checks that a reachable store reports itself ready.
+pub fn check_health<S: SessionStore + ?Sized>(store: &S) {
+    let health = store.health();
+    assert!(
+        health.is_ready(),
+        "a working store must be ready: {health:?}"
+    );
+    assert!(!health.backend.is_empty(), "health must name the backend");
+    assert_eq!(health.format_version, crate::CURRENT_FORMAT_VERSION);
+}
+
+/// Identifiers unique to one check run.
+struct Fixture {
+    env: EnvId,
+    tenant: TenantId,
+    other_tenant: TenantId,
+    run: String,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let run = Uuid::new_v4().simple().to_string();
+        Self {
+            env: EnvId::try_from("conformance").expect("env id"),
+            tenant: TenantId::try_from(format!("tenant-{run}").as_str()).expect("tenant id"),
+            other_tenant: TenantId::try_from(format!("other-{run}").as_str()).expect("tenant id"),
+            run,
+        }
+    }
+
+    fn user(&self, user: &str) -> UserId {
+        UserId::try_from(user).expect("user id")
+    }
+
+    fn ctx(&self, team: &str, user: &str) -> TenantCtx {
+        Self::scoped(
+            TenantCtx::new(self.env.clone(), self.tenant.clone()),
+            team,
+            user,
+        )
+    }
+
+    fn other_tenant_ctx(&self, team: &str, user: &str) -> TenantCtx {
+        Self::scoped(
+            TenantCtx::new(self.env.clone(), self.other_tenant.clone()),
+            team,
+            user,
+        )
+    }
+
+    fn scoped(ctx: TenantCtx, team: &str, user: &str) -> TenantCtx {
+        ctx.with_team(Some(TeamId::try_from(team).expect("team id")))
+            .with_user(Some(UserId::try_from(user).expect("user id")))
+    }
+
+    fn key(&self, name: &str) -> SessionKey {
+        SessionKey::new(format!("conformance-{}-{name}", self.run))
+    }
+
+    /// Whether `key` was made by [`Fixture::key`] for this run.
+    fn owns(&self, key: &SessionKey) -> bool {
+        key.as_str()
+            .strip_prefix("conformance-")
+            .and_then(|rest| rest.strip_prefix(self.run.as_str()))
+            .is_some_and(|rest| rest.starts_with('-'))
+    }
+
+    fn scope(&self, conversation: &str) -> ReplyScope {
+        ReplyScope {
+            conversation: format!("{}:{conversation}", self.run),
+            thread: None,
+            reply_to: None,
+            correlation: None,
+        }
+    }
+
+    fn data(&self, ctx: &TenantCtx, node: &str) -> SessionData {
+        SessionData {
+            tenant_ctx: ctx.clone(),
+            flow_id: FlowId::try_from("flow.conformance").expect("flow id"),
+            pack_id: None,
+            cursor: SessionCursor::new(node.to_string()),
+            context_json: "{}".into(),
+        }
+    }
+}
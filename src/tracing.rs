@@ -0,0 +1,468 @@
+//! Structured tracing emitted through the [`tracing`](https://docs.rs/tracing) facade when the
+//! `tracing` feature is enabled.
+//!
+//! [`TracedSessionStore`] runs every [`SessionStore`] call inside a `session_store` span at debug
+//! level. The span carries `operation` plus whichever of `env`, `tenant`, `team`, `user_hash`,
+//! `scope_hash` and `session_key` the call targets. User ids are never recorded verbatim: `user_hash`
+//! is a truncated HMAC-SHA256 of the id under a key that never leaves the process, so hashes cannot
+//! be reversed by brute-forcing likely ids. The key is random per store unless one is configured with
+//! [`TracedSessionStore::with_user_hash_key`], which replicas must share for their hashes to
+//! correlate. Failed calls emit a warning carrying the error code inside the span.
+//!
+//! Backends additionally emit a warning with target `greentic_session::fence` whenever the tenant
+//! fence rejects a call, independently of the wrapper.
+
+#[cfg(feature = "tracing")]
+pub use traced::TracedSessionStore;
+
+use greentic_types::TenantCtx;
+
+/// Reports a call rejected by the tenant fence of a backend.
+pub(crate) fn record_fence_rejection(expected: &TenantCtx, provided: &TenantCtx, reason: &str) {
+    #[cfg(feature = "tracing")]
+    ::tracing::warn!(
+        target: "greentic_session::fence",
+        reason,
+        expected_env = expected.env.as_str(),
+        expected_tenant = expected.tenant_id.as_str(),
+        expected_team = crate::fence::normalize_team(expected).map(|t| t.as_str()).unwrap_or("-"),
+        provided_env = provided.env.as_str(),
+        provided_tenant = provided.tenant_id.as_str(),
+        provided_team = crate::fence::normalize_team(provided).map(|t| t.as_str()).unwrap_or("-"),
+        "tenant fence rejected session store call"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (expected, provided, reason);
+}
+
+#[cfg(feature = "tracing")]
+mod traced {
+    use crate::ReplyScope;
+    use crate::dedup::EventDedup;
+    use crate::error::SessionResult;
+    use crate::fence::normalize_team;
+    use crate::format::MigrationReport;
+    use crate::health::StoreHealth;
+    use crate::history::SessionVersion;
+    use crate::inbox::InboxMessage;
+    use crate::listing::{SessionFilter, SessionPage};
+    use crate::patch::SessionPatch;
+    use crate::purge::PurgeReport;
+    use crate::repair::IndexRepairReport;
+    use crate::stats::SessionStats;
+    use crate::store::SessionStore;
+    use crate::wait::{SessionWait, WaitSpec};
+    use greentic_types::{EnvId, SessionData, SessionKey, TenantCtx, TenantId, UserId};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::time::Duration;
+    use tracing::Span;
+    use tracing::field::Empty;
+
+    /// Hex characters of the user digest kept in spans.
+    const USER_HASH_LEN: usize = 16;
+
+    fn span(operation: &'static str) -> Span {
+        ::tracing::debug_span!(
+            "session_store",
+            operation,
+            env = Empty,
+            tenant = Empty,
+            team = Empty,
+            user_hash = Empty,
+            scope_hash = Empty,
+            session_key = Empty,
+        )
+    }
+
+    fn record_key(span: &Span, key: &SessionKey) {
+        span.record("session_key", key.as_str());
+    }
+
+    fn record_scope(span: &Span, scope: &ReplyScope) {
+        span.record("scope_hash", scope.scope_hash());
+    }
+
+    /// Runs `op` inside `span`, reporting failures as a warning within the span.
+    fn run<T>(span: Span, op: impl FnOnce() -> SessionResult<T>) -> SessionResult<T> {
+        span.in_scope(|| {
+            let result = op();
+            if let Err(err) = &result {
+                ::tracing::warn!(code = ?err.code, error = %err.message, "session store call failed");
+            }
+            result
+        })
+    }
+
+    /// [`SessionStore`] wrapper running every call inside a `session_store` span.
+    pub struct TracedSessionStore<S> {
+        inner: S,
+        user_hash_key: Vec<u8>,
+    }
+
+    impl<S: SessionStore> TracedSessionStore<S> {
+        /// Wraps `inner`, hashing user ids under a random key generated for this store.
+        pub fn new(inner: S) -> Self {
+            let user_hash_key = [
+                *uuid::Uuid::new_v4().as_bytes(),
+                *uuid::Uuid::new_v4().as_bytes(),
+            ];
+            Self::with_user_hash_key(inner, user_hash_key.concat())
+        }
+
+        /// Wraps `inner`, hashing user ids under `key`.
+        ///
+        /// Stores configured with the same key record the same `user_hash` for a user, which lets
+        /// spans from several replicas be correlated. Treat the key as a secret.
+        pub fn with_user_hash_key(inner: S, key: impl Into<Vec<u8>>) -> Self {
+            Self {
+                inner,
+                user_hash_key: key.into(),
+            }
+        }
+
+        /// Returns the wrapped store.
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+
+        /// Unwraps the store.
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+
+        fn user_hash(&self, user: &UserId) -> String {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&self.user_hash_key)
+                .expect("HMAC accepts keys of any length");
+            mac.update(user.as_str().as_bytes());
+            let digest = hex::encode(mac.finalize().into_bytes());
+            digest[..USER_HASH_LEN].to_string()
+        }
+
+        fn record_ctx(&self, span: &Span, ctx: &TenantCtx) {
+            span.record("env", ctx.env.as_str());
+            span.record("tenant", ctx.tenant_id.as_str());
+            if let Some(team) = normalize_team(ctx) {
+                span.record("team", team.as_str());
+            }
+            if let Some(user) = ctx.user_id.as_ref().or(ctx.user.as_ref()) {
+                self.record_user(span, user);
+            }
+        }
+
+        fn record_user(&self, span: &Span, user: &UserId) {
+            span.record("user_hash", self.user_hash(user));
+        }
+    }
+
+    impl<S: SessionStore> SessionStore for TracedSessionStore<S> {
+        fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+            let span = span("create_session");
+            self.record_ctx(&span, ctx);
+            let key = run(span.clone(), || self.inner.create_session(ctx, data))?;
+            record_key(&span, &key);
+            Ok(key)
+        }
+
+        fn insert_session(
+            &self,
+            ctx: &TenantCtx,
+            key: &SessionKey,
+            data: SessionData,
+        ) -> SessionResult<()> {
+            let span = span("insert_session");
+            self.record_ctx(&span, ctx);
+            record_key(&span, key);
+            run(span, || self.inner.insert_session(ctx, key, data))
+        }
+
+        fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+            let span = span("get_session");
+            record_key(&span, key);
+            let data = run(span.clone(), || self.inner.get_session(key))?;
+            if let Some(data) = &data {
+                self.record_ctx(&span, &data.tenant_ctx);
+            }
+            Ok(data)
+        }
+
+        fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+            let span = span("update_session");
+            self.record_ctx(&span, &data.tenant_ctx);
+            record_key(&span, key);
+            run(span, || self.inner.update_session(key, data))
+        }
+
+        fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+            let span = span("remove_session");
+            record_key(&span, key);
+            run(span, || self.inner.remove_session(key))
+        }
+
+        fn migrate_all(&self) -> SessionResult<MigrationReport> {
+            run(span("migrate_all"), || self.inner.migrate_all())
+        }
+
+        fn patch_session(
+            &self,
+            key: &SessionKey,
+            patch: &SessionPatch,
+        ) -> SessionResult<SessionData> {
+            let span = span("patch_session");
+            record_key(&span, key);
+            let data = run(span.clone(), || self.inner.patch_session(key, patch))?;
+            self.record_ctx(&span, &data.tenant_ctx);
+            Ok(data)
+        }
+
+        fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+            let span = span("list_session_versions");
+            record_key(&span, key);
+            run(span, || self.inner.list_session_versions(key))
+        }
+
+        fn get_session_version(
+            &self,
+            key: &SessionKey,
+            version: u64,
+        ) -> SessionResult<Option<SessionData>> {
+            let span = span("get_session_version");
+            record_key(&span, key);
+            run(span, || self.inner.get_session_version(key, version))
+        }
+
+        fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+            let span = span("rollback_session");
+            record_key(&span, key);
+            run(span, || self.inner.rollback_session(key, version))
+        }
+
+        fn register_wait(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            scope: &ReplyScope,
+            session_key: &SessionKey,
+            data: SessionData,
+            ttl: Option<Duration>,
+        ) -> SessionResult<bool> {
+            let span = span("register_wait");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user_id);
+            record_scope(&span, scope);
+            record_key(&span, session_key);
+            run(span, || {
+                self.inner
+                    .register_wait(ctx, user_id, scope, session_key, data, ttl)
+            })
+        }
+
+        fn register_waits(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            session_key: &SessionKey,
+            data: SessionData,
+            waits: &[WaitSpec],
+        ) -> SessionResult<bool> {
+            let span = span("register_waits");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user_id);
+            record_key(&span, session_key);
+            let scopes: Vec<String> = waits.iter().map(|wait| wait.scope.scope_hash()).collect();
+            span.record("scope_hash", scopes.join(","));
+            run(span, || {
+                self.inner
+                    .register_waits(ctx, user_id, session_key, data, waits)
+            })
+        }
+
+        fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+            let span = span("list_session_waits");
+            record_key(&span, key);
+            run(span, || self.inner.list_session_waits(key))
+        }
+
+        fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+            let span = span("clear_session_wait");
+            record_key(&span, key);
+            run(span, || self.inner.clear_session_wait(key, name))
+        }
+
+        fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+            let span = span("clear_session_waits");
+            record_key(&span, key);
+            run(span, || self.inner.clear_session_waits(key))
+        }
+
+        fn find_wait_by_scope(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            scope: &ReplyScope,
+        ) -> SessionResult<Option<SessionKey>> {
+            let span = span("find_wait_by_scope");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user_id);
+            record_scope(&span, scope);
+            let found = run(span.clone(), || {
+                self.inner.find_wait_by_scope(ctx, user_id, scope)
+            })?;
+            if let Some(key) = &found {
+                record_key(&span, key);
+            }
+            Ok(found)
+        }
+
+        fn list_waits_for_user(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+        ) -> SessionResult<Vec<SessionKey>> {
+            let span = span("list_waits_for_user");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user_id);
+            run(span, || self.inner.list_waits_for_user(ctx, user_id))
+        }
+
+        fn clear_wait(
+            &self,
+            ctx: &TenantCtx,
+            user_id: &UserId,
+            scope: &ReplyScope,
+        ) -> SessionResult<Option<SessionKey>> {
+            let span = span("clear_wait");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user_id);
+            record_scope(&span, scope);
+            run(span, || self.inner.clear_wait(ctx, user_id, scope))
+        }
+
+        fn list_sessions(
+            &self,
+            filter: &SessionFilter,
+            cursor: Option<&str>,
+            limit: usize,
+        ) -> SessionResult<SessionPage> {
+            let span = span("list_sessions");
+            span.record("env", filter.env.as_str());
+            span.record("tenant", filter.tenant.as_str());
+            if let Some(team) = &filter.team {
+                span.record("team", team.as_str());
+            }
+            if let Some(user) = &filter.user {
+                self.record_user(&span, user);
+            }
+            run(span, || self.inner.list_sessions(filter, cursor, limit))
+        }
+
+        fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+            run(span("list_tenants"), || self.inner.list_tenants())
+        }
+
+        fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+            let span = span("purge_tenant");
+            span.record("env", env.as_str());
+            span.record("tenant", tenant.as_str());
+            run(span, || self.inner.purge_tenant(env, tenant))
+        }
+
+        fn purge_user(
+            &self,
+            env: &EnvId,
+            tenant: &TenantId,
+            user: &UserId,
+        ) -> SessionResult<PurgeReport> {
+            let span = span("purge_user");
+            span.record("env", env.as_str());
+            span.record("tenant", tenant.as_str());
+            self.record_user(&span, user);
+            run(span, || self.inner.purge_user(env, tenant, user))
+        }
+
+        fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+            run(span("purge_expired"), || self.inner.purge_expired())
+        }
+
+        fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+            run(span("verify_and_repair"), || {
+                self.inner.verify_and_repair(repair)
+            })
+        }
+
+        fn health(&self) -> StoreHealth {
+            self.inner.health()
+        }
+
+        fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+            let span = span("stats");
+            span.record("env", filter.env.as_str());
+            span.record("tenant", filter.tenant.as_str());
+            run(span, || self.inner.stats(filter))
+        }
+
+        fn record_event_once(
+            &self,
+            ctx: &TenantCtx,
+            event_id: &str,
+            ttl: Duration,
+        ) -> SessionResult<EventDedup> {
+            let span = span("record_event_once");
+            self.record_ctx(&span, ctx);
+            run(span, || self.inner.record_event_once(ctx, event_id, ttl))
+        }
+
+        fn bind_event_session(
+            &self,
+            ctx: &TenantCtx,
+            event_id: &str,
+            key: &SessionKey,
+        ) -> SessionResult<bool> {
+            let span = span("bind_event_session");
+            self.record_ctx(&span, ctx);
+            record_key(&span, key);
+            run(span, || self.inner.bind_event_session(ctx, event_id, key))
+        }
+
+        fn inbox_push(
+            &self,
+            key: &SessionKey,
+            payload: &str,
+            ttl: Duration,
+        ) -> SessionResult<usize> {
+            let span = span("inbox_push");
+            record_key(&span, key);
+            run(span, || self.inner.inbox_push(key, payload, ttl))
+        }
+
+        fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+            let span = span("inbox_peek");
+            record_key(&span, key);
+            run(span, || self.inner.inbox_peek(key))
+        }
+
+        fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+            let span = span("inbox_pop");
+            record_key(&span, key);
+            run(span, || self.inner.inbox_pop(key))
+        }
+
+        fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+            let span = span("inbox_len");
+            record_key(&span, key);
+            run(span, || self.inner.inbox_len(key))
+        }
+
+        #[allow(deprecated)]
+        fn find_by_user(
+            &self,
+            ctx: &TenantCtx,
+            user: &UserId,
+        ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+            let span = span("find_by_user");
+            self.record_ctx(&span, ctx);
+            self.record_user(&span, user);
+            run(span, || self.inner.find_by_user(ctx, user))
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::ReplyScope;
+use crate::error::{SessionResult, invalid_argument};
+use greentic_types::UserId;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Name assigned to the wait created by [`crate::SessionStore::register_wait`].
+pub const DEFAULT_WAIT_NAME: &str = "default";
+
+/// Named wait submitted to [`crate::SessionStore::register_waits`].
+///
+/// Each wait routes replies arriving on its own `scope` back to the session and expires
+/// independently after `ttl`. A session stays resumable until its last wait is cleared or expires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WaitSpec {
+    /// Name identifying the wait within its session (for example `slack` or `email`).
+    pub name: String,
+    /// Reply scope that resumes the session.
+    pub scope: ReplyScope,
+    /// Optional lifetime of the wait.
+    pub ttl: Option<Duration>,
+}
+
+impl WaitSpec {
+    /// Creates a wait without an expiry.
+    pub fn new(name: impl Into<String>, scope: ReplyScope) -> Self {
+        Self {
+            name: name.into(),
+            scope,
+            ttl: None,
+        }
+    }
+
+    /// Sets the lifetime of the wait.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// Wait currently registered on a session, as reported by
+/// [`crate::SessionStore::list_session_waits`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionWait {
+    /// Name identifying the wait within its session.
+    pub name: String,
+    /// User the wait is bound to.
+    pub user_id: UserId,
+    /// Reply scope that resumes the session.
+    pub scope: ReplyScope,
+    /// Remaining lifetime of the wait, if it expires.
+    pub expires_in: Option<Duration>,
+}
+
+pub(crate) fn validate_waits(waits: &[WaitSpec]) -> SessionResult<()> {
+    if waits.is_empty() {
+        return Err(invalid_argument("at least one wait must be registered"));
+    }
+    let mut names = HashSet::new();
+    let mut scopes = HashSet::new();
+    for wait in waits {
+        if wait.name.is_empty() {
+            return Err(invalid_argument("wait name must not be empty"));
+        }
+        if !names.insert(wait.name.as_str()) {
+            return Err(invalid_argument(format!(
+                "wait name `{}` is registered more than once",
+                wait.name
+            )));
+        }
+        if !scopes.insert(wait.scope.scope_hash()) {
+            return Err(invalid_argument(format!(
+                "wait `{}` reuses a scope already claimed by another wait of the session",
+                wait.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Lifetime of a session holding the supplied waits: it lives as long as its longest wait.
+pub(crate) fn session_ttl(waits: &[WaitSpec]) -> Option<Duration> {
+    waits
+        .iter()
+        .map(|wait| wait.ttl)
+        .try_fold(Duration::ZERO, |longest, ttl| {
+            ttl.map(|ttl| longest.max(ttl))
+        })
+}
@@ -0,0 +1,107 @@
+use greentic_session::admin::{extend_session, show_session};
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ErrorCode, ReplyScope, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::time::Duration;
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-admin").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, node: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.admin").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn seeded() -> (InMemorySessionStore, SessionKey, SessionKey) {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let idle = store
+        .create_session(&ctx, data(&ctx, "node.idle"))
+        .expect("create");
+    let waiting = SessionKey::new("waiting");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &waiting,
+            data(&ctx, "node.wait"),
+            &[
+                WaitSpec::new("approval", scope("chat-1")).with_ttl(Duration::from_secs(5)),
+                WaitSpec::new("reminder", scope("chat-2")),
+            ],
+        )
+        .expect("register");
+    (store, idle, waiting)
+}
+
+#[test]
+fn show_session_includes_waits() {
+    let (store, idle, waiting) = seeded();
+    let listing = show_session(&store, &waiting)
+        .expect("show")
+        .expect("session");
+    assert_eq!(listing.data.cursor.node_pointer, "node.wait");
+    assert_eq!(listing.waits.len(), 2);
+    assert!(
+        show_session(&store, &idle)
+            .expect("show")
+            .expect("idle")
+            .waits
+            .is_empty()
+    );
+    assert!(
+        show_session(&store, &SessionKey::new("missing"))
+            .expect("show")
+            .is_none()
+    );
+}
+
+#[test]
+fn extend_resets_every_wait_lifetime() {
+    let (store, idle, waiting) = seeded();
+    let extended = extend_session(&store, &waiting, Duration::from_secs(3600)).expect("extend");
+    assert_eq!(extended, 2);
+    let waits = store.list_session_waits(&waiting).expect("waits");
+    assert_eq!(waits.len(), 2);
+    for wait in waits {
+        let remaining = wait.expires_in.expect("ttl");
+        assert!(remaining > Duration::from_secs(3500), "{remaining:?}");
+    }
+    assert_eq!(
+        store
+            .get_session(&waiting)
+            .expect("get")
+            .expect("session")
+            .cursor
+            .node_pointer,
+        "node.wait"
+    );
+
+    let err = extend_session(&store, &idle, Duration::from_secs(60)).expect_err("idle");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    let err = extend_session(&store, &SessionKey::new("missing"), Duration::from_secs(60))
+        .expect_err("missing");
+    assert_eq!(err.code, ErrorCode::NotFound);
+}
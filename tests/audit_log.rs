@@ -0,0 +1,268 @@
+use greentic_session::SessionResult;
+use greentic_session::audit::{
+    AuditOperation, AuditRecord, AuditSink, AuditedSessionStore, GENESIS_HASH, InMemoryAuditSink,
+    JsonlAuditSink, verify_chain,
+};
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ErrorCode, ReplyScope};
+use greentic_types::{
+    EnvId, FlowId, Impersonation, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId,
+    UserId,
+};
+use std::sync::{Arc, Mutex};
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-audit").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, node: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.audit").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope() -> ReplyScope {
+    ReplyScope {
+        conversation: "chat-1".into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn run_lifecycle(store: &impl SessionStore) -> SessionKey {
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let key = store
+        .create_session(&ctx, data(&ctx, "node.start"))
+        .expect("create");
+    store
+        .update_session(&key, data(&ctx, "node.ask"))
+        .expect("update");
+    store
+        .register_wait(&ctx, &user, &scope(), &key, data(&ctx, "node.wait"), None)
+        .expect("suspend");
+    store.clear_session_waits(&key).expect("resume");
+    store.remove_session(&key).expect("remove");
+    key
+}
+
+#[test]
+fn mutations_are_recorded_in_a_verifiable_chain() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let store =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("audited store");
+    let key = run_lifecycle(&store);
+    store.get_session(&key).expect("reads are not audited");
+
+    let records = sink.read_all().expect("records");
+    let operations: Vec<AuditOperation> = records.iter().map(|record| record.operation).collect();
+    assert_eq!(
+        operations,
+        vec![
+            AuditOperation::Created,
+            AuditOperation::Updated,
+            AuditOperation::Suspended,
+            AuditOperation::Resumed,
+            AuditOperation::Deleted,
+        ]
+    );
+    assert!(records.iter().all(|record| record.actor == "user-1"));
+    assert!(
+        records
+            .iter()
+            .all(|record| record.session_key.as_ref() == Some(&key))
+    );
+    let updated = &records[1];
+    assert_eq!(
+        updated.previous_cursor, None,
+        "replacing writes are recorded without a separate read"
+    );
+    assert_eq!(
+        records[0]
+            .new_cursor
+            .as_ref()
+            .map(|c| c.node_pointer.as_str()),
+        Some("node.start"),
+        "the chain's preceding record carries the previous cursor"
+    );
+    assert_eq!(
+        updated.new_cursor.as_ref().map(|c| c.node_pointer.as_str()),
+        Some("node.ask")
+    );
+    assert_eq!(records[3].detail.as_deref(), Some("waits=default"));
+    verify_chain(&records).expect("intact chain");
+}
+
+#[test]
+fn impersonation_and_purges_are_attributed() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let store = AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone())
+        .expect("audited store")
+        .with_default_actor("ops-console");
+    let mut ctx = ctx();
+    ctx.impersonation = Some(Impersonation {
+        actor_id: UserId::try_from("support-agent").expect("user id"),
+        reason: None,
+    });
+    store
+        .create_session(&ctx, data(&ctx, "node.start"))
+        .expect("create");
+    store
+        .purge_user(
+            &ctx.env,
+            &ctx.tenant_id,
+            ctx.user_id.as_ref().expect("user"),
+        )
+        .expect("purge");
+
+    let records = sink.read_all().expect("records");
+    assert_eq!(records[0].actor, "support-agent");
+    assert_eq!(records[1].operation, AuditOperation::Purged);
+    assert_eq!(records[1].actor, "ops-console");
+    assert_eq!(records[1].user.as_ref().map(|u| u.as_str()), Some("user-1"));
+    assert!(records[1].session_key.is_none());
+}
+
+#[test]
+fn tampering_is_detected() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let store =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("audited store");
+    run_lifecycle(&store);
+    let records = sink.read_all().expect("records");
+
+    let mut edited = records.clone();
+    edited[1].actor = "someone-else".into();
+    let err = verify_chain(&edited).expect_err("edited record");
+    assert_eq!(err.code, ErrorCode::Conflict);
+    assert!(err.message.contains("sequence 1"), "{}", err.message);
+
+    let mut removed = records.clone();
+    removed.remove(2);
+    verify_chain(&removed).expect_err("removed record");
+
+    let mut rehashed = records;
+    rehashed[2].actor = "someone-else".into();
+    rehashed[2].hash = rehashed[2].compute_hash().expect("hash");
+    let err = verify_chain(&rehashed).expect_err("rehashed record breaks the link");
+    assert!(err.message.contains("sequence 3"), "{}", err.message);
+}
+
+#[test]
+fn stores_sharing_a_sink_keep_one_chain() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let first =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("first store");
+    let second =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("second store");
+    run_lifecycle(&first);
+    run_lifecycle(&second);
+    run_lifecycle(&first);
+
+    let records = sink.read_all().expect("records");
+    assert_eq!(records.len(), 15);
+    verify_chain(&records).expect("writers extend the same chain");
+}
+
+#[test]
+fn jsonl_sink_continues_an_existing_chain() {
+    let path = std::env::temp_dir().join(format!("greentic-audit-{}.jsonl", uuid::Uuid::new_v4()));
+    {
+        let sink = Arc::new(JsonlAuditSink::open(&path).expect("open sink"));
+        let store =
+            AuditedSessionStore::new(InMemorySessionStore::new(), sink).expect("audited store");
+        run_lifecycle(&store);
+    }
+    let sink = Arc::new(JsonlAuditSink::open(&path).expect("reopen sink"));
+    let store =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("audited store");
+    run_lifecycle(&store);
+
+    let records = sink.read_all().expect("records");
+    assert_eq!(records.len(), 10);
+    verify_chain(&records).expect("chain spans both runs");
+    std::fs::remove_file(&path).expect("cleanup");
+}
+
+/// Sink whose appends always fail, as when its file system is full.
+struct FailingSink;
+
+impl AuditSink for FailingSink {
+    fn append(&self, _record: &AuditRecord) -> SessionResult<()> {
+        Err(greentic_session::GreenticError::new(
+            ErrorCode::Unavailable,
+            "disk full",
+        ))
+    }
+
+    fn last_record(&self) -> SessionResult<Option<AuditRecord>> {
+        Ok(None)
+    }
+
+    fn read_all(&self) -> SessionResult<Vec<AuditRecord>> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn sink_failures_do_not_fail_applied_mutations() {
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let seen = failures.clone();
+    let store = AuditedSessionStore::new(InMemorySessionStore::new(), Arc::new(FailingSink))
+        .expect("audited store")
+        .on_failure(move |failure| {
+            seen.lock()
+                .expect("lock")
+                .push((failure.operation, failure.error.message.clone()));
+        });
+    let ctx = ctx();
+    let key = store
+        .create_session(&ctx, data(&ctx, "node.start"))
+        .expect("the write applied, so it is reported as such");
+    assert!(store.get_session(&key).expect("get").is_some());
+    store.remove_session(&key).expect("remove");
+
+    assert_eq!(store.failed_appends(), 2);
+    assert_eq!(
+        *failures.lock().expect("lock"),
+        vec![
+            (AuditOperation::Created, "disk full".to_string()),
+            (AuditOperation::Deleted, "disk full".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn jsonl_sink_rejects_records_linking_to_a_stale_tail() {
+    let path = std::env::temp_dir().join(format!("greentic-audit-{}.jsonl", uuid::Uuid::new_v4()));
+    let sink = Arc::new(JsonlAuditSink::open(&path).expect("open sink"));
+    let store =
+        AuditedSessionStore::new(InMemorySessionStore::new(), sink.clone()).expect("audited store");
+    run_lifecycle(&store);
+    let last = sink.last_record().expect("tail").expect("records written");
+    assert_eq!(last.sequence, 4);
+
+    let mut stale = last.clone();
+    stale.sequence = 1;
+    stale.prev_hash = GENESIS_HASH.to_string();
+    stale.hash = stale.compute_hash().expect("hash");
+    assert!(!sink.append_linked(&stale).expect("append"));
+
+    let mut next = last.clone();
+    next.sequence = last.sequence + 1;
+    next.prev_hash = last.hash.clone();
+    next.hash = next.compute_hash().expect("hash");
+    assert!(sink.append_linked(&next).expect("append"));
+    assert_eq!(sink.last_record().expect("tail"), Some(next));
+    verify_chain(&sink.read_all().expect("records")).expect("intact chain");
+    std::fs::remove_file(&path).expect("cleanup");
+}
@@ -0,0 +1,66 @@
+use greentic_session::{JsonCodec, SessionCodec};
+use greentic_types::{
+    EnvId, FlowId, PackId, SessionCursor, SessionData, TeamId, TenantCtx, TenantId, UserId,
+};
+use proptest::prelude::*;
+
+const ID: &str = "[a-zA-Z0-9._-]{1,16}";
+
+fn session_data() -> impl Strategy<Value = SessionData> {
+    (
+        (ID, ID, proptest::option::of(ID), proptest::option::of(ID)),
+        (ID, proptest::option::of(ID)),
+        (
+            "\\PC*",
+            proptest::option::of("\\PC*"),
+            proptest::option::of("\\PC*"),
+        ),
+        "\\PC*",
+    )
+        .prop_map(
+            |((env, tenant, team, user), (flow, pack), (node, reason, marker), context)| {
+                let ctx = TenantCtx::new(
+                    EnvId::try_from(env.as_str()).expect("env id"),
+                    TenantId::try_from(tenant.as_str()).expect("tenant id"),
+                )
+                .with_team(team.map(|team| TeamId::try_from(team.as_str()).expect("team id")))
+                .with_user(user.map(|user| UserId::try_from(user.as_str()).expect("user id")));
+                let mut cursor = SessionCursor::new(node);
+                cursor.wait_reason = reason;
+                cursor.outbox_marker = marker;
+                SessionData {
+                    tenant_ctx: ctx,
+                    flow_id: FlowId::try_from(flow.as_str()).expect("flow id"),
+                    pack_id: pack.map(|pack| PackId::try_from(pack.as_str()).expect("pack id")),
+                    cursor,
+                    context_json: context,
+                }
+            },
+        )
+}
+
+fn round_trip(codec: &dyn SessionCodec, data: &SessionData) -> SessionData {
+    let value = serde_json::to_value(data).expect("to value");
+    let bytes = codec.encode(&value).expect("encode");
+    let decoded = codec.decode(&bytes).expect("decode");
+    serde_json::from_value(decoded).expect("from value")
+}
+
+proptest! {
+    #[test]
+    fn json_round_trips_session_data(data in session_data()) {
+        prop_assert_eq!(round_trip(&JsonCodec, &data), data);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trips_session_data(data in session_data()) {
+        prop_assert_eq!(round_trip(&greentic_session::CborCodec, &data), data);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trips_session_data(data in session_data()) {
+        prop_assert_eq!(round_trip(&greentic_session::MessagePackCodec, &data), data);
+    }
+}
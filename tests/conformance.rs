@@ -0,0 +1,15 @@
+#![cfg(feature = "testkit")]
+
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::{SessionBackendConfig, create_session_store, testkit};
+
+#[test]
+fn inmemory_store_passes_conformance_suite() {
+    testkit::run_all(&InMemorySessionStore::new());
+}
+
+#[test]
+fn boxed_store_passes_conformance_suite() {
+    let store = create_session_store(SessionBackendConfig::InMemory).expect("in-memory store");
+    testkit::run_all(&store);
+}
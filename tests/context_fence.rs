@@ -1,4 +1,8 @@
-use greentic_session::{ReplyScope, SessionBackendConfig, create_session_store};
+use greentic_session::{
+    FenceField, ReplyScope, SessionBackendConfig, SessionErrorDetail, SessionStore,
+    SessionStoreOptions, TenantFence, WaitSpec, create_session_store,
+    create_session_store_with_options, with_admin_claim,
+};
 use greentic_types::{
     EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId,
     UserId,
@@ -47,11 +51,12 @@ fn create_session_rejects_team_mismatch() {
         .create_session(&caller_ctx, data(&stored_ctx))
         .expect_err("team mismatch should be rejected");
     assert_eq!(err.code, ErrorCode::InvalidInput);
-    assert!(
-        err.message.contains("team"),
-        "mismatch reason should mention team: {}",
-        err.message
-    );
+    match SessionErrorDetail::of(&err) {
+        Some(SessionErrorDetail::Fence(violation)) => {
+            assert_eq!(violation.fields, [FenceField::Team]);
+        }
+        other => panic!("expected a fence violation, got {other:?}"),
+    }
 }
 
 #[test]
@@ -123,3 +128,168 @@ fn find_by_user_enforces_scope_and_user() {
         .expect("should find session");
     assert_eq!(found.0, key);
 }
+
+#[test]
+fn fence_violation_lists_every_mismatched_field() {
+    let stored = ctx("team-a", Some("user-1"));
+    let mut caller = ctx("team-b", Some("user-2"));
+    caller.tenant_id = TenantId::try_from("tenant-b").expect("tenant id");
+    let violation = TenantFence::strict()
+        .check_write(&caller, &data(&stored))
+        .expect_err("foreign caller");
+    assert_eq!(
+        violation.fields,
+        [FenceField::Tenant, FenceField::Team, FenceField::User]
+    );
+    assert_eq!(violation.reason, "env/tenant must match");
+    assert_eq!(*violation.expected, stored);
+
+    let store = create_session_store(SessionBackendConfig::InMemory).expect("store");
+    let err = store
+        .create_session(&caller, data(&stored))
+        .expect_err("foreign caller");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    assert!(
+        err.message.contains("mismatched: tenant, team, user"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn relaxed_fence_accepts_missing_team_and_new_user() {
+    let fence = TenantFence::strict()
+        .allow_missing_team()
+        .allow_user_introduction();
+    let store = create_session_store_with_options(
+        SessionBackendConfig::InMemory,
+        SessionStoreOptions::default().with_fence(fence),
+    )
+    .expect("store");
+    let teamless = ctx("team-a", None).with_team(None);
+    let key = store
+        .create_session(&teamless, data(&ctx("team-a", None)))
+        .expect("missing team is tolerated");
+    store
+        .update_session(&key, data(&ctx("team-a", Some("user-1"))))
+        .expect("user can be introduced");
+    let err = store
+        .update_session(&key, data(&ctx("team-b", Some("user-1"))))
+        .expect_err("teams present on both sides must still match");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    let err = store
+        .update_session(&key, data(&ctx("team-a", Some("user-2"))))
+        .expect_err("an introduced user cannot change");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+
+    let strict = create_session_store(SessionBackendConfig::InMemory).expect("store");
+    let key = strict
+        .create_session(&ctx("team-a", None), data(&ctx("team-a", None)))
+        .expect("create");
+    let err = strict
+        .update_session(&key, data(&ctx("team-a", Some("user-1"))))
+        .expect_err("strict fence rejects user introduction");
+    assert!(err.message.contains("introduced"), "{}", err.message);
+}
+
+#[test]
+fn cross_team_reads_relax_only_the_team() {
+    let stored = ctx("team-a", Some("user-1"));
+    let admin = with_admin_claim(ctx("team-ops", Some("user-1")));
+    let user = UserId::try_from("user-1").expect("user id");
+    let other_user = UserId::try_from("user-2").expect("user id");
+
+    assert!(!TenantFence::strict().permits_read(&stored, &admin, &user));
+    let fence = TenantFence::strict().cross_team_reads();
+    assert!(fence.permits_read(&stored, &admin, &user));
+    assert!(
+        !fence.permits_read(&stored, &ctx("team-ops", Some("user-1")), &user),
+        "the policy alone must not let a non-admin read another team"
+    );
+    assert!(!fence.permits_read(&stored, &admin, &other_user));
+    assert!(
+        fence.check_write(&admin, &data(&stored)).is_err(),
+        "cross-team reads must not open writes"
+    );
+}
+
+#[test]
+fn cross_team_reads_find_waits_of_another_team() {
+    let user = UserId::try_from("user-1").expect("user id");
+    let owner = ctx("team-a", Some("user-1"));
+    let admin = with_admin_claim(ctx("team-ops", Some("user-1")));
+    let waiting_scope = scope("slack", "c-1");
+    let register = |store: &dyn SessionStore| {
+        let key = SessionKey::new("team-a-wait");
+        store
+            .register_waits(
+                &owner,
+                &user,
+                &key,
+                data(&owner),
+                &[WaitSpec::new("reply", waiting_scope.clone())],
+            )
+            .expect("register");
+        key
+    };
+
+    let strict = create_session_store(SessionBackendConfig::InMemory).expect("store");
+    register(strict.as_ref());
+    assert_eq!(
+        strict
+            .find_wait_by_scope(&admin, &user, &waiting_scope)
+            .expect("find"),
+        None
+    );
+    assert!(
+        strict
+            .list_waits_for_user(&admin, &user)
+            .expect("list")
+            .is_empty()
+    );
+
+    let store = create_session_store_with_options(
+        SessionBackendConfig::InMemory,
+        SessionStoreOptions::default().with_fence(TenantFence::strict().cross_team_reads()),
+    )
+    .expect("store");
+    let key = register(store.as_ref());
+    let non_admin = ctx("team-ops", Some("user-1"));
+    assert_eq!(
+        store
+            .find_wait_by_scope(&non_admin, &user, &waiting_scope)
+            .expect("find"),
+        None,
+        "a caller without the admin claim keeps the strict team check"
+    );
+    assert!(
+        store
+            .list_waits_for_user(&non_admin, &user)
+            .expect("list")
+            .is_empty()
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&admin, &user, &waiting_scope)
+            .expect("find"),
+        Some(key.clone())
+    );
+    assert_eq!(
+        store.list_waits_for_user(&admin, &user).expect("list"),
+        vec![key.clone()]
+    );
+    let other_user = UserId::try_from("user-2").expect("user id");
+    assert_eq!(
+        store
+            .find_wait_by_scope(&admin, &other_user, &waiting_scope)
+            .expect("find"),
+        None,
+        "the user must still match"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&owner, &user, &waiting_scope)
+            .expect("owner still finds its wait"),
+        Some(key)
+    );
+}
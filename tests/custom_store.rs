@@ -0,0 +1,156 @@
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    HealthStatus, ReplyScope, SessionErrorDetail, SessionFilter, SessionPatch, SessionResult,
+};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use parking_lot::Mutex;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Store written against the 0.4 trait, implementing only the required methods.
+#[derive(Default)]
+struct MapStore {
+    sessions: Mutex<HashMap<String, SessionData>>,
+}
+
+impl SessionStore for MapStore {
+    fn create_session(&self, _ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        let key = SessionKey::new(format!("custom-{}", self.sessions.lock().len()));
+        self.sessions.lock().insert(key.as_str().to_string(), data);
+        Ok(key)
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        Ok(self.sessions.lock().get(key.as_str()).cloned())
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.sessions.lock().insert(key.as_str().to_string(), data);
+        Ok(())
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        self.sessions.lock().remove(key.as_str());
+        Ok(())
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        _ctx: &TenantCtx,
+        _user_id: &UserId,
+        _scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        Ok(None)
+    }
+
+    fn list_waits_for_user(
+        &self,
+        _ctx: &TenantCtx,
+        _user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        Ok(Vec::new())
+    }
+
+    fn clear_wait(
+        &self,
+        _ctx: &TenantCtx,
+        _user_id: &UserId,
+        _scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        Ok(None)
+    }
+
+    fn find_by_user(
+        &self,
+        _ctx: &TenantCtx,
+        _user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        Ok(None)
+    }
+}
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-custom").expect("tenant id");
+    TenantCtx::new(env, tenant)
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.custom").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: r#"{"step":1}"#.into(),
+    }
+}
+
+#[test]
+fn default_patch_goes_through_get_and_update() {
+    let store = MapStore::default();
+    let ctx = ctx();
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+
+    let patched = store
+        .patch_session(&key, &SessionPatch::merge(json!({"step": 2})))
+        .expect("patch");
+    assert_eq!(patched.context_json, r#"{"step":2}"#);
+    assert_eq!(store.get_session(&key).expect("get"), Some(patched));
+
+    let err = store
+        .patch_session(&SessionKey::new("missing"), &SessionPatch::merge(json!({})))
+        .expect_err("missing session");
+    assert_eq!(err.code, ErrorCode::NotFound);
+}
+
+#[test]
+fn default_history_reads_report_nothing_archived() {
+    let store = MapStore::default();
+    let ctx = ctx();
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+
+    assert!(store.list_session_versions(&key).expect("list").is_empty());
+    assert_eq!(store.get_session_version(&key, 1).expect("get"), None);
+    let err = store.rollback_session(&key, 1).expect_err("no history");
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::VersionNotFound { key, version: 1 })
+    );
+}
+
+#[test]
+fn default_extensions_report_unsupported_operations() {
+    let store = MapStore::default();
+    let ctx = ctx();
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+
+    let err = store
+        .list_sessions(&filter, None, 10)
+        .expect_err("listing is not implemented");
+    assert_eq!(err.code, ErrorCode::Internal);
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::Unsupported {
+            operation: "list_sessions"
+        })
+    );
+    let user = UserId::try_from("user-1").expect("user id");
+    let scope = ReplyScope {
+        conversation: "chat:1".into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    };
+    let err = store
+        .register_wait(&ctx, &user, &scope, &SessionKey::new("w"), data(&ctx), None)
+        .expect_err("waits are not implemented");
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::Unsupported {
+            operation: "register_waits"
+        })
+    );
+    assert_eq!(store.health().status, HealthStatus::Healthy);
+}
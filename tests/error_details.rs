@@ -0,0 +1,133 @@
+use greentic_session::audit::JsonlAuditSink;
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    ErrorCode, HistoryPolicy, JsonPatch, QuotaKind, QuotaPolicy, SessionErrorDetail, SessionLimits,
+    SessionPatch, SessionStoreOptions,
+};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-errors").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.errors").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+#[test]
+fn lookup_errors_carry_the_key() {
+    let store = InMemorySessionStore::with_options(
+        SessionStoreOptions::default().with_history(HistoryPolicy::new(1)),
+    );
+    let ctx = ctx();
+    let missing = SessionKey::new("missing");
+    let err = store.remove_session(&missing).expect_err("missing");
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::NotFound {
+            key: missing.clone()
+        })
+    );
+
+    let key = SessionKey::new("taken");
+    store
+        .insert_session(&ctx, &key, data(&ctx))
+        .expect("insert");
+    let err = store
+        .insert_session(&ctx, &key, data(&ctx))
+        .expect_err("taken");
+    assert_eq!(err.code, ErrorCode::Conflict);
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::SessionExists { key: key.clone() })
+    );
+
+    let err = store
+        .rollback_session(&key, 7)
+        .expect_err("no such version");
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::VersionNotFound { key, version: 7 })
+    );
+}
+
+#[test]
+fn quota_errors_report_the_limit_hit() {
+    let store = InMemorySessionStore::with_options(SessionStoreOptions::default().with_quotas(
+        QuotaPolicy::new(SessionLimits::unlimited().with_max_sessions_per_tenant(1)),
+    ));
+    let ctx = ctx();
+    store.create_session(&ctx, data(&ctx)).expect("first");
+    let err = store
+        .create_session(&ctx, data(&ctx))
+        .expect_err("over quota");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::QuotaExceeded {
+            tenant: ctx.tenant_id.clone(),
+            quota: QuotaKind::SessionsPerTenant,
+            limit: 1,
+            actual: 1,
+        })
+    );
+}
+
+#[test]
+fn failed_patch_tests_name_the_operation() {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+    let patch: JsonPatch = serde_json::from_value(serde_json::json!([
+        {"op": "add", "path": "/step", "value": 1},
+        {"op": "test", "path": "/step", "value": 2},
+    ]))
+    .expect("json patch");
+    let err = store
+        .patch_session(&key, &SessionPatch::json_patch(patch))
+        .expect_err("test operation fails");
+    assert_eq!(err.code, ErrorCode::Conflict);
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::PatchTestFailed {
+            operation: 1,
+            path: "/step".into(),
+        })
+    );
+}
+
+#[test]
+fn file_errors_report_the_file_backend() {
+    let missing = std::env::temp_dir()
+        .join(format!("greentic-missing-{}", uuid::Uuid::new_v4()))
+        .join("audit.jsonl");
+    let err = JsonlAuditSink::open(&missing)
+        .err()
+        .expect("missing directory");
+    assert_eq!(err.code, ErrorCode::Unavailable);
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::BackendUnavailable {
+            backend: "file",
+            kind: "NotFound".into(),
+        })
+    );
+}
+
+#[test]
+fn errors_from_elsewhere_have_no_detail() {
+    let err = greentic_session::GreenticError::new(ErrorCode::Internal, "boom");
+    assert_eq!(SessionErrorDetail::of(&err), None);
+}
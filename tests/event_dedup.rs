@@ -0,0 +1,133 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    ErrorCode, EventDedup, MAX_EVENT_ID_LEN, MigratingSessionStore, MigrationPhase,
+};
+use greentic_types::{EnvId, SessionKey, TeamId, TenantCtx, TenantId};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(60);
+
+fn tenant_ctx(tenant: &str, team: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    TenantCtx::new(env, tenant).with_team(Some(TeamId::try_from(team).expect("team id")))
+}
+
+#[test]
+fn duplicates_report_the_session_bound_by_the_first_delivery() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("tenant-dedup", "team-a");
+
+    assert_eq!(
+        store.record_event_once(&ctx, "evt-1", TTL).expect("record"),
+        EventDedup::FirstSeen
+    );
+    let pending = store.record_event_once(&ctx, "evt-1", TTL).expect("retry");
+    assert!(pending.is_duplicate());
+    assert_eq!(pending.session(), None, "first delivery still in flight");
+
+    let key = SessionKey::new("resumed-session");
+    assert!(store.bind_event_session(&ctx, "evt-1", &key).expect("bind"));
+    assert_eq!(
+        store.record_event_once(&ctx, "evt-1", TTL).expect("retry"),
+        EventDedup::Duplicate {
+            session: Some(key.clone())
+        }
+    );
+    assert!(
+        !store
+            .bind_event_session(&ctx, "evt-unknown", &key)
+            .expect("bind unknown"),
+        "binding needs a recorded event"
+    );
+
+    for other in [
+        tenant_ctx("tenant-dedup", "team-b"),
+        tenant_ctx("tenant-other", "team-a"),
+    ] {
+        assert_eq!(
+            store
+                .record_event_once(&other, "evt-1", TTL)
+                .expect("record"),
+            EventDedup::FirstSeen,
+            "event ids are scoped to the tenant and team"
+        );
+    }
+}
+
+#[test]
+fn expired_records_let_the_event_through_again() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("tenant-dedup-expiry", "team-a");
+    let short = Duration::from_millis(50);
+    store
+        .record_event_once(&ctx, "evt-short", short)
+        .expect("record");
+    store
+        .record_event_once(&ctx, "evt-long", TTL)
+        .expect("record");
+
+    std::thread::sleep(Duration::from_millis(80));
+    assert!(
+        !store
+            .bind_event_session(&ctx, "evt-short", &SessionKey::new("late"))
+            .expect("bind")
+    );
+    store.purge_expired().expect("purge expired");
+    assert_eq!(
+        store
+            .record_event_once(&ctx, "evt-short", short)
+            .expect("record"),
+        EventDedup::FirstSeen
+    );
+    assert!(
+        store
+            .record_event_once(&ctx, "evt-long", TTL)
+            .expect("record")
+            .is_duplicate()
+    );
+
+    store.purge_tenant(&ctx.env, &ctx.tenant_id).expect("purge");
+    assert_eq!(
+        store
+            .record_event_once(&ctx, "evt-long", TTL)
+            .expect("record"),
+        EventDedup::FirstSeen
+    );
+}
+
+#[test]
+fn invalid_events_are_rejected_and_migration_keeps_old_records() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("tenant-dedup-invalid", "team-a");
+    let too_long = "e".repeat(MAX_EVENT_ID_LEN + 1);
+    for (event_id, ttl) in [("", TTL), ("evt", Duration::ZERO), (too_long.as_str(), TTL)] {
+        let err = store
+            .record_event_once(&ctx, event_id, ttl)
+            .expect_err("invalid event");
+        assert_eq!(err.code, ErrorCode::InvalidInput, "{}", err.message);
+    }
+
+    let old = InMemorySessionStore::new();
+    old.record_event_once(&ctx, "evt-before", TTL)
+        .expect("record");
+    old.bind_event_session(&ctx, "evt-before", &SessionKey::new("old-session"))
+        .expect("bind");
+    let store = MigratingSessionStore::new(old, InMemorySessionStore::new());
+    assert_eq!(
+        store
+            .record_event_once(&ctx, "evt-before", TTL)
+            .expect("record")
+            .session(),
+        Some(&SessionKey::new("old-session"))
+    );
+    store.set_phase(MigrationPhase::NewOnly);
+    assert!(
+        store
+            .record_event_once(&ctx, "evt-before", TTL)
+            .expect("record")
+            .is_duplicate(),
+        "the new store recorded the event during dual writes"
+    );
+}
@@ -0,0 +1,63 @@
+use greentic_session::{CURRENT_FORMAT_VERSION, ErrorCode, MigrationRegistry};
+use greentic_types::{EnvId, FlowId, SessionCursor, SessionData, TenantCtx, TenantId};
+use serde_json::{Value, json};
+
+fn data() -> SessionData {
+    let ctx = TenantCtx::new(
+        EnvId::try_from("dev").expect("env id"),
+        TenantId::try_from("tenant-format").expect("tenant id"),
+    );
+    SessionData {
+        tenant_ctx: ctx,
+        flow_id: FlowId::try_from("flow.format").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{\"step\":1}".into(),
+    }
+}
+
+#[test]
+fn payloads_are_enveloped_and_legacy_payloads_still_decode() {
+    let registry = MigrationRegistry::new();
+    let encoded = registry.encode(&data()).expect("encode");
+    let envelope: Value = serde_json::from_str(&encoded).expect("json");
+    assert_eq!(envelope["format_version"], json!(CURRENT_FORMAT_VERSION));
+    assert_eq!(registry.decode(&encoded).expect("decode"), data());
+
+    let legacy = serde_json::to_string(&data()).expect("legacy payload");
+    assert_eq!(registry.decode(&legacy).expect("legacy decode"), data());
+}
+
+#[test]
+fn registered_steps_upgrade_older_payloads() {
+    let old = MigrationRegistry::new().encode(&data()).expect("encode");
+    let registry = MigrationRegistry::new().register(1, |mut data| {
+        data["context_json"] = json!("{\"step\":2}");
+        Ok(data)
+    });
+    assert_eq!(registry.target_version(), 2);
+
+    let upgraded = registry.decode(&old).expect("upgrade");
+    assert_eq!(upgraded.context_json, "{\"step\":2}");
+    let current = registry.encode(&upgraded).expect("encode");
+    assert_eq!(registry.decode(&current).expect("decode"), upgraded);
+}
+
+#[test]
+fn newer_or_unreachable_versions_are_rejected() {
+    let newer = json!({"format_version": 7, "data": data()}).to_string();
+    let err = MigrationRegistry::new()
+        .decode(&newer)
+        .expect_err("newer format");
+    assert_eq!(err.code, ErrorCode::Internal);
+    assert!(err.message.contains("newer"), "{}", err.message);
+
+    let gap = MigrationRegistry::new().register(2, Ok);
+    let old = MigrationRegistry::new().encode(&data()).expect("encode");
+    let err = gap.decode(&old).expect_err("missing step");
+    assert!(
+        err.message.contains("from format version 1"),
+        "{}",
+        err.message
+    );
+}
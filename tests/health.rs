@@ -0,0 +1,48 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    CURRENT_FORMAT_VERSION, HealthStatus, MigratingSessionStore, MigrationPhase,
+    SessionBackendConfig, StoreHealth, create_session_store,
+};
+
+#[test]
+fn inmemory_store_reports_healthy() {
+    let health = create_session_store(SessionBackendConfig::InMemory)
+        .expect("store")
+        .health();
+    assert_eq!(health.status, HealthStatus::Healthy);
+    assert_eq!(health.backend, "inmemory");
+    assert_eq!(health.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(health.format_version, CURRENT_FORMAT_VERSION);
+    assert_eq!(health.namespace, None);
+    assert!(health.latency.is_some());
+    assert!(health.reasons.is_empty());
+    assert!(health.is_healthy() && health.is_ready());
+
+    let json = serde_json::to_value(&health).expect("json");
+    assert_eq!(json["status"], "healthy");
+    assert!(json["latency_ms"].is_number());
+}
+
+#[test]
+fn degrade_keeps_the_worst_status() {
+    let health = StoreHealth::healthy("custom").degrade("slow disk");
+    assert_eq!(health.status, HealthStatus::Degraded);
+    assert!(health.is_ready());
+    let health = StoreHealth::unavailable("custom", "down").degrade("slow disk");
+    assert_eq!(health.status, HealthStatus::Unavailable);
+    assert_eq!(health.reasons, ["down", "slow disk"]);
+    assert!(!health.is_ready());
+}
+
+#[test]
+fn migrating_store_reports_both_backends_until_cut_over() {
+    let store =
+        MigratingSessionStore::new(InMemorySessionStore::new(), InMemorySessionStore::new());
+    let health = store.health();
+    assert_eq!(health.status, HealthStatus::Healthy);
+    assert_eq!(health.backend, "migrating(inmemory -> inmemory)");
+
+    store.set_phase(MigrationPhase::NewOnly);
+    assert_eq!(store.health().backend, "inmemory");
+}
@@ -0,0 +1,126 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ErrorCode, MigratingSessionStore, MigrationPhase, ReplyScope, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(60);
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-inbox").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.inbox").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn drain(store: &impl SessionStore, key: &SessionKey) -> Vec<String> {
+    std::iter::from_fn(|| store.inbox_pop(key).expect("pop"))
+        .map(|message| message.payload)
+        .collect()
+}
+
+#[test]
+fn messages_are_drained_in_arrival_order() {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+
+    assert_eq!(store.inbox_push(&key, "first", TTL).expect("push"), 1);
+    assert_eq!(store.inbox_push(&key, "second", TTL).expect("push"), 2);
+    let head = store.inbox_peek(&key).expect("peek").expect("head");
+    assert_eq!(head.payload, "first");
+    assert!(head.enqueued_at_ms > 0);
+    assert_eq!(store.inbox_len(&key).expect("len"), 2);
+
+    assert_eq!(drain(&store, &key), ["first", "second"]);
+    assert_eq!(store.inbox_len(&key).expect("len"), 0);
+    assert!(store.inbox_peek(&key).expect("peek").is_none());
+
+    let err = store
+        .inbox_push(&key, "late", Duration::ZERO)
+        .expect_err("zero ttl");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
+
+#[test]
+fn inbox_follows_the_session_lifecycle() {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let missing = SessionKey::new("missing");
+    let err = store
+        .inbox_push(&missing, "lost", TTL)
+        .expect_err("missing session");
+    assert_eq!(err.code, ErrorCode::NotFound);
+
+    let key = SessionKey::new("inbox-waiting");
+    let scope = |conversation: &str| ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    };
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            data(&ctx),
+            &[WaitSpec::new("chat", scope("chat-1"))],
+        )
+        .expect("register");
+    store.inbox_push(&key, "queued", TTL).expect("push");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            data(&ctx),
+            &[WaitSpec::new("chat", scope("chat-2"))],
+        )
+        .expect("re-register");
+    assert_eq!(
+        store.inbox_len(&key).expect("len"),
+        1,
+        "re-registering keeps the inbox"
+    );
+
+    store.remove_session(&key).expect("remove");
+    assert_eq!(store.inbox_len(&key).expect("len"), 0);
+
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+    store
+        .inbox_push(&key, "stale", Duration::from_millis(50))
+        .expect("push");
+    std::thread::sleep(Duration::from_millis(80));
+    assert!(store.inbox_pop(&key).expect("pop").is_none());
+}
+
+#[test]
+fn migrating_store_drains_the_old_inbox_first() {
+    let old = InMemorySessionStore::new();
+    let ctx = ctx();
+    let key = old.create_session(&ctx, data(&ctx)).expect("old");
+    old.inbox_push(&key, "before", TTL).expect("old push");
+    let store = MigratingSessionStore::new(old, InMemorySessionStore::new());
+
+    store.inbox_push(&key, "after", TTL).expect("push");
+    assert_eq!(store.inbox_len(&key).expect("len"), 2);
+    assert_eq!(drain(&store, &key), ["before", "after"]);
+
+    store.inbox_push(&key, "new-only", TTL).expect("push");
+    store.set_phase(MigrationPhase::NewOnly);
+    assert_eq!(drain(&store, &key), ["new-only"]);
+}
@@ -0,0 +1,94 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ReplyScope, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::thread;
+use std::time::Duration;
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-repair").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.repair").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.wait".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+#[test]
+fn consistent_store_reports_no_issues() {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    store.create_session(&ctx, data(&ctx)).expect("create");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &SessionKey::new("waiting"),
+            data(&ctx),
+            &[
+                WaitSpec::new("slack", scope("chat-1")),
+                WaitSpec::new("email", scope("mail-1")),
+            ],
+        )
+        .expect("register");
+
+    let report = store.verify_and_repair(false).expect("verify");
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(report.sessions, 2);
+    assert_eq!(report.scope_pointers, 2);
+    assert_eq!(report.user_wait_members, 1);
+}
+
+#[test]
+fn expired_waits_leave_entries_that_repair_removes() {
+    let store = InMemorySessionStore::new();
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let key = SessionKey::new("short-lived");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            data(&ctx),
+            &[WaitSpec::new("slack", scope("chat-1")).with_ttl(Duration::from_millis(20))],
+        )
+        .expect("register");
+    thread::sleep(Duration::from_millis(40));
+
+    let report = store.verify_and_repair(false).expect("verify");
+    assert!(!report.repaired);
+    assert_eq!(report.dangling_scopes.len(), 1);
+    assert_eq!(report.dangling_scopes[0].session, key);
+    assert_eq!(report.dangling_scopes[0].user, user);
+    assert_eq!(report.orphaned_user_waits.len(), 1);
+    assert!(report.unindexed_waits.is_empty());
+
+    let repaired = store.verify_and_repair(true).expect("repair");
+    assert!(repaired.repaired);
+    assert_eq!(repaired.dangling_scopes, report.dangling_scopes);
+    let after = store.verify_and_repair(false).expect("verify");
+    assert!(after.is_consistent(), "{after:?}");
+    assert_eq!(after.scope_pointers, 0);
+    assert_eq!(after.user_wait_members, 0);
+}
@@ -1,6 +1,6 @@
-use greentic_session::ReplyScope;
 use greentic_session::inmemory::InMemorySessionStore;
 use greentic_session::store::SessionStore;
+use greentic_session::{ReplyScope, WaitSpec};
 use greentic_types::{
     EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
 };
@@ -187,3 +187,199 @@ fn legacy_lookup_is_ambiguous_with_multiple_waits() {
         err.message
     );
 }
+
+#[test]
+fn fan_out_waits_resolve_to_one_session_and_clear_together() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("user-fanout");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let key = SessionKey::new("fanout");
+    let slack = scope("slack", "thread-1");
+    let email = scope("email", "msg-1");
+
+    store
+        .register_waits(
+            &ctx,
+            user,
+            &key,
+            sample_data(&ctx, "node.ask", "{\"step\":30}"),
+            &[
+                WaitSpec::new("slack", slack.clone()),
+                WaitSpec::new("email", email.clone()).with_ttl(Duration::from_secs(60)),
+            ],
+        )
+        .expect("waits registered");
+
+    let waits = store.list_session_waits(&key).expect("list session waits");
+    assert_eq!(waits.len(), 2);
+    let email_wait = waits
+        .iter()
+        .find(|wait| wait.name == "email")
+        .expect("email wait listed");
+    assert!(email_wait.expires_in.is_some());
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &slack).expect("find"),
+        Some(key.clone())
+    );
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &email).expect("find"),
+        Some(key.clone())
+    );
+    assert_eq!(
+        store.list_waits_for_user(&ctx, user).expect("list"),
+        vec![key.clone()]
+    );
+
+    // First answer wins: resuming clears every wait but keeps the session.
+    assert_eq!(
+        store.clear_session_waits(&key).expect("clear waits"),
+        ["email", "slack"]
+    );
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &slack)
+            .expect("find")
+            .is_none()
+    );
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &email)
+            .expect("find")
+            .is_none()
+    );
+    assert!(
+        store
+            .list_waits_for_user(&ctx, user)
+            .expect("list")
+            .is_empty()
+    );
+    assert!(store.get_session(&key).expect("get").is_some());
+}
+
+#[test]
+fn clearing_one_named_wait_keeps_its_siblings() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("user-siblings");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let key = SessionKey::new("siblings");
+    let slack = scope("slack", "thread-2");
+    let email = scope("email", "msg-2");
+
+    store
+        .register_waits(
+            &ctx,
+            user,
+            &key,
+            sample_data(&ctx, "node.ask", "{}"),
+            &[
+                WaitSpec::new("slack", slack.clone()),
+                WaitSpec::new("email", email.clone()),
+            ],
+        )
+        .expect("waits registered");
+
+    assert!(
+        store
+            .clear_session_wait(&key, "slack")
+            .expect("clear slack")
+    );
+    assert!(
+        !store
+            .clear_session_wait(&key, "slack")
+            .expect("already cleared")
+    );
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &slack)
+            .expect("find")
+            .is_none()
+    );
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &email).expect("find"),
+        Some(key.clone())
+    );
+
+    // The single-wait API replaces all waits of the session.
+    store
+        .register_wait(
+            &ctx,
+            user,
+            &slack,
+            &key,
+            sample_data(&ctx, "node.ask", "{}"),
+            None,
+        )
+        .expect("re-register");
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &email)
+            .expect("find")
+            .is_none()
+    );
+    let names: Vec<String> = store
+        .list_session_waits(&key)
+        .expect("list")
+        .into_iter()
+        .map(|wait| wait.name)
+        .collect();
+    assert_eq!(names, vec![greentic_session::DEFAULT_WAIT_NAME.to_string()]);
+}
+
+#[test]
+fn expired_wait_leaves_longer_siblings_routable() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("user-partial-expiry");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let key = SessionKey::new("partial-expiry");
+    let short = scope("sms", "short");
+    let long = scope("email", "long");
+
+    store
+        .register_waits(
+            &ctx,
+            user,
+            &key,
+            sample_data(&ctx, "node.ask", "{}"),
+            &[
+                WaitSpec::new("sms", short.clone()).with_ttl(Duration::from_millis(30)),
+                WaitSpec::new("email", long.clone()).with_ttl(Duration::from_secs(60)),
+            ],
+        )
+        .expect("waits registered");
+
+    sleep(Duration::from_millis(60));
+
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &short)
+            .expect("find")
+            .is_none()
+    );
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &long).expect("find"),
+        Some(key.clone())
+    );
+    let waits = store.list_session_waits(&key).expect("list");
+    assert_eq!(waits.len(), 1);
+    assert_eq!(waits[0].name, "email");
+}
+
+#[test]
+fn duplicate_wait_names_are_rejected() {
+    let store = InMemorySessionStore::new();
+    let ctx = tenant_ctx("user-dup");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let err = store
+        .register_waits(
+            &ctx,
+            user,
+            &SessionKey::new("dup"),
+            sample_data(&ctx, "node.ask", "{}"),
+            &[
+                WaitSpec::new("chat", scope("slack", "a")),
+                WaitSpec::new("chat", scope("slack", "b")),
+            ],
+        )
+        .expect_err("duplicate names rejected");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
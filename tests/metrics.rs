@@ -0,0 +1,175 @@
+#![cfg(feature = "metrics")]
+
+use greentic_session::ReplyScope;
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::metrics::{MeteredSessionStore, MetricsConfig, TenantLabels};
+use greentic_session::store::SessionStore;
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use std::collections::BTreeMap;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn ctx(tenant: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.metrics").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+/// Counter values keyed by metric name and sorted `label=value` pairs.
+fn counters(snapshotter: &Snapshotter) -> BTreeMap<String, u64> {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .filter_map(|(key, _, _, value)| {
+            let DebugValue::Counter(count) = value else {
+                return None;
+            };
+            let key = key.key();
+            let mut labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            labels.sort();
+            Some((format!("{}{{{}}}", key.name(), labels.join(",")), count))
+        })
+        .collect()
+}
+
+#[test]
+fn operations_are_counted_with_outcome_and_tenant() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        let store =
+            MeteredSessionStore::new(InMemorySessionStore::new(), MetricsConfig::new("inmemory"));
+        let ctx = ctx("tenant-a");
+        let user = ctx.user_id.clone().expect("user");
+        let key = store.create_session(&ctx, data(&ctx)).expect("create");
+        store.remove_session(&key).expect("remove");
+        store.remove_session(&key).expect_err("second remove fails");
+        store
+            .register_wait(
+                &ctx,
+                &user,
+                &scope("chat-1"),
+                &SessionKey::new("metered-wait"),
+                data(&ctx),
+                None,
+            )
+            .expect("register");
+        store
+            .find_wait_by_scope(&ctx, &user, &scope("chat-1"))
+            .expect("hit");
+        store
+            .find_wait_by_scope(&ctx, &user, &scope("chat-2"))
+            .expect("miss");
+    });
+
+    let counters = counters(&snapshotter);
+    let op = |operation: &str, outcome: &str, tenant: &str| {
+        counters
+            .get(&format!(
+                "greentic_session_operations_total{{backend=inmemory,operation={operation},outcome={outcome},tenant={tenant}}}"
+            ))
+            .copied()
+    };
+    assert_eq!(op("create_session", "ok", "tenant-a"), Some(1));
+    assert_eq!(op("remove_session", "ok", "-"), Some(1));
+    assert_eq!(op("remove_session", "not_found", "-"), Some(1));
+    assert_eq!(op("register_waits", "ok", "tenant-a"), Some(1));
+    assert_eq!(op("find_wait_by_scope", "ok", "tenant-a"), Some(2));
+    for result in ["hit", "miss"] {
+        assert_eq!(
+            counters.get(&format!(
+                "greentic_session_wait_lookups_total{{backend=inmemory,result={result},tenant=tenant-a}}"
+            )),
+            Some(&1)
+        );
+    }
+}
+
+#[test]
+fn tenant_labels_are_capped() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        let store = MeteredSessionStore::new(
+            InMemorySessionStore::new(),
+            MetricsConfig::new("inmemory").with_tenant_labels(TenantLabels::FirstSeen(1)),
+        );
+        for tenant in ["tenant-a", "tenant-b", "tenant-c"] {
+            let ctx = ctx(tenant);
+            store.create_session(&ctx, data(&ctx)).expect("create");
+        }
+    });
+
+    let counters = counters(&snapshotter);
+    let created = |tenant: &str| {
+        counters
+            .get(&format!(
+                "greentic_session_operations_total{{backend=inmemory,operation=create_session,outcome=ok,tenant={tenant}}}"
+            ))
+            .copied()
+    };
+    assert_eq!(created("tenant-a"), Some(1));
+    assert_eq!(created("other"), Some(2));
+    assert_eq!(created("tenant-b"), None);
+}
+
+#[test]
+fn stale_index_cleanups_are_counted() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        let store = InMemorySessionStore::new();
+        let ctx = ctx("tenant-a");
+        let user = ctx.user_id.clone().expect("user");
+        store
+            .register_wait(
+                &ctx,
+                &user,
+                &scope("chat-1"),
+                &SessionKey::new("stale-wait"),
+                data(&ctx),
+                Some(Duration::from_millis(20)),
+            )
+            .expect("register");
+        sleep(Duration::from_millis(50));
+        assert!(
+            store
+                .find_wait_by_scope(&ctx, &user, &scope("chat-1"))
+                .expect("lookup")
+                .is_none()
+        );
+    });
+
+    assert_eq!(
+        counters(&snapshotter)
+            .get("greentic_session_stale_index_entries_purged_total{backend=inmemory,index=scope}"),
+        Some(&1)
+    );
+}
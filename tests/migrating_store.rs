@@ -0,0 +1,266 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    MigratingSessionStore, MigrationPhase, ReplyScope, SessionFilter, SessionPatch, WaitSpec,
+};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-migrate").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, node: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.migrate").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{\"step\":1}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn filter(ctx: &TenantCtx) -> SessionFilter {
+    SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone())
+}
+
+#[test]
+fn dual_write_mirrors_writes_and_reads_fall_back_to_the_old_store() {
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let old = InMemorySessionStore::new();
+    let legacy = old
+        .create_session(&ctx, data(&ctx, "node.legacy"))
+        .expect("seed");
+    let store = MigratingSessionStore::new(old, InMemorySessionStore::new());
+
+    let fresh = store
+        .create_session(&ctx, data(&ctx, "node.fresh"))
+        .expect("create");
+    assert!(
+        store
+            .new_store()
+            .get_session(&fresh)
+            .expect("get")
+            .is_some()
+    );
+    assert!(
+        store
+            .old_store()
+            .get_session(&fresh)
+            .expect("get")
+            .is_some()
+    );
+
+    assert_eq!(
+        store
+            .get_session(&legacy)
+            .expect("get")
+            .expect("legacy")
+            .cursor
+            .node_pointer,
+        "node.legacy"
+    );
+    assert!(
+        store
+            .new_store()
+            .get_session(&legacy)
+            .expect("get")
+            .is_none()
+    );
+
+    store
+        .patch_session(&legacy, &SessionPatch::merge(json!({"step": 2})))
+        .expect("patch");
+    for side in [
+        store.new_store().get_session(&legacy),
+        store.old_store().get_session(&legacy),
+    ] {
+        let context: serde_json::Value =
+            serde_json::from_str(&side.expect("get").expect("session").context_json)
+                .expect("context");
+        assert_eq!(context, json!({"step": 2}));
+    }
+
+    let page = store
+        .list_sessions(&filter(&ctx), None, 1)
+        .expect("first page");
+    assert_eq!(page.sessions.len(), 1);
+    let rest = store
+        .list_sessions(&filter(&ctx), page.next_cursor.as_deref(), 10)
+        .expect("second page");
+    let mut keys: Vec<_> = page
+        .sessions
+        .iter()
+        .chain(&rest.sessions)
+        .map(|l| l.key.clone())
+        .collect();
+    keys.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    let mut expected = vec![legacy.clone(), fresh];
+    expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    assert_eq!(keys, expected);
+    assert!(rest.next_cursor.is_none());
+
+    let waiting = SessionKey::new("waiting");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &waiting,
+            data(&ctx, "node.wait"),
+            &[WaitSpec::new("approval", scope("chat-1"))],
+        )
+        .expect("register");
+    assert_eq!(
+        store
+            .old_store()
+            .find_wait_by_scope(&ctx, &user, &scope("chat-1"))
+            .expect("find"),
+        Some(waiting.clone())
+    );
+    store.remove_session(&legacy).expect("remove");
+    assert!(store.get_session(&legacy).expect("get").is_none());
+    assert!(
+        store
+            .old_store()
+            .get_session(&legacy)
+            .expect("get")
+            .is_none()
+    );
+}
+
+#[test]
+fn writes_promote_waiting_sessions_and_drain_the_old_store() {
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let old = InMemorySessionStore::new();
+    let waiting = SessionKey::new("paused");
+    old.register_waits(
+        &ctx,
+        &user,
+        &waiting,
+        data(&ctx, "node.wait"),
+        &[
+            WaitSpec::new("approval", scope("chat-1")).with_ttl(Duration::from_secs(600)),
+            WaitSpec::new("reminder", scope("chat-2")),
+        ],
+    )
+    .expect("seed");
+    let store = MigratingSessionStore::new(old, InMemorySessionStore::new())
+        .with_phase(MigrationPhase::NewWrites);
+
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope("chat-2"))
+            .expect("find"),
+        Some(waiting.clone())
+    );
+    assert!(
+        store
+            .clear_session_wait(&waiting, "reminder")
+            .expect("clear")
+    );
+
+    assert!(
+        store
+            .old_store()
+            .get_session(&waiting)
+            .expect("get")
+            .is_none()
+    );
+    let waits = store
+        .new_store()
+        .list_session_waits(&waiting)
+        .expect("waits");
+    assert_eq!(waits.len(), 1);
+    assert_eq!(waits[0].name, "approval");
+    assert!(waits[0].expires_in.is_some());
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope("chat-2"))
+            .expect("find"),
+        None
+    );
+
+    let fresh = store
+        .create_session(&ctx, data(&ctx, "node.fresh"))
+        .expect("create");
+    assert!(
+        store
+            .old_store()
+            .get_session(&fresh)
+            .expect("get")
+            .is_none()
+    );
+}
+
+#[test]
+fn background_backfill_copies_the_rest_before_the_flip() {
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let old = InMemorySessionStore::new();
+    let idle = old
+        .create_session(&ctx, data(&ctx, "node.idle"))
+        .expect("seed");
+    let waiting = SessionKey::new("paused");
+    old.register_waits(
+        &ctx,
+        &user,
+        &waiting,
+        data(&ctx, "node.wait"),
+        &[WaitSpec::new("approval", scope("chat-1"))],
+    )
+    .expect("seed");
+    let store = Arc::new(MigratingSessionStore::new(old, InMemorySessionStore::new()));
+    store
+        .update_session(&idle, data(&ctx, "node.updated"))
+        .expect("update");
+
+    let report = store
+        .spawn_backfill(None)
+        .join()
+        .expect("backfill thread")
+        .expect("backfill");
+    assert_eq!(report.sessions, 1);
+    assert_eq!(report.waits, 1);
+    assert_eq!(report.skipped_existing, 1);
+
+    store.set_phase(MigrationPhase::NewOnly);
+    assert_eq!(store.phase(), MigrationPhase::NewOnly);
+    store
+        .old_store()
+        .purge_tenant(&ctx.env, &ctx.tenant_id)
+        .expect("drop old");
+    assert_eq!(
+        store
+            .get_session(&idle)
+            .expect("get")
+            .expect("idle")
+            .cursor
+            .node_pointer,
+        "node.updated"
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope("chat-1"))
+            .expect("find"),
+        Some(waiting)
+    );
+    assert_eq!(store.list_tenants().expect("tenants").len(), 1);
+}
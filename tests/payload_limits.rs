@@ -0,0 +1,173 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    PayloadLimit, PayloadSizeWarning, QuotaPolicy, ReplyScope, SessionErrorDetail, SessionLimits,
+    SessionStoreOptions,
+};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::sync::{Arc, Mutex};
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-payload").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, filler: usize) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.payload").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: format!("{{\"blob\":\"{}\"}}", "x".repeat(filler)),
+    }
+}
+
+fn size_of(data: &SessionData) -> usize {
+    SessionStoreOptions::default()
+        .encode_payload(data)
+        .expect("encode")
+        .len()
+}
+
+fn store_with(limit: PayloadLimit) -> InMemorySessionStore {
+    InMemorySessionStore::with_options(SessionStoreOptions::default().with_payload_limit(limit))
+}
+
+#[test]
+fn oversized_writes_are_rejected_with_sizes() {
+    let ctx = ctx();
+    let max = size_of(&data(&ctx, 100));
+    let store = store_with(PayloadLimit::new(max));
+
+    let err = store
+        .create_session(&ctx, data(&ctx, 101))
+        .expect_err("oversized create rejected");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    match SessionErrorDetail::of(&err) {
+        Some(SessionErrorDetail::PayloadTooLarge {
+            actual, allowed, ..
+        }) => {
+            assert_eq!(*actual, max + 1);
+            assert_eq!(*allowed, max);
+        }
+        other => panic!("unexpected detail {other:?}"),
+    }
+    assert!(
+        err.message.contains(&format!("maximum of {max} bytes")),
+        "{}",
+        err.message
+    );
+
+    let key = store
+        .create_session(&ctx, data(&ctx, 100))
+        .expect("payload at the limit is accepted");
+    let err = store
+        .update_session(&key, data(&ctx, 500))
+        .expect_err("oversized update rejected");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    assert_eq!(
+        store.get_session(&key).expect("get").expect("session"),
+        data(&ctx, 100),
+        "rejected update leaves the stored payload untouched"
+    );
+
+    let err = store
+        .register_wait(
+            &ctx,
+            ctx.user_id.as_ref().expect("user"),
+            &ReplyScope {
+                conversation: "chat-1".into(),
+                thread: None,
+                reply_to: None,
+                correlation: None,
+            },
+            &SessionKey::new("payload-wait"),
+            data(&ctx, 500),
+            None,
+        )
+        .expect_err("oversized wait rejected");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn limit_measures_the_stored_encoding() {
+    let ctx = ctx();
+    let options = SessionStoreOptions::default().with_codec(greentic_session::CborCodec);
+    let encoded = |data: &SessionData| options.encode_payload(data).expect("encode").len();
+    let max = encoded(&data(&ctx, 100));
+    assert_ne!(max, size_of(&data(&ctx, 100)));
+    let store = InMemorySessionStore::with_options(
+        options.clone().with_payload_limit(PayloadLimit::new(max)),
+    );
+
+    store
+        .create_session(&ctx, data(&ctx, 100))
+        .expect("payload at the limit is accepted");
+    let oversized = data(&ctx, 101);
+    let err = store
+        .create_session(&ctx, oversized.clone())
+        .expect_err("oversized create rejected");
+    assert!(
+        err.message
+            .contains(&format!("{} bytes", encoded(&oversized))),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn near_limit_hook_reports_large_payloads() {
+    let ctx = ctx();
+    let max = size_of(&data(&ctx, 200));
+    let warn = size_of(&data(&ctx, 150));
+    let seen: Arc<Mutex<Vec<PayloadSizeWarning>>> = Arc::default();
+    let sink = Arc::clone(&seen);
+    let store = store_with(
+        PayloadLimit::new(max)
+            .with_warn_bytes(warn)
+            .on_near_limit(move |warning| sink.lock().expect("lock").push(warning.clone())),
+    );
+
+    store
+        .create_session(&ctx, data(&ctx, 10))
+        .expect("small payload");
+    assert!(seen.lock().expect("lock").is_empty());
+
+    let key = store
+        .create_session(&ctx, data(&ctx, 180))
+        .expect("large payload still accepted");
+    let warnings = seen.lock().expect("lock").clone();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].key, key);
+    assert_eq!(warnings[0].bytes, size_of(&data(&ctx, 180)));
+    assert_eq!(warnings[0].max_bytes, max);
+    assert_eq!(warnings[0].tenant_ctx, ctx);
+}
+
+#[test]
+fn store_limit_and_tenant_quota_report_distinct_codes() {
+    let ctx = ctx();
+    let small = size_of(&data(&ctx, 50));
+    let store = InMemorySessionStore::with_options(
+        SessionStoreOptions::default()
+            .with_payload_limit(PayloadLimit::new(small * 4))
+            .with_quotas(QuotaPolicy::new(
+                SessionLimits::unlimited().with_max_payload_bytes(small),
+            )),
+    );
+
+    let err = store
+        .create_session(&ctx, data(&ctx, 100))
+        .expect_err("tenant quota applies below the store limit");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+
+    let err = store
+        .create_session(&ctx, data(&ctx, small * 5))
+        .expect_err("store limit wins above it");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
@@ -0,0 +1,180 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    QuotaKind, QuotaPolicy, ReplyScope, SessionErrorDetail, SessionLimits, SessionStoreOptions,
+    WaitSpec,
+};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+
+fn ctx(tenant: &str, user: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let user = UserId::try_from(user).expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, context_json: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.quota").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: context_json.to_string(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn store_with(limits: SessionLimits) -> InMemorySessionStore {
+    InMemorySessionStore::with_options(
+        SessionStoreOptions::default().with_quotas(QuotaPolicy::new(limits)),
+    )
+}
+
+#[test]
+fn session_quota_is_enforced_per_tenant() {
+    let store = store_with(SessionLimits::unlimited().with_max_sessions_per_tenant(2));
+    let busy = ctx("tenant-busy", "user-1");
+    let other = ctx("tenant-other", "user-1");
+    let first = store
+        .create_session(&busy, data(&busy, "{}"))
+        .expect("first");
+    store
+        .create_session(&busy, data(&busy, "{}"))
+        .expect("second");
+
+    let err = store
+        .create_session(&busy, data(&busy, "{}"))
+        .expect_err("third session exceeds quota");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+    assert!(err.message.starts_with("quota exceeded"), "{}", err.message);
+
+    store
+        .create_session(&other, data(&other, "{}"))
+        .expect("other tenants are unaffected");
+
+    store.remove_session(&first).expect("remove");
+    store
+        .create_session(&busy, data(&busy, "{}"))
+        .expect("capacity is released on removal");
+}
+
+#[test]
+fn wait_quota_counts_waiting_sessions_per_user() {
+    let store = store_with(SessionLimits::unlimited().with_max_waits_per_user(1));
+    let ctx = ctx("tenant-a", "user-waits");
+    let user = ctx.user_id.clone().expect("user");
+    let key = SessionKey::new("wait-1");
+    store
+        .register_wait(&ctx, &user, &scope("chat:1"), &key, data(&ctx, "{}"), None)
+        .expect("first wait");
+    store
+        .register_wait(&ctx, &user, &scope("chat:2"), &key, data(&ctx, "{}"), None)
+        .expect("re-registering the same session does not consume quota");
+
+    let err = store
+        .register_wait(
+            &ctx,
+            &user,
+            &scope("chat:3"),
+            &SessionKey::new("wait-2"),
+            data(&ctx, "{}"),
+            None,
+        )
+        .expect_err("second waiting session exceeds quota");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+}
+
+#[test]
+fn wait_quota_counts_every_named_wait() {
+    let store = store_with(SessionLimits::unlimited().with_max_waits_per_user(2));
+    let ctx = ctx("tenant-a", "user-fanout");
+    let user = ctx.user_id.clone().expect("user");
+    let waits = |count: usize| -> Vec<WaitSpec> {
+        (0..count)
+            .map(|i| WaitSpec::new(format!("w{i}"), scope(&format!("fan:{i}"))))
+            .collect()
+    };
+    let fanned = SessionKey::new("fanned");
+
+    let err = store
+        .register_waits(&ctx, &user, &fanned, data(&ctx, "{}"), &waits(3))
+        .expect_err("a batch larger than the quota is rejected");
+    assert_eq!(
+        SessionErrorDetail::of(&err),
+        Some(&SessionErrorDetail::QuotaExceeded {
+            tenant: ctx.tenant_id.clone(),
+            quota: QuotaKind::WaitsPerUser,
+            limit: 2,
+            actual: 0,
+        })
+    );
+    assert!(store.get_session(&fanned).expect("get").is_none());
+
+    store
+        .register_waits(&ctx, &user, &fanned, data(&ctx, "{}"), &waits(2))
+        .expect("two waits fit");
+    store
+        .register_waits(&ctx, &user, &fanned, data(&ctx, "{}"), &waits(2))
+        .expect("re-registering replaces the session's own waits");
+    let err = store
+        .register_waits(
+            &ctx,
+            &user,
+            &SessionKey::new("single"),
+            data(&ctx, "{}"),
+            &[WaitSpec::new("reply", scope("single:1"))],
+        )
+        .expect_err("a third wait on another session exceeds the quota");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+
+    store
+        .clear_session_wait(&fanned, "w0")
+        .expect("clear one wait");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &SessionKey::new("single"),
+            data(&ctx, "{}"),
+            &[WaitSpec::new("reply", scope("single:1"))],
+        )
+        .expect("clearing a wait releases its quota");
+}
+
+#[test]
+fn payload_quota_applies_to_writes_and_honours_overrides() {
+    let tenant = TenantId::try_from("tenant-large").expect("tenant id");
+    let policy = QuotaPolicy::new(SessionLimits::unlimited().with_max_payload_bytes(256))
+        .with_tenant_limits(tenant, SessionLimits::unlimited());
+    let store =
+        InMemorySessionStore::with_options(SessionStoreOptions::default().with_quotas(policy));
+    let small = ctx("tenant-small", "user-1");
+    let large = ctx("tenant-large", "user-1");
+    let blob = format!("{{\"blob\":\"{}\"}}", "x".repeat(512));
+
+    let key = store
+        .create_session(&small, data(&small, "{}"))
+        .expect("small payload fits");
+    let err = store
+        .update_session(&key, data(&small, &blob))
+        .expect_err("oversized update rejected");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+    let err = store
+        .create_session(&small, data(&small, &blob))
+        .expect_err("oversized create rejected");
+    assert_eq!(err.code, ErrorCode::RateLimited);
+
+    store
+        .create_session(&large, data(&large, &blob))
+        .expect("override lifts the payload quota");
+}
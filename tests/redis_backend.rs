@@ -1,8 +1,12 @@
 #![cfg(feature = "redis")]
 
-use greentic_session::{ReplyScope, SessionBackendConfig, create_session_store};
+use greentic_session::{
+    QuotaPolicy, ReplyScope, SessionBackendConfig, SessionFilter, SessionLimits,
+    SessionStoreOptions, TenantFence, WaitSpec, create_session_store,
+    create_session_store_with_options, with_admin_claim,
+};
 use greentic_types::{
-    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId,
 };
 
 fn ctx(user: &str) -> TenantCtx {
@@ -85,3 +89,1172 @@ fn redis_backend_crud_when_url_provided() {
     let missing = store.get_session(&key).expect("get after delete");
     assert!(missing.is_none());
 }
+
+#[test]
+fn redis_backend_fan_out_waits_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!("skipping redis_backend_fan_out_waits_when_url_provided: REDIS_URL not set");
+            return;
+        }
+    };
+
+    let store =
+        create_session_store(SessionBackendConfig::RedisUrl(url)).expect("construct redis store");
+    let ctx = ctx("user-redis-fanout");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.ask".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = SessionKey::new("redis-fanout");
+    let slack = scope("slack", "redis-thread");
+    let email = scope("email", "redis-msg");
+
+    store
+        .register_waits(
+            &ctx,
+            user,
+            &key,
+            data,
+            &[
+                WaitSpec::new("slack", slack.clone()),
+                WaitSpec::new("email", email.clone()),
+            ],
+        )
+        .expect("register waits");
+    assert_eq!(store.list_session_waits(&key).expect("list").len(), 2);
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &email).expect("find"),
+        Some(key.clone())
+    );
+
+    assert!(
+        store
+            .clear_session_wait(&key, "email")
+            .expect("clear email")
+    );
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &email)
+            .expect("find")
+            .is_none()
+    );
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, user, &slack).expect("find"),
+        Some(key.clone())
+    );
+
+    store.remove_session(&key).expect("remove");
+    assert!(
+        store
+            .find_wait_by_scope(&ctx, user, &slack)
+            .expect("find")
+            .is_none()
+    );
+    assert!(
+        store
+            .list_waits_for_user(&ctx, user)
+            .expect("list")
+            .is_empty()
+    );
+}
+
+#[test]
+fn redis_backend_lists_tenant_sessions_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_lists_tenant_sessions_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-list");
+    let mut expected = Vec::new();
+    for step in 0..5 {
+        let data = SessionData {
+            tenant_ctx: ctx.clone(),
+            flow_id: FlowId::try_from("flow.redis").expect("flow"),
+            pack_id: None,
+            cursor: SessionCursor::new(format!("node.{step}")),
+            context_json: "{}".into(),
+        };
+        expected.push(store.create_session(&ctx, data).expect("create"));
+    }
+    expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    store.remove_session(&expected[1]).expect("remove");
+    let removed = expected.remove(1);
+
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+    let mut listed = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = store
+            .list_sessions(&filter, cursor.as_deref(), 2)
+            .expect("list page");
+        listed.extend(page.sessions.into_iter().map(|listing| listing.key));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(listed, expected);
+    assert!(!listed.contains(&removed));
+}
+
+#[test]
+fn redis_backend_purges_tenant_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!("skipping redis_backend_purges_tenant_when_url_provided: REDIS_URL not set");
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-purge");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.wait".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = SessionKey::new("redis-purge");
+    store
+        .register_waits(
+            &ctx,
+            user,
+            &key,
+            data.clone(),
+            &[
+                WaitSpec::new("slack", scope("slack", "purge")),
+                WaitSpec::new("email", scope("email", "purge")),
+            ],
+        )
+        .expect("register waits");
+    store.create_session(&ctx, data).expect("create idle");
+
+    let report = store
+        .purge_tenant(&ctx.env, &ctx.tenant_id)
+        .expect("purge tenant");
+    assert_eq!(report.sessions, 2);
+    assert_eq!(report.scope_pointers, 2);
+    assert_eq!(report.user_wait_sets, 1);
+    assert!(store.get_session(&key).expect("get").is_none());
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+    assert!(
+        store
+            .list_sessions(&filter, None, 10)
+            .expect("list")
+            .sessions
+            .is_empty()
+    );
+}
+
+#[test]
+fn redis_backend_reports_expired_sessions_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_reports_expired_sessions_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-expiry");
+    let user = ctx.user_id.as_ref().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.wait".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = SessionKey::new("redis-expiring");
+    store
+        .register_wait(
+            &ctx,
+            user,
+            &scope("redis", "expiring"),
+            &key,
+            data,
+            Some(std::time::Duration::from_millis(50)),
+        )
+        .expect("register wait");
+    std::thread::sleep(std::time::Duration::from_millis(150));
+
+    assert_eq!(store.purge_expired().expect("purge expired"), vec![key]);
+    assert!(store.purge_expired().expect("second purge").is_empty());
+}
+
+#[test]
+fn redis_stream_audit_sink_round_trips_when_url_provided() {
+    use greentic_session::SessionStore;
+    use greentic_session::audit::{
+        AuditSink, AuditedSessionStore, RedisStreamAuditSink, verify_chain,
+    };
+    use std::sync::Arc;
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_stream_audit_sink_round_trips_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let stream = format!("greentic:test:{}:audit", uuid::Uuid::new_v4());
+    let sink = Arc::new(RedisStreamAuditSink::from_url(&url, stream).expect("audit sink"));
+    let store = AuditedSessionStore::new(
+        create_session_store(SessionBackendConfig::InMemory).expect("store"),
+        sink.clone(),
+    )
+    .expect("audited store");
+    let ctx = ctx("user-redis-audit");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.start".to_string()),
+        context_json: "{}".into(),
+    };
+    // A second store, as on another host, must extend the same chain.
+    let replica = AuditedSessionStore::new(
+        create_session_store(SessionBackendConfig::InMemory).expect("store"),
+        sink.clone(),
+    )
+    .expect("audited replica");
+    let key = store.create_session(&ctx, data.clone()).expect("create");
+    let replica_key = replica.create_session(&ctx, data.clone()).expect("create");
+    store.update_session(&key, data).expect("update");
+    store.remove_session(&key).expect("remove");
+    replica.remove_session(&replica_key).expect("remove");
+
+    let records = sink.read_all().expect("read stream");
+    assert_eq!(records.len(), 5);
+    assert_eq!(sink.last_record().expect("tail"), records.last().cloned());
+    verify_chain(&records).expect("intact chain");
+}
+
+#[test]
+fn redis_backend_keeps_session_history_when_url_provided() {
+    use greentic_session::{HistoryPolicy, SessionStoreOptions, create_session_store_with_options};
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_keeps_session_history_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store_with_options(
+        SessionBackendConfig::RedisUrlWithNamespace {
+            url,
+            namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+        },
+        SessionStoreOptions::default().with_history(HistoryPolicy::new(2)),
+    )
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-history");
+    let data = |node: &str| SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{}".into(),
+    };
+    let key = store.create_session(&ctx, data("node.a")).expect("create");
+    for node in ["node.b", "node.c", "node.d"] {
+        store.update_session(&key, data(node)).expect("update");
+    }
+
+    let versions: Vec<u64> = store
+        .list_session_versions(&key)
+        .expect("versions")
+        .into_iter()
+        .map(|version| version.version)
+        .collect();
+    assert_eq!(versions, vec![3, 2]);
+
+    store.rollback_session(&key, 2).expect("rollback");
+    let current = store.get_session(&key).expect("get").expect("present");
+    assert_eq!(current.cursor.node_pointer, "node.b");
+    assert_eq!(
+        store
+            .get_session_version(&key, 4)
+            .expect("fetch")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.d".to_string())
+    );
+
+    store.remove_session(&key).expect("remove");
+    assert!(store.list_session_versions(&key).is_err());
+}
+
+#[test]
+fn redis_backend_patches_sessions_when_url_provided() {
+    use greentic_session::SessionPatch;
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_patches_sessions_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-patch");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: r#"{"items":[],"count":12345678901234567}"#.into(),
+    };
+    let key = store.create_session(&ctx, data).expect("create");
+
+    store
+        .patch_session(
+            &key,
+            &SessionPatch::merge(serde_json::json!({"name": "Ada"}))
+                .with_cursor(SessionCursor::new("node.next".to_string())),
+        )
+        .expect("patch");
+
+    let stored = store.get_session(&key).expect("get").expect("present");
+    assert_eq!(stored.cursor.node_pointer, "node.next");
+    assert_eq!(stored.tenant_ctx, ctx);
+    let context: serde_json::Value =
+        serde_json::from_str(&stored.context_json).expect("context json");
+    assert_eq!(
+        context,
+        serde_json::json!({"items": [], "count": 12345678901234567u64, "name": "Ada"})
+    );
+}
+
+#[test]
+fn redis_backend_migrates_legacy_payloads_when_url_provided() {
+    use redis::Commands;
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_migrates_legacy_payloads_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let namespace = format!("greentic:test:{}", uuid::Uuid::new_v4());
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url: url.clone(),
+        namespace: namespace.clone(),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-format");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.legacy".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = store.create_session(&ctx, data.clone()).expect("create");
+    let entry_key = format!("{namespace}:session:{}", key.as_str());
+    let mut conn = redis::Client::open(url)
+        .expect("client")
+        .get_connection()
+        .expect("connection");
+    let legacy = serde_json::to_string(&data).expect("legacy payload");
+    conn.set::<_, _, ()>(&entry_key, legacy)
+        .expect("write legacy payload");
+
+    assert_eq!(store.get_session(&key).expect("get"), Some(data.clone()));
+    let report = store.migrate_all().expect("migrate");
+    assert_eq!((report.scanned, report.migrated), (1, 1));
+    let stored: Vec<u8> = conn.get(&entry_key).expect("read back");
+    assert_eq!(stored.first(), Some(&b'J'));
+    let envelope: serde_json::Value = serde_json::from_slice(&stored[1..]).expect("json");
+    assert_eq!(envelope["format_version"], serde_json::json!(1));
+    assert_eq!(store.migrate_all().expect("second run").migrated, 0);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn redis_backend_reads_mixed_codecs_when_url_provided() {
+    use greentic_session::{CborCodec, SessionStoreOptions, create_session_store_with_options};
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_reads_mixed_codecs_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let config = SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    };
+    let json_store = create_session_store(config.clone()).expect("json store");
+    let cbor_store = create_session_store_with_options(
+        config,
+        SessionStoreOptions::default().with_codec(CborCodec),
+    )
+    .expect("cbor store");
+    let ctx = ctx("user-redis-codec");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.codec".to_string()),
+        context_json: "{}".into(),
+    };
+
+    let json_key = json_store.create_session(&ctx, data.clone()).expect("json");
+    let cbor_key = cbor_store.create_session(&ctx, data.clone()).expect("cbor");
+    assert_eq!(
+        json_store.get_session(&cbor_key).expect("get"),
+        Some(data.clone())
+    );
+    assert_eq!(cbor_store.get_session(&json_key).expect("get"), Some(data));
+
+    let report = cbor_store.migrate_all().expect("migrate");
+    assert_eq!((report.scanned, report.migrated), (2, 1));
+}
+
+#[test]
+fn redis_backend_round_trips_archives_when_url_provided() {
+    use greentic_session::archive::{export_sessions, import_sessions};
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_round_trips_archives_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store_in = |namespace: String| {
+        create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+            url: url.clone(),
+            namespace,
+        })
+        .expect("construct redis store")
+    };
+    let source = store_in(format!("greentic:test:{}", uuid::Uuid::new_v4()));
+    let target = store_in(format!("greentic:test:{}", uuid::Uuid::new_v4()));
+    let ctx = ctx("user-redis-archive");
+    let user = ctx.user_id.clone().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.archive".to_string()),
+        context_json: "{}".into(),
+    };
+    let idle = source.create_session(&ctx, data.clone()).expect("create");
+    let waiting = SessionKey::new("redis-archived-wait");
+    source
+        .register_wait(
+            &ctx,
+            &user,
+            &scope("redis", "archive"),
+            &waiting,
+            data,
+            None,
+        )
+        .expect("register");
+
+    let mut archive = Vec::new();
+    let exported = export_sessions(&source, None, &mut archive).expect("export");
+    assert_eq!((exported.sessions, exported.waits), (2, 1));
+    let imported = import_sessions(&target, archive.as_slice()).expect("import");
+    assert_eq!((imported.sessions, imported.waits), (2, 1));
+
+    assert!(target.get_session(&idle).expect("get").is_some());
+    assert_eq!(
+        target
+            .find_wait_by_scope(&ctx, &user, &scope("redis", "archive"))
+            .expect("scope"),
+        Some(waiting)
+    );
+    assert_eq!(
+        target.list_tenants().expect("tenants"),
+        vec![(ctx.env.clone(), ctx.tenant_id.clone())]
+    );
+    let err = target
+        .insert_session(
+            &ctx,
+            &idle,
+            target.get_session(&idle).expect("get").expect("present"),
+        )
+        .expect_err("taken key");
+    assert_eq!(err.code, greentic_session::ErrorCode::Conflict);
+}
+
+#[test]
+fn redis_backend_verifies_and_repairs_indices_when_url_provided() {
+    use greentic_session::repair::IndexIssueKind;
+    use redis::Commands;
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_verifies_and_repairs_indices_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let namespace = format!("greentic:test:{}", uuid::Uuid::new_v4());
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url: url.clone(),
+        namespace: namespace.clone(),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-repair");
+    let user = ctx.user_id.clone().expect("user");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.wait".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = SessionKey::new("repair-wait");
+    let reply = scope("redis", "repair");
+    store
+        .register_wait(&ctx, &user, &reply, &key, data, None)
+        .expect("register");
+    assert!(
+        store
+            .verify_and_repair(false)
+            .expect("verify")
+            .is_consistent()
+    );
+
+    let mut conn = redis::Client::open(url)
+        .expect("client")
+        .get_connection()
+        .expect("connection");
+    let pointers: Vec<String> = conn
+        .keys(format!("{namespace}:waits:scope:*"))
+        .expect("scope pointers");
+    assert_eq!(pointers.len(), 1);
+    conn.del::<_, ()>(&pointers[0]).expect("drop pointer");
+    let user_set = format!("{namespace}:waits:user:dev:tenant-redis:-:user-redis-repair");
+    conn.sadd::<_, _, ()>(&user_set, "ghost").expect("orphan");
+    conn.set::<_, _, ()>(
+        format!("{namespace}:waits:scope:dev:tenant-redis:-:user-redis-repair:deadbeef"),
+        "ghost",
+    )
+    .expect("dangling");
+
+    let report = store.verify_and_repair(false).expect("verify");
+    assert_eq!(report.dangling_scopes.len(), 1);
+    assert_eq!(report.dangling_scopes[0].session.as_str(), "ghost");
+    assert_eq!(report.orphaned_user_waits.len(), 1);
+    assert_eq!(report.unindexed_waits.len(), 1);
+    assert_eq!(
+        report.unindexed_waits[0].kind,
+        IndexIssueKind::ScopeNotRouted
+    );
+    assert!(!report.repaired);
+
+    let repaired = store.verify_and_repair(true).expect("repair");
+    assert_eq!(repaired.dangling_scopes, report.dangling_scopes);
+    assert!(
+        store
+            .verify_and_repair(false)
+            .expect("verify")
+            .is_consistent()
+    );
+    assert_eq!(
+        store.find_wait_by_scope(&ctx, &user, &reply).expect("find"),
+        Some(key)
+    );
+}
+
+#[cfg(feature = "testkit")]
+#[test]
+fn redis_backend_passes_conformance_suite_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_passes_conformance_suite_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    })
+    .expect("construct redis store");
+    greentic_session::testkit::run_all(&store);
+}
+
+#[test]
+fn redis_backend_reports_unreachable_server_as_unavailable() {
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url: "redis://127.0.0.1:1/".into(),
+        namespace: "greentic:health".into(),
+    })
+    .expect("construct redis store");
+    let health = store.health();
+    assert_eq!(health.status, greentic_session::HealthStatus::Unavailable);
+    assert_eq!(health.backend, "redis");
+    assert_eq!(health.namespace.as_deref(), Some("greentic:health"));
+    assert_eq!(health.latency, None);
+    assert_eq!(health.reasons.len(), 1);
+}
+
+#[test]
+fn redis_backend_reports_healthy_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_reports_healthy_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store(SessionBackendConfig::RedisUrl(url)).expect("redis store");
+    let health = store.health();
+    assert!(health.is_ready(), "{health:?}");
+    assert!(health.latency.is_some());
+    assert_eq!(health.namespace.as_deref(), Some("greentic:session"));
+}
+
+#[test]
+fn redis_backend_maintains_stats_counters_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_maintains_stats_counters_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    use redis::Commands;
+
+    let namespace = format!("greentic:test:{}", uuid::Uuid::new_v4());
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url: url.clone(),
+        namespace: namespace.clone(),
+    })
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-stats");
+    let user = ctx.user_id.clone().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.stats".to_string()),
+        context_json: "{}".into(),
+    };
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+    // A narrower filter is answered by listing, which the counters must agree with.
+    let listed = filter.clone().with_flow(data.flow_id.clone());
+
+    let plain = store.create_session(&ctx, data.clone()).expect("create");
+    let waiting = SessionKey::new("redis-stats-waiting");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &waiting,
+            data.clone(),
+            &[
+                WaitSpec::new("slack", scope("slack", "stats"))
+                    .with_ttl(std::time::Duration::from_secs(30)),
+                WaitSpec::new("email", scope("email", "stats"))
+                    .with_ttl(std::time::Duration::from_secs(30)),
+            ],
+        )
+        .expect("register waits");
+    assert!(store.clear_session_wait(&waiting, "email").expect("clear"));
+    let mut patched = data.clone();
+    patched.context_json = r#"{"grown":"payload"}"#.into();
+    store.update_session(&plain, patched).expect("update");
+
+    let counted = store.stats(&filter).expect("counted stats");
+    assert_eq!((counted.sessions, counted.waits), (2, 1));
+    assert_eq!((counted.ttl.under_minute, counted.ttl.no_expiry), (1, 1));
+    assert_eq!(counted.scope_pointers, 1);
+    assert_eq!(counted, store.stats(&listed).expect("listed stats"));
+
+    // Sessions and pointers written before the counters existed are backfilled by migrate_all.
+    let mut conn = redis::Client::open(url)
+        .expect("client")
+        .get_connection()
+        .expect("connection");
+    let stats_key = format!("{namespace}:stats:dev:tenant-redis");
+    let _: () = conn
+        .del(&[
+            stats_key.clone(),
+            format!("{stats_key}:sessions"),
+            format!("{stats_key}:expiry"),
+            format!("{stats_key}:pointers"),
+        ])
+        .expect("drop counters");
+    assert_eq!(store.stats(&filter).expect("empty stats").sessions, 0);
+    store.migrate_all().expect("backfill");
+    assert_eq!(store.stats(&filter).expect("backfilled stats"), counted);
+
+    store.remove_session(&plain).expect("remove");
+    let counted = store.stats(&filter).expect("counted stats");
+    assert_eq!(counted, store.stats(&listed).expect("listed stats"));
+    assert_eq!(counted.sessions, 1);
+
+    store
+        .purge_tenant(&filter.env, &filter.tenant)
+        .expect("purge");
+    assert_eq!(store.stats(&filter).expect("stats").sessions, 0);
+}
+
+#[test]
+fn redis_backend_reads_across_teams_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_reads_across_teams_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store_with_options(
+        SessionBackendConfig::RedisUrlWithNamespace {
+            url,
+            namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+        },
+        SessionStoreOptions::default().with_fence(TenantFence::strict().cross_team_reads()),
+    )
+    .expect("construct redis store");
+    let team = |name: &str| Some(TeamId::try_from(name).expect("team id"));
+    let owner = ctx("user-redis-teams").with_team(team("team-a"));
+    let non_admin = ctx("user-redis-teams").with_team(team("team-ops"));
+    let admin = with_admin_claim(non_admin.clone());
+    let user = owner.user_id.clone().expect("user present");
+    let data = SessionData {
+        tenant_ctx: owner.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.teams".to_string()),
+        context_json: "{}".into(),
+    };
+    let key = SessionKey::new("redis-team-a-wait");
+    let waiting_scope = scope("slack", "teams");
+    store
+        .register_waits(
+            &owner,
+            &user,
+            &key,
+            data,
+            &[WaitSpec::new("reply", waiting_scope.clone())],
+        )
+        .expect("register waits");
+
+    assert_eq!(
+        store
+            .find_wait_by_scope(&non_admin, &user, &waiting_scope)
+            .expect("find"),
+        None
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&admin, &user, &waiting_scope)
+            .expect("find"),
+        Some(key.clone())
+    );
+    assert_eq!(
+        store.list_waits_for_user(&admin, &user).expect("list"),
+        [key]
+    );
+}
+
+#[test]
+fn redis_backend_holds_session_quota_under_concurrent_creates_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_holds_session_quota_under_concurrent_creates_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let limit = 2;
+    let store = create_session_store_with_options(
+        SessionBackendConfig::RedisUrlWithNamespace {
+            url,
+            namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+        },
+        SessionStoreOptions::default().with_quotas(QuotaPolicy::new(
+            SessionLimits::unlimited().with_max_sessions_per_tenant(limit),
+        )),
+    )
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-quota");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.quota".to_string()),
+        context_json: "{}".into(),
+    };
+
+    let created = std::thread::scope(|threads| {
+        let attempts: Vec<_> = (0..8)
+            .map(|_| threads.spawn(|| store.create_session(&ctx, data.clone()).is_ok()))
+            .collect();
+        attempts
+            .into_iter()
+            .map(|attempt| attempt.join().expect("thread"))
+            .filter(|created| *created)
+            .count()
+    });
+    assert!(
+        created <= limit,
+        "created {created} sessions over a quota of {limit}"
+    );
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+    let listed = store.list_sessions(&filter, None, 10).expect("list");
+    assert_eq!(listed.sessions.len(), created);
+}
+
+#[test]
+fn redis_backend_counts_fanned_out_waits_against_quota_when_url_provided() {
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_counts_fanned_out_waits_against_quota_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store_with_options(
+        SessionBackendConfig::RedisUrlWithNamespace {
+            url,
+            namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+        },
+        SessionStoreOptions::default().with_quotas(QuotaPolicy::new(
+            SessionLimits::unlimited().with_max_waits_per_user(2),
+        )),
+    )
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-wait-quota");
+    let user = ctx.user_id.clone().expect("user present");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.quota".to_string()),
+        context_json: "{}".into(),
+    };
+    let waits = |count: usize| -> Vec<WaitSpec> {
+        (0..count)
+            .map(|i| WaitSpec::new(format!("w{i}"), scope("fan", &i.to_string())))
+            .collect()
+    };
+    let fanned = SessionKey::new("redis-fanned");
+
+    store
+        .register_waits(&ctx, &user, &fanned, data.clone(), &waits(3))
+        .expect_err("a batch larger than the quota is rejected");
+    store
+        .register_waits(&ctx, &user, &fanned, data.clone(), &waits(2))
+        .expect("two waits fit");
+    store
+        .register_waits(&ctx, &user, &fanned, data.clone(), &waits(2))
+        .expect("re-registering replaces the session's own waits");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &SessionKey::new("redis-single"),
+            data,
+            &[WaitSpec::new("reply", scope("single", "1"))],
+        )
+        .expect_err("a third wait on another session exceeds the quota");
+}
+
+#[test]
+fn redis_backend_rolls_back_in_the_current_codec_when_url_provided() {
+    use greentic_session::{CborCodec, HistoryPolicy};
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_rolls_back_in_the_current_codec_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let config = SessionBackendConfig::RedisUrlWithNamespace {
+        url,
+        namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+    };
+    let history = HistoryPolicy::new(4);
+    let json_store = create_session_store_with_options(
+        config.clone(),
+        SessionStoreOptions::default().with_history(history),
+    )
+    .expect("json store");
+    let cbor_store = create_session_store_with_options(
+        config,
+        SessionStoreOptions::default()
+            .with_history(history)
+            .with_codec(CborCodec),
+    )
+    .expect("cbor store");
+    let ctx = ctx("user-redis-rollback");
+    let data = |node: &str| SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{}".into(),
+    };
+    let key = json_store
+        .create_session(&ctx, data("node.json"))
+        .expect("create");
+    cbor_store
+        .update_session(&key, data("node.cbor"))
+        .expect("update");
+
+    cbor_store.rollback_session(&key, 1).expect("rollback");
+    assert_eq!(
+        cbor_store
+            .get_session(&key)
+            .expect("get")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.json".to_string())
+    );
+    let report = cbor_store.migrate_all().expect("migrate");
+    assert_eq!(
+        (report.scanned, report.migrated),
+        (1, 0),
+        "the restored payload is already written with the current codec"
+    );
+}
+
+#[test]
+fn redis_backend_finds_unindexed_sessions_when_url_provided() {
+    use redis::Commands;
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_finds_unindexed_sessions_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let namespace = format!("greentic:test:{}", uuid::Uuid::new_v4());
+    let store = create_session_store(SessionBackendConfig::RedisUrlWithNamespace {
+        url: url.clone(),
+        namespace: namespace.clone(),
+    })
+    .expect("construct redis store");
+    let (alice, bob) = (ctx("user-unindexed-a"), ctx("user-unindexed-b"));
+    let data = |ctx: &TenantCtx| SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.redis.legacy".to_string()),
+        context_json: "{}".into(),
+    };
+    let updated = store.create_session(&alice, data(&alice)).expect("create");
+    let migrated = store.create_session(&alice, data(&alice)).expect("create");
+    let purged = store.create_session(&bob, data(&bob)).expect("create");
+
+    // Sessions written before the listing index existed have no index entry.
+    let mut conn = redis::Client::open(url)
+        .expect("client")
+        .get_connection()
+        .expect("connection");
+    let tenant_index = format!("{namespace}:index:tenant:dev:tenant-redis");
+    let _: () = conn.del(&tenant_index).expect("drop index");
+    let filter = SessionFilter::tenant(alice.env.clone(), alice.tenant_id.clone());
+    let listed = |store: &dyn greentic_session::SessionStore| {
+        let mut keys: Vec<String> = store
+            .list_sessions(&filter, None, 10)
+            .expect("list")
+            .sessions
+            .into_iter()
+            .map(|listing| listing.key.as_str().to_string())
+            .collect();
+        keys.sort();
+        keys
+    };
+    assert!(listed(store.as_ref()).is_empty());
+
+    store
+        .update_session(&updated, data(&alice))
+        .expect("update");
+    assert_eq!(listed(store.as_ref()), vec![updated.as_str().to_string()]);
+
+    let bob_id = bob.user_id.clone().expect("user present");
+    let report = store
+        .purge_user(&bob.env, &bob.tenant_id, &bob_id)
+        .expect("purge user");
+    assert_eq!(report.sessions, 1);
+    assert!(store.get_session(&purged).expect("get").is_none());
+
+    store.migrate_all().expect("backfill index");
+    let mut expected = vec![updated.as_str().to_string(), migrated.as_str().to_string()];
+    expected.sort();
+    assert_eq!(listed(store.as_ref()), expected);
+    assert_eq!(store.stats(&filter).expect("stats").sessions, 2);
+
+    let _: () = conn.del(&tenant_index).expect("drop index");
+    let report = store
+        .purge_tenant(&alice.env, &alice.tenant_id)
+        .expect("purge tenant");
+    assert_eq!(report.sessions, 2);
+    assert!(store.get_session(&updated).expect("get").is_none());
+    assert!(store.get_session(&migrated).expect("get").is_none());
+}
+
+#[test]
+fn redis_backend_merges_json_patches_in_place_when_url_provided() {
+    use greentic_session::{HistoryPolicy, PayloadLimit, SessionPatch, SessionStore};
+
+    let url = match std::env::var("REDIS_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!(
+                "skipping redis_backend_merges_json_patches_in_place_when_url_provided: REDIS_URL not set"
+            );
+            return;
+        }
+    };
+
+    let store = create_session_store_with_options(
+        SessionBackendConfig::RedisUrlWithNamespace {
+            url,
+            namespace: format!("greentic:test:{}", uuid::Uuid::new_v4()),
+        },
+        SessionStoreOptions::default()
+            .with_history(HistoryPolicy::new(4))
+            .with_payload_limit(PayloadLimit::new(1024)),
+    )
+    .expect("construct redis store");
+    let ctx = ctx("user-redis-merge");
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.redis").expect("flow"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: r#"{"profile":{"name":"Ada","tags":["a"]},"step":1}"#.into(),
+    };
+    let key = store.create_session(&ctx, data).expect("create");
+
+    let patched = store
+        .patch_session(
+            &key,
+            &SessionPatch::merge(
+                serde_json::json!({"profile": {"tags": null, "lang": "en"}, "step": 2}),
+            )
+            .with_cursor(SessionCursor::new("node.next".to_string())),
+        )
+        .expect("patch");
+    let stored = store.get_session(&key).expect("get").expect("present");
+    assert_eq!(stored, patched);
+    assert_eq!(stored.cursor.node_pointer, "node.next");
+    assert_eq!(stored.tenant_ctx, ctx);
+    let context: serde_json::Value =
+        serde_json::from_str(&stored.context_json).expect("context json");
+    assert_eq!(
+        context,
+        serde_json::json!({"profile": {"name": "Ada", "lang": "en"}, "step": 2})
+    );
+    let versions = store.list_session_versions(&key).expect("versions");
+    assert_eq!(versions.len(), 1);
+    assert_eq!(
+        store
+            .get_session_version(&key, versions[0].version)
+            .expect("version")
+            .map(|data| data.cursor.node_pointer),
+        Some("node.start".to_string())
+    );
+    let filter = SessionFilter::tenant(ctx.env.clone(), ctx.tenant_id.clone());
+    assert_eq!(
+        store.stats(&filter).expect("stats"),
+        store
+            .stats(&filter.clone().with_flow(stored.flow_id.clone()))
+            .expect("listed stats"),
+        "the script must record the patched payload size"
+    );
+
+    // Outgrowing the ceiling falls back to the exact client-side checks.
+    let err = store
+        .patch_session(
+            &key,
+            &SessionPatch::merge(serde_json::json!({"blob": "x".repeat(2048)})),
+        )
+        .expect_err("payload limit");
+    assert_eq!(err.code, greentic_types::ErrorCode::InvalidInput);
+    assert_eq!(store.get_session(&key).expect("get"), Some(patched));
+}
@@ -0,0 +1,375 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    CircuitBreakerPolicy, CircuitState, ErrorCode, EventDedup, GreenticError, HealthStatus,
+    InboxMessage, IndexRepairReport, MigrationReport, PurgeReport, ReplyScope,
+    ResilientSessionStore, RetryPolicy, SessionErrorDetail, SessionFilter, SessionPage,
+    SessionPatch, SessionResult, SessionStats, SessionVersion, SessionWait, StoreHealth, WaitSpec,
+};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// In-memory store failing the next `failures` calls with `Unavailable`.
+#[derive(Default)]
+struct FlakyStore {
+    inner: InMemorySessionStore,
+    failures: AtomicUsize,
+    calls: AtomicUsize,
+}
+
+impl FlakyStore {
+    fn fail_next(&self, failures: usize) {
+        self.failures.store(failures, Ordering::SeqCst);
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn gate(&self) -> SessionResult<()> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let pending = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            });
+        match pending {
+            Ok(_) => Err(GreenticError::new(ErrorCode::Unavailable, "backend down")),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl SessionStore for FlakyStore {
+    fn create_session(&self, ctx: &TenantCtx, data: SessionData) -> SessionResult<SessionKey> {
+        self.gate()?;
+        self.inner.create_session(ctx, data)
+    }
+
+    fn insert_session(
+        &self,
+        ctx: &TenantCtx,
+        key: &SessionKey,
+        data: SessionData,
+    ) -> SessionResult<()> {
+        self.gate()?;
+        self.inner.insert_session(ctx, key, data)
+    }
+
+    fn get_session(&self, key: &SessionKey) -> SessionResult<Option<SessionData>> {
+        self.gate()?;
+        self.inner.get_session(key)
+    }
+
+    fn update_session(&self, key: &SessionKey, data: SessionData) -> SessionResult<()> {
+        self.gate()?;
+        self.inner.update_session(key, data)
+    }
+
+    fn remove_session(&self, key: &SessionKey) -> SessionResult<()> {
+        self.gate()?;
+        self.inner.remove_session(key)
+    }
+
+    fn migrate_all(&self) -> SessionResult<MigrationReport> {
+        self.inner.migrate_all()
+    }
+
+    fn patch_session(&self, key: &SessionKey, patch: &SessionPatch) -> SessionResult<SessionData> {
+        self.inner.patch_session(key, patch)
+    }
+
+    fn list_session_versions(&self, key: &SessionKey) -> SessionResult<Vec<SessionVersion>> {
+        self.inner.list_session_versions(key)
+    }
+
+    fn get_session_version(
+        &self,
+        key: &SessionKey,
+        version: u64,
+    ) -> SessionResult<Option<SessionData>> {
+        self.inner.get_session_version(key, version)
+    }
+
+    fn rollback_session(&self, key: &SessionKey, version: u64) -> SessionResult<()> {
+        self.inner.rollback_session(key, version)
+    }
+
+    fn register_waits(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        session_key: &SessionKey,
+        data: SessionData,
+        waits: &[WaitSpec],
+    ) -> SessionResult<bool> {
+        self.gate()?;
+        self.inner
+            .register_waits(ctx, user_id, session_key, data, waits)
+    }
+
+    fn list_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<SessionWait>> {
+        self.inner.list_session_waits(key)
+    }
+
+    fn clear_session_wait(&self, key: &SessionKey, name: &str) -> SessionResult<bool> {
+        self.inner.clear_session_wait(key, name)
+    }
+
+    fn clear_session_waits(&self, key: &SessionKey) -> SessionResult<Vec<String>> {
+        self.inner.clear_session_waits(key)
+    }
+
+    fn find_wait_by_scope(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.inner.find_wait_by_scope(ctx, user_id, scope)
+    }
+
+    fn list_waits_for_user(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+    ) -> SessionResult<Vec<SessionKey>> {
+        self.inner.list_waits_for_user(ctx, user_id)
+    }
+
+    fn clear_wait(
+        &self,
+        ctx: &TenantCtx,
+        user_id: &UserId,
+        scope: &ReplyScope,
+    ) -> SessionResult<Option<SessionKey>> {
+        self.inner.clear_wait(ctx, user_id, scope)
+    }
+
+    fn list_sessions(
+        &self,
+        filter: &SessionFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> SessionResult<SessionPage> {
+        self.inner.list_sessions(filter, cursor, limit)
+    }
+
+    fn list_tenants(&self) -> SessionResult<Vec<(EnvId, TenantId)>> {
+        self.inner.list_tenants()
+    }
+
+    fn purge_tenant(&self, env: &EnvId, tenant: &TenantId) -> SessionResult<PurgeReport> {
+        self.inner.purge_tenant(env, tenant)
+    }
+
+    fn purge_user(
+        &self,
+        env: &EnvId,
+        tenant: &TenantId,
+        user: &UserId,
+    ) -> SessionResult<PurgeReport> {
+        self.inner.purge_user(env, tenant, user)
+    }
+
+    fn purge_expired(&self) -> SessionResult<Vec<SessionKey>> {
+        self.inner.purge_expired()
+    }
+
+    fn verify_and_repair(&self, repair: bool) -> SessionResult<IndexRepairReport> {
+        self.inner.verify_and_repair(repair)
+    }
+
+    fn health(&self) -> StoreHealth {
+        self.inner.health()
+    }
+
+    fn stats(&self, filter: &SessionFilter) -> SessionResult<SessionStats> {
+        self.inner.stats(filter)
+    }
+
+    fn record_event_once(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        ttl: Duration,
+    ) -> SessionResult<EventDedup> {
+        self.inner.record_event_once(ctx, event_id, ttl)
+    }
+
+    fn bind_event_session(
+        &self,
+        ctx: &TenantCtx,
+        event_id: &str,
+        key: &SessionKey,
+    ) -> SessionResult<bool> {
+        self.inner.bind_event_session(ctx, event_id, key)
+    }
+
+    fn inbox_push(&self, key: &SessionKey, payload: &str, ttl: Duration) -> SessionResult<usize> {
+        self.inner.inbox_push(key, payload, ttl)
+    }
+
+    fn inbox_peek(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_peek(key)
+    }
+
+    fn inbox_pop(&self, key: &SessionKey) -> SessionResult<Option<InboxMessage>> {
+        self.inner.inbox_pop(key)
+    }
+
+    fn inbox_len(&self, key: &SessionKey) -> SessionResult<usize> {
+        self.inner.inbox_len(key)
+    }
+
+    #[allow(deprecated)]
+    fn find_by_user(
+        &self,
+        ctx: &TenantCtx,
+        user: &UserId,
+    ) -> SessionResult<Option<(SessionKey, SessionData)>> {
+        self.inner.find_by_user(ctx, user)
+    }
+}
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-resilient").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.resilient").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn fast_retries(attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(attempts).with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+}
+
+#[test]
+fn idempotent_calls_are_retried_until_the_backend_answers() {
+    let store = ResilientSessionStore::new(FlakyStore::default()).with_retry(fast_retries(3));
+    let ctx = ctx();
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+
+    store.inner().fail_next(2);
+    let before = store.inner().calls();
+    assert!(store.get_session(&key).expect("retried").is_some());
+    assert_eq!(store.inner().calls() - before, 3);
+    assert_eq!(store.circuit_health().state, CircuitState::Closed);
+    assert_eq!(store.circuit_health().consecutive_failures, 0);
+
+    store.inner().fail_next(3);
+    let err = store.get_session(&key).expect_err("attempts exhausted");
+    assert_eq!(err.code, ErrorCode::Unavailable);
+
+    store.inner().fail_next(0);
+    let err = store
+        .remove_session(&SessionKey::new("missing"))
+        .expect_err("missing");
+    assert_eq!(
+        err.code,
+        ErrorCode::NotFound,
+        "non-transient errors pass through"
+    );
+}
+
+#[test]
+fn non_idempotent_calls_are_tried_once_unless_opted_in() {
+    let ctx = ctx();
+    let store = ResilientSessionStore::new(FlakyStore::default()).with_retry(fast_retries(3));
+    store.inner().fail_next(1);
+    let err = store
+        .create_session(&ctx, data(&ctx))
+        .expect_err("not retried");
+    assert_eq!(err.code, ErrorCode::Unavailable);
+    assert_eq!(store.inner().calls(), 1);
+
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+    store.inner().fail_next(1);
+    let before = store.inner().calls();
+    store
+        .update_session(&key, data(&ctx))
+        .expect_err("update not retried");
+    assert_eq!(store.inner().calls() - before, 1);
+    store.inner().fail_next(1);
+    let user = ctx.user_id.clone().expect("user");
+    let scope = ReplyScope {
+        conversation: "chat-once".into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    };
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            data(&ctx),
+            &[WaitSpec::new("reply", scope)],
+        )
+        .expect_err("registration not retried");
+    assert_eq!(store.inner().calls() - before, 2);
+
+    let store = ResilientSessionStore::new(FlakyStore::default())
+        .with_retry(fast_retries(3).retry_non_idempotent());
+    store.inner().fail_next(1);
+    store.create_session(&ctx, data(&ctx)).expect("retried");
+    assert_eq!(store.inner().calls(), 2);
+}
+
+#[test]
+fn circuit_opens_fails_fast_and_recovers_through_a_probe() {
+    let store = ResilientSessionStore::new(FlakyStore::default())
+        .with_retry(RetryPolicy::disabled())
+        .with_circuit_breaker(CircuitBreakerPolicy::new(2, Duration::from_millis(50)));
+    let key = SessionKey::new("any");
+
+    store.inner().fail_next(10);
+    for _ in 0..2 {
+        store.get_session(&key).expect_err("backend down");
+    }
+    let health = store.circuit_health();
+    assert_eq!(health.state, CircuitState::Open);
+    let store_health = store.health();
+    assert_eq!(store_health.status, HealthStatus::Degraded);
+    assert!(store_health.is_ready());
+    assert_eq!(health.consecutive_failures, 2);
+    assert_eq!(health.last_error.as_deref(), Some("backend down"));
+    assert!(!health.is_healthy());
+
+    let calls = store.inner().calls();
+    let err = store.get_session(&key).expect_err("fails fast");
+    assert_eq!(err.code, ErrorCode::Unavailable);
+    assert!(matches!(
+        SessionErrorDetail::of(&err),
+        Some(SessionErrorDetail::CircuitOpen { .. })
+    ));
+    assert_eq!(
+        store.inner().calls(),
+        calls,
+        "open circuit must not reach the backend"
+    );
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(store.circuit_health().state, CircuitState::HalfOpen);
+    store.get_session(&key).expect_err("failed probe");
+    assert_eq!(store.circuit_health().state, CircuitState::Open);
+
+    std::thread::sleep(Duration::from_millis(60));
+    store.inner().fail_next(0);
+    assert!(store.get_session(&key).expect("probe").is_none());
+    let health = store.circuit_health();
+    assert_eq!(health.state, CircuitState::Closed);
+    assert_eq!(health.last_error, None);
+}
@@ -0,0 +1,152 @@
+use greentic_session::archive::{ExportReport, ImportReport, export_sessions, import_sessions};
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ErrorCode, ReplyScope, SessionFilter, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::time::Duration;
+
+fn ctx(tenant: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, node: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.archive").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: "{\"step\":1}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn seeded() -> (InMemorySessionStore, SessionKey, SessionKey) {
+    let store = InMemorySessionStore::new();
+    let alpha = ctx("tenant-alpha");
+    let user = alpha.user_id.clone().expect("user");
+    let idle = store
+        .create_session(&alpha, data(&alpha, "node.idle"))
+        .expect("create");
+    let waiting = SessionKey::new("waiting");
+    store
+        .register_waits(
+            &alpha,
+            &user,
+            &waiting,
+            data(&alpha, "node.wait"),
+            &[
+                WaitSpec::new("approval", scope("chat-1")).with_ttl(Duration::from_secs(600)),
+                WaitSpec::new("reminder", scope("chat-2")),
+            ],
+        )
+        .expect("register");
+    let beta = ctx("tenant-beta");
+    store
+        .create_session(&beta, data(&beta, "node.beta"))
+        .expect("create");
+    (store, idle, waiting)
+}
+
+#[test]
+fn archives_round_trip_sessions_waits_and_ttls() {
+    let (source, idle, waiting) = seeded();
+    let mut archive = Vec::new();
+    let exported = export_sessions(&source, None, &mut archive).expect("export");
+    assert_eq!(
+        exported,
+        ExportReport {
+            sessions: 3,
+            waits: 2
+        }
+    );
+
+    let target = InMemorySessionStore::new();
+    let imported = import_sessions(&target, archive.as_slice()).expect("import");
+    assert_eq!(imported.sessions, 3);
+    assert_eq!(imported.waits, 2);
+
+    let alpha = ctx("tenant-alpha");
+    let user = alpha.user_id.clone().expect("user");
+    assert_eq!(
+        target.get_session(&idle).expect("get"),
+        source.get_session(&idle).expect("get")
+    );
+    assert_eq!(
+        target
+            .find_wait_by_scope(&alpha, &user, &scope("chat-1"))
+            .expect("scope lookup"),
+        Some(waiting.clone())
+    );
+    assert_eq!(
+        target
+            .list_waits_for_user(&alpha, &user)
+            .expect("user waits"),
+        vec![waiting.clone()]
+    );
+    let waits = target.list_session_waits(&waiting).expect("waits");
+    let approval = waits
+        .iter()
+        .find(|wait| wait.name == "approval")
+        .expect("approval wait");
+    let remaining = approval.expires_in.expect("ttl preserved");
+    assert!(remaining <= Duration::from_secs(600) && remaining > Duration::from_secs(590));
+    assert!(
+        waits
+            .iter()
+            .any(|wait| wait.name == "reminder" && wait.expires_in.is_none())
+    );
+
+    let again = import_sessions(&target, archive.as_slice()).expect("re-import");
+    assert_eq!(
+        again,
+        ImportReport {
+            skipped_existing: 3,
+            ..ImportReport::default()
+        }
+    );
+}
+
+#[test]
+fn exports_can_be_limited_to_a_tenant() {
+    let (source, _, _) = seeded();
+    let beta = ctx("tenant-beta");
+    let filter = SessionFilter::tenant(beta.env.clone(), beta.tenant_id.clone());
+    let mut archive = Vec::new();
+    let exported = export_sessions(&source, Some(&filter), &mut archive).expect("export");
+    assert_eq!(exported.sessions, 1);
+
+    let target = InMemorySessionStore::new();
+    import_sessions(&target, archive.as_slice()).expect("import");
+    assert_eq!(
+        target.list_tenants().expect("tenants"),
+        vec![(beta.env, beta.tenant_id)]
+    );
+}
+
+#[test]
+fn malformed_archives_are_rejected() {
+    let store = InMemorySessionStore::new();
+    let err = import_sessions(&store, "{\"kind\":\"nope\"}\n".as_bytes()).expect_err("bad line");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    assert!(err.message.contains("line 1"), "{}", err.message);
+
+    let mut archive = Vec::new();
+    export_sessions(&seeded().0, None, &mut archive).expect("export");
+    let text = String::from_utf8(archive).expect("utf8");
+    let headerless: String = text.lines().skip(1).collect::<Vec<_>>().join("\n");
+    let err = import_sessions(&store, headerless.as_bytes()).expect_err("no header");
+    assert!(err.message.contains("header"), "{}", err.message);
+}
@@ -0,0 +1,135 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{HistoryPolicy, ReplyScope, SessionStoreOptions};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, TenantCtx, TenantId, UserId,
+};
+use std::time::Duration;
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-history").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, node: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.history").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new(node.to_string()),
+        context_json: format!("{{\"node\":\"{node}\"}}"),
+    }
+}
+
+fn store_with(policy: HistoryPolicy) -> InMemorySessionStore {
+    InMemorySessionStore::with_options(SessionStoreOptions::default().with_history(policy))
+}
+
+fn nodes(store: &impl SessionStore, key: &greentic_types::SessionKey) -> Vec<(u64, String)> {
+    store
+        .list_session_versions(key)
+        .expect("versions")
+        .into_iter()
+        .map(|version| (version.version, version.cursor.node_pointer))
+        .collect()
+}
+
+#[test]
+fn updates_are_archived_up_to_the_configured_depth() {
+    let store = store_with(HistoryPolicy::new(2));
+    let ctx = ctx();
+    let key = store
+        .create_session(&ctx, data(&ctx, "node.a"))
+        .expect("create");
+    assert!(nodes(&store, &key).is_empty());
+
+    for node in ["node.b", "node.c", "node.d"] {
+        store
+            .update_session(&key, data(&ctx, node))
+            .expect("update");
+    }
+
+    assert_eq!(
+        nodes(&store, &key),
+        vec![(3, "node.c".to_string()), (2, "node.b".to_string())]
+    );
+    let archived = store
+        .get_session_version(&key, 2)
+        .expect("fetch")
+        .expect("version retained");
+    assert_eq!(archived.context_json, "{\"node\":\"node.b\"}");
+    assert!(store.get_session_version(&key, 1).expect("fetch").is_none());
+}
+
+#[test]
+fn rollback_restores_a_version_and_archives_the_current_payload() {
+    let store = store_with(HistoryPolicy::new(5));
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let scope = ReplyScope {
+        conversation: "chat-1".into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    };
+    let key = store
+        .create_session(&ctx, data(&ctx, "node.a"))
+        .expect("create");
+    store
+        .register_wait(&ctx, &user, &scope, &key, data(&ctx, "node.wait"), None)
+        .expect("suspend");
+
+    store.rollback_session(&key, 1).expect("rollback");
+
+    let current = store.get_session(&key).expect("get").expect("present");
+    assert_eq!(current.cursor.node_pointer, "node.a");
+    assert_eq!(
+        nodes(&store, &key),
+        vec![(2, "node.wait".to_string()), (1, "node.a".to_string())]
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(&ctx, &user, &scope)
+            .expect("lookup"),
+        Some(key.clone()),
+        "rollback keeps waits"
+    );
+
+    let err = store
+        .rollback_session(&key, 9)
+        .expect_err("unknown version");
+    assert_eq!(err.code, ErrorCode::NotFound);
+    assert!(err.message.contains("version 9"), "{}", err.message);
+}
+
+#[test]
+fn history_is_disabled_by_default_and_respects_retention() {
+    let ctx = ctx();
+    let plain = InMemorySessionStore::new();
+    let key = plain
+        .create_session(&ctx, data(&ctx, "node.a"))
+        .expect("create");
+    plain
+        .update_session(&key, data(&ctx, "node.b"))
+        .expect("update");
+    assert!(nodes(&plain, &key).is_empty());
+
+    let store = store_with(HistoryPolicy::new(5).with_retention(Duration::from_millis(20)));
+    let key = store
+        .create_session(&ctx, data(&ctx, "node.a"))
+        .expect("create");
+    store
+        .update_session(&key, data(&ctx, "node.b"))
+        .expect("update");
+    assert_eq!(nodes(&store, &key).len(), 1);
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(nodes(&store, &key).is_empty());
+
+    store.remove_session(&key).expect("remove");
+    let err = store
+        .list_session_versions(&key)
+        .expect_err("missing session");
+    assert_eq!(err.code, ErrorCode::NotFound);
+}
@@ -0,0 +1,136 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{ReplyScope, SessionFilter};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId,
+    UserId,
+};
+
+fn ctx(tenant: &str, team: &str, user: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let team = TeamId::try_from(team).expect("team id");
+    let user = UserId::try_from(user).expect("user id");
+    TenantCtx::new(env, tenant)
+        .with_team(Some(team))
+        .with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx, flow: &str) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from(flow).expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn tenant_filter(tenant: &str) -> SessionFilter {
+    SessionFilter::tenant(
+        EnvId::try_from("dev").expect("env id"),
+        TenantId::try_from(tenant).expect("tenant id"),
+    )
+}
+
+fn collect_all(store: &InMemorySessionStore, filter: &SessionFilter) -> Vec<SessionKey> {
+    let mut keys = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = store
+            .list_sessions(filter, cursor.as_deref(), 2)
+            .expect("list page");
+        assert!(page.sessions.len() <= 2);
+        keys.extend(page.sessions.into_iter().map(|listing| listing.key));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    keys
+}
+
+#[test]
+fn pages_through_tenant_sessions_in_key_order() {
+    let store = InMemorySessionStore::new();
+    let alpha = ctx("tenant-a", "team-1", "user-1");
+    let beta = ctx("tenant-a", "team-2", "user-2");
+    let other = ctx("tenant-b", "team-1", "user-1");
+
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        expected.push(
+            store
+                .create_session(&alpha, data(&alpha, "flow.alpha"))
+                .expect("create"),
+        );
+        expected.push(
+            store
+                .create_session(&beta, data(&beta, "flow.beta"))
+                .expect("create"),
+        );
+    }
+    store
+        .create_session(&other, data(&other, "flow.alpha"))
+        .expect("create other tenant");
+    expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    assert_eq!(collect_all(&store, &tenant_filter("tenant-a")), expected);
+}
+
+#[test]
+fn filters_by_team_flow_and_waiting_state() {
+    let store = InMemorySessionStore::new();
+    let alpha = ctx("tenant-a", "team-1", "user-1");
+    let beta = ctx("tenant-a", "team-2", "user-2");
+    let idle = store
+        .create_session(&alpha, data(&alpha, "flow.alpha"))
+        .expect("create idle");
+    store
+        .create_session(&beta, data(&beta, "flow.beta"))
+        .expect("create beta");
+    let waiting = SessionKey::new("waiting-alpha");
+    store
+        .register_wait(
+            &alpha,
+            alpha.user_id.as_ref().expect("user"),
+            &ReplyScope {
+                conversation: "slack:thread".into(),
+                thread: None,
+                reply_to: None,
+                correlation: None,
+            },
+            &waiting,
+            data(&alpha, "flow.alpha"),
+            None,
+        )
+        .expect("register wait");
+
+    let team_one = tenant_filter("tenant-a").with_team(TeamId::try_from("team-1").expect("team"));
+    let mut team_keys = collect_all(&store, &team_one);
+    team_keys.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    let mut expected = vec![idle.clone(), waiting.clone()];
+    expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    assert_eq!(team_keys, expected);
+
+    let beta_flow =
+        tenant_filter("tenant-a").with_flow(FlowId::try_from("flow.beta").expect("flow"));
+    assert_eq!(collect_all(&store, &beta_flow).len(), 1);
+
+    let page = store
+        .list_sessions(&tenant_filter("tenant-a").waiting_only(), None, 10)
+        .expect("list waiting");
+    assert_eq!(page.sessions.len(), 1);
+    assert_eq!(page.sessions[0].key, waiting);
+    assert_eq!(page.sessions[0].waits.len(), 1);
+    assert!(page.next_cursor.is_none());
+}
+
+#[test]
+fn zero_page_limit_is_rejected() {
+    let store = InMemorySessionStore::new();
+    let err = store
+        .list_sessions(&tenant_filter("tenant-a"), None, 0)
+        .expect_err("zero limit rejected");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
@@ -0,0 +1,236 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    ObservedSessionStore, PurgeReport, ReplyScope, SessionBackendConfig, SessionObserver, WaitSpec,
+    create_session_store,
+};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<String>>,
+}
+
+impl Recorder {
+    fn push(&self, event: String) {
+        self.events.lock().expect("lock").push(event);
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.events.lock().expect("lock"))
+    }
+}
+
+impl SessionObserver for Recorder {
+    fn on_created(&self, key: &SessionKey, _data: &SessionData) {
+        self.push(format!("created:{}", key.as_str()));
+    }
+
+    fn on_updated(&self, key: &SessionKey, _data: &SessionData) {
+        self.push(format!("updated:{}", key.as_str()));
+    }
+
+    fn on_removed(&self, key: &SessionKey) {
+        self.push(format!("removed:{}", key.as_str()));
+    }
+
+    fn on_wait_registered(&self, key: &SessionKey, waits: &[WaitSpec]) {
+        let names: Vec<&str> = waits.iter().map(|wait| wait.name.as_str()).collect();
+        self.push(format!("waits:{}:{}", key.as_str(), names.join(",")));
+    }
+
+    fn on_wait_cleared(&self, key: &SessionKey, names: &[String]) {
+        self.push(format!("cleared:{}:{}", key.as_str(), names.join(",")));
+    }
+
+    fn on_expired(&self, key: &SessionKey) {
+        self.push(format!("expired:{}", key.as_str()));
+    }
+
+    fn on_purged(
+        &self,
+        _env: &EnvId,
+        tenant: &TenantId,
+        user: Option<&UserId>,
+        report: &PurgeReport,
+    ) {
+        self.push(format!(
+            "purged:{}:{}:{}",
+            tenant.as_str(),
+            user.map(|user| user.as_str()).unwrap_or("-"),
+            report.sessions
+        ));
+    }
+}
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-observed").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.observed").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+#[test]
+fn lifecycle_events_follow_store_changes() {
+    let recorder = Arc::new(Recorder::default());
+    let store = ObservedSessionStore::new(InMemorySessionStore::new())
+        .with_shared_observer(recorder.clone());
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+
+    let key = store.create_session(&ctx, data(&ctx)).expect("create");
+    store.update_session(&key, data(&ctx)).expect("update");
+    store
+        .register_waits(
+            &ctx,
+            &user,
+            &key,
+            data(&ctx),
+            &[
+                WaitSpec::new("chat", scope("chat-1")),
+                WaitSpec::new("email", scope("mail-1")),
+            ],
+        )
+        .expect("register waits");
+    assert!(store.clear_session_wait(&key, "chat").expect("clear one"));
+    assert!(
+        !store
+            .clear_session_wait(&key, "chat")
+            .expect("already gone")
+    );
+    assert_eq!(
+        store.clear_session_waits(&key).expect("clear all"),
+        ["email"]
+    );
+    assert!(
+        store
+            .clear_session_waits(&key)
+            .expect("nothing left")
+            .is_empty(),
+        "a clear that removed nothing does not notify"
+    );
+    store.remove_session(&key).expect("remove");
+    store
+        .remove_session(&key)
+        .expect_err("failed operations do not notify");
+
+    let k = key.as_str();
+    assert_eq!(
+        recorder.take(),
+        vec![
+            format!("created:{k}"),
+            format!("updated:{k}"),
+            format!("updated:{k}"),
+            format!("waits:{k}:chat,email"),
+            format!("cleared:{k}:chat"),
+            format!("cleared:{k}:email"),
+            format!("removed:{k}"),
+        ]
+    );
+}
+
+#[test]
+fn waits_on_new_sessions_and_scope_clears_are_reported() {
+    let recorder = Arc::new(Recorder::default());
+    let store = ObservedSessionStore::new(
+        create_session_store(SessionBackendConfig::InMemory).expect("store"),
+    )
+    .with_shared_observer(recorder.clone());
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let key = SessionKey::new("observed-wait");
+
+    assert!(
+        store
+            .register_wait(&ctx, &user, &scope("chat-1"), &key, data(&ctx), None)
+            .expect("register wait")
+    );
+    assert!(
+        !store
+            .register_wait(&ctx, &user, &scope("chat-1"), &key, data(&ctx), None)
+            .expect("register again")
+    );
+    assert_eq!(
+        store
+            .clear_wait(&ctx, &user, &scope("chat-1"))
+            .expect("clear wait"),
+        Some(key.clone())
+    );
+    assert_eq!(
+        store
+            .clear_wait(&ctx, &user, &scope("chat-1"))
+            .expect("nothing left to clear"),
+        None
+    );
+    store
+        .purge_user(&ctx.env, &ctx.tenant_id, &user)
+        .expect("purge user");
+
+    assert_eq!(
+        recorder.take(),
+        vec![
+            "created:observed-wait".to_string(),
+            "waits:observed-wait:default".to_string(),
+            "updated:observed-wait".to_string(),
+            "waits:observed-wait:default".to_string(),
+            "removed:observed-wait".to_string(),
+            "purged:tenant-observed:user-1:0".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn purge_expired_reports_elapsed_sessions() {
+    let recorder = Arc::new(Recorder::default());
+    let store = ObservedSessionStore::new(InMemorySessionStore::new())
+        .with_shared_observer(recorder.clone());
+    let ctx = ctx();
+    let user = ctx.user_id.clone().expect("user");
+    let short = SessionKey::new("short-lived");
+    let long = SessionKey::new("long-lived");
+    store
+        .register_wait(
+            &ctx,
+            &user,
+            &scope("chat-short"),
+            &short,
+            data(&ctx),
+            Some(Duration::from_millis(30)),
+        )
+        .expect("short wait");
+    store
+        .register_wait(&ctx, &user, &scope("chat-long"), &long, data(&ctx), None)
+        .expect("long wait");
+    recorder.take();
+
+    sleep(Duration::from_millis(60));
+    assert_eq!(store.purge_expired().expect("purge"), vec![short.clone()]);
+    assert_eq!(recorder.take(), vec!["expired:short-lived".to_string()]);
+    assert!(store.get_session(&short).expect("get").is_none());
+    assert!(store.get_session(&long).expect("get").is_some());
+    assert!(store.purge_expired().expect("second purge").is_empty());
+}
@@ -0,0 +1,115 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{HistoryPolicy, JsonPatch, SessionPatch, SessionStoreOptions};
+use greentic_types::{
+    EnvId, ErrorCode, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use serde_json::{Value, json};
+
+fn ctx() -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-patch").expect("tenant id");
+    let user = UserId::try_from("user-1").expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn seeded(store: &InMemorySessionStore) -> SessionKey {
+    let ctx = ctx();
+    let data = SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.patch").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: json!({"answers": {"name": "Ada"}, "items": [], "step": 1}).to_string(),
+    };
+    store.create_session(&ctx, data).expect("create")
+}
+
+fn context(store: &InMemorySessionStore, key: &SessionKey) -> Value {
+    let data = store.get_session(key).expect("get").expect("present");
+    serde_json::from_str(&data.context_json).expect("context json")
+}
+
+fn json_patch(ops: Value) -> JsonPatch {
+    serde_json::from_value(ops).expect("json patch")
+}
+
+#[test]
+fn merge_patch_updates_fields_and_cursor() {
+    let store = InMemorySessionStore::new();
+    let key = seeded(&store);
+
+    let patched = store
+        .patch_session(
+            &key,
+            &SessionPatch::merge(json!({"answers": {"email": "ada@example.com"}, "step": null}))
+                .with_cursor(SessionCursor::new("node.confirm".to_string())),
+        )
+        .expect("patch");
+
+    assert_eq!(patched.cursor.node_pointer, "node.confirm");
+    assert_eq!(patched.tenant_ctx, ctx());
+    assert_eq!(
+        context(&store, &key),
+        json!({"answers": {"name": "Ada", "email": "ada@example.com"}, "items": []})
+    );
+}
+
+#[test]
+fn json_patch_applies_all_operations_or_none() {
+    let store = InMemorySessionStore::with_options(
+        SessionStoreOptions::default().with_history(HistoryPolicy::new(4)),
+    );
+    let key = seeded(&store);
+
+    store
+        .patch_session(
+            &key,
+            &SessionPatch::json_patch(json_patch(json!([
+                {"op": "test", "path": "/step", "value": 1},
+                {"op": "add", "path": "/items/-", "value": "first"},
+                {"op": "replace", "path": "/step", "value": 2}
+            ]))),
+        )
+        .expect("patch");
+    assert_eq!(context(&store, &key)["items"], json!(["first"]));
+    assert_eq!(context(&store, &key)["step"], json!(2));
+    assert_eq!(
+        store.list_session_versions(&key).expect("versions").len(),
+        1
+    );
+
+    let err = store
+        .patch_session(
+            &key,
+            &SessionPatch::json_patch(json_patch(json!([
+                {"op": "replace", "path": "/step", "value": 3},
+                {"op": "test", "path": "/step", "value": 1}
+            ]))),
+        )
+        .expect_err("failed test");
+    assert_eq!(err.code, ErrorCode::Conflict);
+    assert_eq!(context(&store, &key)["step"], json!(2));
+
+    let err = store
+        .patch_session(
+            &key,
+            &SessionPatch::json_patch(json_patch(json!([
+                {"op": "remove", "path": "/missing/field"}
+            ]))),
+        )
+        .expect_err("bad pointer");
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+}
+
+#[test]
+fn patching_missing_sessions_fails() {
+    let store = InMemorySessionStore::new();
+    let err = store
+        .patch_session(
+            &SessionKey::new("missing"),
+            &SessionPatch::merge(json!({"step": 2})),
+        )
+        .expect_err("missing session");
+    assert_eq!(err.code, ErrorCode::NotFound);
+}
@@ -0,0 +1,164 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{
+    MigratingSessionStore, MigrationPhase, ReplyScope, SessionFilter, SessionStats, TtlBuckets,
+    WaitSpec,
+};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TenantCtx, TenantId, UserId,
+};
+use std::time::Duration;
+
+fn ctx(tenant: &str, user: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let user = UserId::try_from(user).expect("user id");
+    TenantCtx::new(env, tenant).with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.stats").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: r#"{"step":1}"#.into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn tenant_filter(tenant: &str) -> SessionFilter {
+    SessionFilter::tenant(
+        EnvId::try_from("dev").expect("env id"),
+        TenantId::try_from(tenant).expect("tenant id"),
+    )
+}
+
+#[test]
+fn counts_sessions_waits_and_lifetimes_per_tenant() {
+    let store = InMemorySessionStore::new();
+    let alice = ctx("tenant-stats", "alice");
+    let bob = ctx("tenant-stats", "bob");
+    let user = |ctx: &TenantCtx| ctx.user_id.clone().expect("user");
+
+    store.create_session(&alice, data(&alice)).expect("create");
+    store
+        .register_waits(
+            &alice,
+            &user(&alice),
+            &SessionKey::new("alice-waiting"),
+            data(&alice),
+            &[
+                WaitSpec::new("slack", scope("slack:1")).with_ttl(Duration::from_secs(30)),
+                WaitSpec::new("email", scope("email:1")).with_ttl(Duration::from_secs(30)),
+            ],
+        )
+        .expect("register alice");
+    store
+        .register_waits(
+            &bob,
+            &user(&bob),
+            &SessionKey::new("bob-waiting"),
+            data(&bob),
+            &[WaitSpec::new("slack", scope("slack:2")).with_ttl(Duration::from_secs(7200))],
+        )
+        .expect("register bob");
+    let other = ctx("tenant-other", "alice");
+    store.create_session(&other, data(&other)).expect("other");
+
+    let stats = store.stats(&tenant_filter("tenant-stats")).expect("stats");
+    assert_eq!(stats.sessions, 3);
+    assert_eq!(stats.waits, 3);
+    assert_eq!(stats.scope_pointers, 3);
+    assert!(stats.payload_bytes > 0);
+    assert_eq!(
+        stats.ttl,
+        TtlBuckets {
+            under_minute: 1,
+            under_day: 1,
+            no_expiry: 1,
+            ..TtlBuckets::default()
+        }
+    );
+
+    let waiting = store
+        .stats(&tenant_filter("tenant-stats").waiting_only())
+        .expect("waiting");
+    assert_eq!(waiting.sessions, 2);
+    let bobs = store
+        .stats(&tenant_filter("tenant-stats").with_user(user(&bob)))
+        .expect("bob");
+    assert_eq!((bobs.sessions, bobs.waits), (1, 1));
+    assert!(bobs.payload_bytes > 0 && bobs.payload_bytes < stats.payload_bytes);
+}
+
+#[test]
+fn expired_and_cleared_state_drops_out_of_the_stats() {
+    let store = InMemorySessionStore::new();
+    let alice = ctx("tenant-stats-expiry", "alice");
+    let user = alice.user_id.clone().expect("user");
+    let key = SessionKey::new("short-lived");
+    store
+        .register_waits(
+            &alice,
+            &user,
+            &key,
+            data(&alice),
+            &[WaitSpec::new("slack", scope("slack:3")).with_ttl(Duration::from_millis(50))],
+        )
+        .expect("register");
+    let durable = store.create_session(&alice, data(&alice)).expect("create");
+    store
+        .register_waits(
+            &alice,
+            &user,
+            &durable,
+            data(&alice),
+            &[WaitSpec::new("email", scope("email:3"))],
+        )
+        .expect("register durable");
+    let filter = tenant_filter("tenant-stats-expiry");
+    assert_eq!(store.stats(&filter).expect("stats").sessions, 2);
+
+    std::thread::sleep(Duration::from_millis(80));
+    let stats = store.stats(&filter).expect("stats");
+    assert_eq!((stats.sessions, stats.waits), (1, 1));
+    assert_eq!(stats.ttl.no_expiry, 1);
+
+    store.clear_session_waits(&durable).expect("clear");
+    let stats = store.stats(&filter).expect("stats");
+    assert_eq!(
+        (stats.sessions, stats.waits, stats.scope_pointers),
+        (1, 0, 0)
+    );
+
+    store
+        .purge_tenant(&filter.env, &filter.tenant)
+        .expect("purge");
+    assert_eq!(
+        store.stats(&filter).expect("stats"),
+        SessionStats::default()
+    );
+}
+
+#[test]
+fn migrating_store_sums_both_backends_until_cut_over() {
+    let old = InMemorySessionStore::new();
+    let alice = ctx("tenant-stats-migrating", "alice");
+    old.create_session(&alice, data(&alice)).expect("old");
+    let store = MigratingSessionStore::new(old, InMemorySessionStore::new());
+    store.create_session(&alice, data(&alice)).expect("dual");
+
+    let filter = tenant_filter("tenant-stats-migrating");
+    assert_eq!(store.stats(&filter).expect("stats").sessions, 3);
+    store.set_phase(MigrationPhase::NewOnly);
+    assert_eq!(store.stats(&filter).expect("stats").sessions, 1);
+}
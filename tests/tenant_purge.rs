@@ -0,0 +1,148 @@
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::{PurgeReport, ReplyScope, SessionFilter, WaitSpec};
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId,
+};
+
+fn ctx(tenant: &str, team: &str, user: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from(tenant).expect("tenant id");
+    let team = TeamId::try_from(team).expect("team id");
+    let user = UserId::try_from(user).expect("user id");
+    TenantCtx::new(env, tenant)
+        .with_team(Some(team))
+        .with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.purge").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.wait".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope(conversation: &str) -> ReplyScope {
+    ReplyScope {
+        conversation: conversation.to_string(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+fn wait(store: &InMemorySessionStore, ctx: &TenantCtx, key: &str, conversations: &[&str]) {
+    let waits: Vec<WaitSpec> = conversations
+        .iter()
+        .map(|conversation| WaitSpec::new(*conversation, scope(conversation)))
+        .collect();
+    store
+        .register_waits(
+            ctx,
+            ctx.user_id.as_ref().expect("user"),
+            &SessionKey::new(key),
+            data(ctx),
+            &waits,
+        )
+        .expect("register waits");
+}
+
+fn tenant_sessions(store: &InMemorySessionStore, tenant: &str) -> usize {
+    let filter = SessionFilter::tenant(
+        EnvId::try_from("dev").expect("env id"),
+        TenantId::try_from(tenant).expect("tenant id"),
+    );
+    store
+        .list_sessions(&filter, None, 100)
+        .expect("list")
+        .sessions
+        .len()
+}
+
+#[test]
+fn purge_tenant_removes_sessions_and_indices_of_that_tenant_only() {
+    let store = InMemorySessionStore::new();
+    let doomed = ctx("tenant-gone", "team-1", "user-1");
+    let doomed_team = ctx("tenant-gone", "team-2", "user-2");
+    let kept = ctx("tenant-kept", "team-1", "user-1");
+    wait(&store, &doomed, "gone-1", &["slack:a", "email:a"]);
+    wait(&store, &doomed_team, "gone-2", &["slack:b"]);
+    store
+        .create_session(&doomed, data(&doomed))
+        .expect("create");
+    wait(&store, &kept, "kept-1", &["slack:a"]);
+
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-gone").expect("tenant id");
+    let report = store.purge_tenant(&env, &tenant).expect("purge");
+    assert_eq!(
+        report,
+        PurgeReport {
+            sessions: 3,
+            scope_pointers: 3,
+            user_wait_sets: 2,
+        }
+    );
+    assert_eq!(tenant_sessions(&store, "tenant-gone"), 0);
+    assert!(
+        store
+            .find_wait_by_scope(
+                &doomed,
+                doomed.user_id.as_ref().expect("user"),
+                &scope("slack:a")
+            )
+            .expect("find")
+            .is_none()
+    );
+    assert_eq!(
+        store
+            .find_wait_by_scope(
+                &kept,
+                kept.user_id.as_ref().expect("user"),
+                &scope("slack:a")
+            )
+            .expect("find"),
+        Some(SessionKey::new("kept-1"))
+    );
+
+    // Re-running an already completed purge is a no-op.
+    assert_eq!(
+        store.purge_tenant(&env, &tenant).expect("purge again"),
+        PurgeReport::default()
+    );
+}
+
+#[test]
+fn purge_user_spans_teams_and_spares_other_users() {
+    let store = InMemorySessionStore::new();
+    let team_one = ctx("tenant-a", "team-1", "user-erase");
+    let team_two = ctx("tenant-a", "team-2", "user-erase");
+    let neighbour = ctx("tenant-a", "team-1", "user-keep");
+    wait(&store, &team_one, "erase-1", &["slack:1"]);
+    wait(&store, &team_two, "erase-2", &["slack:2"]);
+    store
+        .create_session(&team_one, data(&team_one))
+        .expect("create");
+    wait(&store, &neighbour, "keep-1", &["slack:1"]);
+
+    let report = store
+        .purge_user(
+            &EnvId::try_from("dev").expect("env id"),
+            &TenantId::try_from("tenant-a").expect("tenant id"),
+            &UserId::try_from("user-erase").expect("user id"),
+        )
+        .expect("purge user");
+    assert_eq!(report.sessions, 3);
+    assert_eq!(report.scope_pointers, 2);
+    assert_eq!(report.user_wait_sets, 2);
+    assert_eq!(tenant_sessions(&store, "tenant-a"), 1);
+    assert!(
+        store
+            .get_session(&SessionKey::new("keep-1"))
+            .expect("get")
+            .is_some()
+    );
+}
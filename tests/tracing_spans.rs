@@ -0,0 +1,179 @@
+#![cfg(feature = "tracing")]
+
+use greentic_session::ReplyScope;
+use greentic_session::inmemory::InMemorySessionStore;
+use greentic_session::store::SessionStore;
+use greentic_session::tracing::TracedSessionStore;
+use greentic_types::{
+    EnvId, FlowId, SessionCursor, SessionData, SessionKey, TeamId, TenantCtx, TenantId, UserId,
+};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn output(&self) -> String {
+        String::from_utf8(self.0.lock().expect("lock").clone()).expect("utf8 output")
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().expect("lock").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Capture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn traced<T>(run: impl FnOnce() -> T) -> String {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(capture.clone())
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .finish();
+    tracing::subscriber::with_default(subscriber, run);
+    capture.output()
+}
+
+fn ctx(team: &str) -> TenantCtx {
+    let env = EnvId::try_from("dev").expect("env id");
+    let tenant = TenantId::try_from("tenant-traced").expect("tenant id");
+    let team = TeamId::try_from(team).expect("team id");
+    let user = UserId::try_from("user-secret").expect("user id");
+    TenantCtx::new(env, tenant)
+        .with_team(Some(team))
+        .with_user(Some(user))
+}
+
+fn data(ctx: &TenantCtx) -> SessionData {
+    SessionData {
+        tenant_ctx: ctx.clone(),
+        flow_id: FlowId::try_from("flow.traced").expect("flow id"),
+        pack_id: None,
+        cursor: SessionCursor::new("node.start".to_string()),
+        context_json: "{}".into(),
+    }
+}
+
+fn scope() -> ReplyScope {
+    ReplyScope {
+        conversation: "chat-1".into(),
+        thread: None,
+        reply_to: None,
+        correlation: None,
+    }
+}
+
+#[test]
+fn spans_carry_tenant_scope_and_key_without_raw_user() {
+    let store = TracedSessionStore::new(InMemorySessionStore::new());
+    let ctx = ctx("team-1");
+    let user = ctx.user_id.clone().expect("user");
+    let key = SessionKey::new("traced-wait");
+
+    let output = traced(|| {
+        store
+            .register_wait(&ctx, &user, &scope(), &key, data(&ctx), None)
+            .expect("register");
+        store
+            .find_wait_by_scope(&ctx, &user, &scope())
+            .expect("lookup");
+    });
+
+    for operation in ["register_wait", "find_wait_by_scope"] {
+        assert!(
+            output.contains(&format!("operation=\"{operation}\"")),
+            "{output}"
+        );
+    }
+    assert!(output.contains("env=\"dev\""), "{output}");
+    assert!(output.contains("tenant=\"tenant-traced\""), "{output}");
+    assert!(output.contains("team=\"team-1\""), "{output}");
+    assert!(output.contains("user_hash="), "{output}");
+    assert!(
+        output.contains(&format!("scope_hash=\"{}\"", scope().scope_hash())),
+        "{output}"
+    );
+    assert!(output.contains("session_key=\"traced-wait\""), "{output}");
+    assert!(!output.contains("user-secret"), "{output}");
+}
+
+fn user_hash(output: &str) -> &str {
+    let start = output.find("user_hash=").expect("user hash recorded") + "user_hash=".len();
+    output[start..]
+        .split_whitespace()
+        .next()
+        .expect("hash value")
+}
+
+#[test]
+fn user_hashes_are_keyed() {
+    let ctx = ctx("team-1");
+    let lookup = |store: &TracedSessionStore<InMemorySessionStore>| {
+        let user = ctx.user_id.clone().expect("user");
+        traced(|| {
+            store
+                .find_wait_by_scope(&ctx, &user, &scope())
+                .expect("lookup");
+        })
+    };
+
+    let first = lookup(&TracedSessionStore::with_user_hash_key(
+        InMemorySessionStore::new(),
+        "shared-secret",
+    ));
+    let replica = lookup(&TracedSessionStore::with_user_hash_key(
+        InMemorySessionStore::new(),
+        "shared-secret",
+    ));
+    let other = lookup(&TracedSessionStore::with_user_hash_key(
+        InMemorySessionStore::new(),
+        "other-secret",
+    ));
+    let random = lookup(&TracedSessionStore::new(InMemorySessionStore::new()));
+
+    assert_eq!(user_hash(&first), user_hash(&replica));
+    assert_ne!(user_hash(&first), user_hash(&other));
+    assert_ne!(user_hash(&first), user_hash(&random));
+}
+
+#[test]
+fn fence_rejections_emit_events() {
+    let store = TracedSessionStore::new(InMemorySessionStore::new());
+    let owner = ctx("team-1");
+    let intruder = ctx("team-2");
+    let key = store.create_session(&owner, data(&owner)).expect("create");
+
+    let output = traced(|| {
+        store
+            .update_session(&key, data(&intruder))
+            .expect_err("team change is rejected");
+    });
+
+    assert!(output.contains("greentic_session::fence"), "{output}");
+    assert!(
+        output.contains("tenant fence rejected session store call"),
+        "{output}"
+    );
+    assert!(output.contains("expected_team=\"team-1\""), "{output}");
+    assert!(output.contains("provided_team=\"team-2\""), "{output}");
+    assert!(output.contains("session store call failed"), "{output}");
+    assert!(output.contains("operation=\"update_session\""), "{output}");
+}